@@ -0,0 +1,62 @@
+//! A greedy cell mover written against the library,
+//! with a second thread watching its progress through the shared counters.
+//!
+//! cargo run --example custom_optimizer -- <input> <output>
+
+use anyhow::{anyhow, Result};
+use cell_move_router::Chip;
+use std::{
+    env,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+    let infile = args.next().ok_or_else(|| anyhow!("Missing input file"))?;
+    let outfile = args.next().ok_or_else(|| anyhow!("Missing output file"))?;
+
+    let mut chip = Chip::default();
+    chip.read_file(&infile)?;
+
+    // The observer only holds the counters, not the chip.
+    let counters = Arc::clone(&chip.counters);
+    let observer = thread::spawn(move || {
+        while Arc::strong_count(&counters) > 1 {
+            let metrics = counters.metrics(0);
+            eprintln!("moves accepted: {}", metrics.moves_accepted);
+            thread::sleep(Duration::from_millis(100));
+        }
+    });
+
+    // Move every cell to its cheapest candidate if that beats staying.
+    let mut density = chip.density_map();
+    for idx in 0..chip.cells.len() {
+        let current = chip.cells[idx].position;
+        let stay = chip.move_cost(idx, current, &density);
+        let best = chip
+            .candidates(idx)
+            .into_iter()
+            .map(|target| (chip.move_cost(idx, target, &density), target))
+            .min_by_key(|&(cost, _)| cost);
+
+        if let Some((cost, target)) = best {
+            if cost < stay {
+                chip.move_cell(idx, target)?;
+                density.move_weight(current, target, chip.cell_weight(idx));
+            }
+        }
+    }
+
+    // Moving cells disconnects nets, reconnect them and keep the result only if legal.
+    chip.reroute_nets(Instant::now(), Duration::from_secs(60))?;
+    chip.ensure_legal()?;
+    println!("{:?}", chip.metrics());
+
+    chip.write_file(&outfile)?;
+    drop(chip);
+    observer.join().map_err(|_| anyhow!("Observer panicked"))?;
+
+    Ok(())
+}
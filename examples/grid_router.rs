@@ -0,0 +1,43 @@
+//! Routes nets on a capacity grid without any cells, using the router on its own.
+//!
+//! cargo run --example grid_router
+
+use anyhow::Result;
+use cell_move_router::{Direction, GlobalRouter, GridLayer, GridNet, Pair, Point};
+use std::time::Duration;
+
+fn main() -> Result<()> {
+    let dim = Pair(4, 4);
+    let layer = |direction, supply| GridLayer {
+        direction,
+        capacity: vec![supply; dim.size()],
+    };
+    let layers = vec![
+        layer(Direction::Horizontal, 2),
+        layer(Direction::Vertical, 2),
+        layer(Direction::Horizontal, 1),
+    ];
+    let nets = vec![
+        GridNet {
+            pins: vec![Point(1, 1, 1), Point(4, 4, 1)],
+            min_layer: 0,
+        },
+        GridNet {
+            pins: vec![Point(1, 4, 1), Point(4, 1, 1), Point(2, 2, 1)],
+            min_layer: 1,
+        },
+    ];
+
+    let mut router = GlobalRouter::new(dim, layers, &nets)?;
+    router.route(Duration::from_secs(10))?;
+
+    for id in 0..nets.len() {
+        println!("Net {}:", id + 1);
+        for route in router.routes(id) {
+            println!("  {}", route);
+        }
+    }
+    println!("Overflow: {}", router.overflow());
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+//! Reroutes a single net of an input file and prints its new segments.
+//!
+//! cargo run --example route_one_net -- <input> <net name, e.g. N1>
+
+use anyhow::{anyhow, Result};
+use cell_move_router::{Chip, FactoryID, Net, Search};
+use std::env;
+
+fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+    let infile = args.next().ok_or_else(|| anyhow!("Missing input file"))?;
+    let name = args.next().unwrap_or_else(|| "N1".to_string());
+
+    let mut chip = Chip::default();
+    chip.read_file(&infile)?;
+    chip.search = Search::AStar;
+
+    let id = Net::from_str(&name)?;
+    println!("Topology length: {}", chip.steiner_topology(id).length());
+
+    // Free the net's own demand before searching, so it may reuse its GGrids.
+    let old = chip.rip_up(id)?;
+    match chip.route_net(id, None) {
+        Some(routes) => chip.commit_routes(id, routes)?,
+        None => {
+            eprintln!("Cannot route {}, keeping the old segments", name);
+            chip.commit_routes(id, old)?;
+        }
+    }
+
+    print!("{}", chip.nets[id]);
+    Ok(())
+}
@@ -0,0 +1,50 @@
+//! Reports violations, overflow and wirelength of an input file.
+//!
+//! cargo run --example score_solution -- <input>
+
+use anyhow::{anyhow, Result};
+use cell_move_router::Chip;
+use std::env;
+
+fn main() -> Result<()> {
+    let infile = env::args()
+        .nth(1)
+        .ok_or_else(|| anyhow!("Missing input file"))?;
+
+    let mut chip = Chip::default();
+    chip.read_file(&infile)?;
+    println!("{}", chip.provenance);
+
+    let report = chip.diagnose();
+    println!("{}", report);
+
+    let view = chip.view();
+    println!("Legal: {}", report.errors == 0);
+    println!("Overflow: {}", view.overflow());
+    println!("Wirelength: {}", view.wirelength());
+
+    let score = chip.score();
+    for (id, layer) in score.layers.iter().enumerate() {
+        println!(
+            "Layer {}: horizontal {}, vertical {}",
+            id + 1,
+            layer.horizontal,
+            layer.vertical
+        );
+    }
+    for (id, vias) in score.vias.iter().enumerate() {
+        println!("Vias {}-{}: {}", id + 1, id + 2, vias);
+    }
+    println!("Bends: {}", score.bends);
+    for (id, net) in score.nets.iter().enumerate() {
+        println!(
+            "Net N{}: wirelength {}, vias {}, bends {}",
+            id + 1,
+            net.wirelength,
+            net.vias,
+            net.bends
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use crate::{args::Args, chip::Chip, snapshot::Snapshot};
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// The result of running one strategy of an A/B comparison.
+#[derive(Clone, Debug, Default)]
+pub struct AbOutcome {
+    /// the options the strategy was given
+    pub strategy: String,
+    /// whether the strategy failed and fell back
+    pub failed: bool,
+    /// quality of the result, see `Chip::quality`
+    pub quality: (bool, usize),
+    /// the result
+    pub snapshot: Snapshot,
+}
+
+impl AbOutcome {
+    /// Checks if this result is better than `other`, see `Chip::quality`.
+    pub fn beats(&self, other: &AbOutcome) -> bool {
+        self.quality < other.quality
+    }
+
+    /// The best of `outcomes`, the first one on ties.
+    pub fn best(outcomes: &[AbOutcome]) -> Option<&AbOutcome> {
+        outcomes
+            .iter()
+            .reduce(|best, outcome| if outcome.beats(best) { outcome } else { best })
+    }
+}
+
+impl Chip {
+    /// Runs every strategy from the current state, splitting the time limit in `args` evenly,
+    /// and keeps the best result, see `AbOutcome::beats`.
+    /// Every strategy runs on a fresh copy of the chip, see `variant`,
+    /// so options it leaves out are defaults and no router state carries over between them.
+    /// Returns the outcomes of all strategies in order.
+    pub fn run_ab(&mut self, args: &Args, strategies: &[(String, Args)]) -> Result<Vec<AbOutcome>> {
+        if strategies.is_empty() {
+            return Err(anyhow!("No strategy to compare"));
+        }
+
+        let share = Self::duration(args) / strategies.len() as u32;
+
+        let mut outcomes = Vec::with_capacity(strategies.len());
+        for (strategy, strategy_args) in strategies.iter() {
+            let arm = Args {
+                sec: Some(share.as_secs().max(1) as usize),
+                min: None,
+                hr: None,
+                autosave: None,
+                ..strategy_args.clone()
+            };
+            let mut chip = self.variant(&arm)?;
+            let failed = match chip.run(&arm) {
+                Ok(_) => false,
+                Err(err) => {
+                    eprintln!("Strategy \"{}\" failed: {}", strategy, err);
+                    chip.rollback()?;
+                    true
+                }
+            };
+
+            outcomes.push(AbOutcome {
+                strategy: strategy.clone(),
+                failed,
+                quality: chip.quality(),
+                snapshot: chip.snapshot(),
+            });
+        }
+
+        if let Some(best) = AbOutcome::best(&outcomes) {
+            self.restore(&best.snapshot)?;
+        }
+
+        Ok(outcomes)
+    }
+}
+
+impl Display for AbOutcome {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "Strategy \"{}\": {}, wirelength {}{}",
+            self.strategy,
+            if self.quality.0 { "illegal" } else { "legal" },
+            self.quality.1,
+            if self.failed { " (fell back)" } else { "" }
+        )
+    }
+}
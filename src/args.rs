@@ -1,3 +1,12 @@
+#[cfg(feature = "stress")]
+use crate::stress::Stress;
+use crate::{
+    force::Mover,
+    output::OutputVersion,
+    pipeline::Pipeline,
+    router::{NetOrder, Search, TieBreak},
+    rules::PinDemand,
+};
 use clap::Clap;
 
 #[derive(Clap, Clone, Default, Debug)]
@@ -22,11 +31,214 @@ pub struct Args {
     #[clap(short, long)]
     pub hr: Option<usize>,
 
-    // move cells
+    // move cells, alternating with rerouting their nets
     #[clap(short, long)]
     pub cell: bool,
 
     // route nets
     #[clap(short, long)]
     pub net: bool,
+
+    // maximum manhattan distance (in GGrids) a cell may move from its original position
+    #[clap(long)]
+    pub displacement: Option<usize>,
+
+    // highest cell density (occupancy over capacity) of a GGrid before moves are penalized
+    #[clap(long)]
+    pub density: Option<f64>,
+
+    // whether pins consume routing demand: consume or ignore
+    #[clap(long)]
+    pub pin_demand: Option<PinDemand>,
+
+    // refuse to write an illegal solution, roll back to the last legal one instead;
+    // fails without writing anything if there is none, as when the input itself is illegal
+    #[clap(long)]
+    pub strict_legal: bool,
+
+    // let routes reach pins on GGrids without supply, otherwise such nets are not rerouted
+    #[clap(long)]
+    pub pin_access_exempt: bool,
+
+    // route nets on a 2D grid first, then assign layers
+    #[clap(long)]
+    pub two_phase: bool,
+
+    // search used by the maze router: bfs, astar or cost
+    #[clap(long)]
+    pub search: Option<Search>,
+
+    // tie-breaking rules separated by commas, earlier ones first:
+    // lower-layer, fewer-turns and straight for searches, nearest for cell moves
+    #[clap(long)]
+    pub tie_break: Option<TieBreak>,
+
+    // cost of a via in thousandths of a planar step, for the cost search and negotiation
+    #[clap(long)]
+    pub via_cost: Option<usize>,
+
+    // cost of a change of direction in thousandths of a planar step, 0 by default
+    #[clap(long)]
+    pub bend_cost: Option<usize>,
+
+    // order nets are rerouted in: input, hpwl, pins, congestion or difficulty
+    #[clap(long)]
+    pub net_order: Option<NetOrder>,
+
+    // write a JSON breakdown of the final score to this file
+    #[clap(long)]
+    pub stats: Option<String>,
+
+    // report how far the initial and final wirelength are from their lower bounds
+    #[clap(long)]
+    pub gap_report: bool,
+
+    // report the nets hardest to route before optimizing
+    #[clap(long)]
+    pub difficulty_report: bool,
+
+    // report how many cells are movable, fixed and moved, and why candidate positions were skipped
+    #[clap(long)]
+    pub placement_report: bool,
+
+    // most errors and warnings reported at once, 20 by default
+    #[clap(long)]
+    pub max_diagnostics: Option<usize>,
+
+    // optimize this many more times with perturbed net and move orders, keeping the best result
+    #[clap(long)]
+    pub restarts: Option<usize>,
+
+    // seed of the perturbations of restarts
+    #[clap(long)]
+    pub seed: Option<u64>,
+
+    // evolve this many placement variants in parallel, each mutated by a different mover, keeping the best
+    #[clap(long)]
+    pub population: Option<usize>,
+
+    // number of worker threads, all cores by default
+    #[clap(long)]
+    pub threads: Option<usize>,
+
+    // save the latest legal state to the output file every this many minutes
+    #[clap(long)]
+    pub autosave: Option<u64>,
+
+    // output format for older evaluators, v1 or v2 (default); autosaves are always written as v2
+    #[clap(long)]
+    pub output_version: Option<OutputVersion>,
+
+    // pause workers whose average gain over recent iterations drops below this
+    #[clap(long)]
+    pub min_gain: Option<f64>,
+
+    // spread co-located conflicting cells before routing
+    #[clap(long)]
+    pub spread: bool,
+
+    // with --cell, first place the cells globally by recursively dividing the chip into quadrants
+    #[clap(long)]
+    pub partition: bool,
+
+    // with --cell, first move every cell to the median of its connected pins
+    #[clap(long)]
+    pub median: bool,
+
+    // with --cell, refine the placement window by window with branch and bound after moving cells
+    #[clap(long)]
+    pub windows: bool,
+
+    // the passes to run instead of the default ones, e.g.
+    // "repair; loop(move(batch=100); reroute(dirty); prune) until time(90%); legalize"
+    #[clap(long)]
+    pub pipeline: Option<Pipeline>,
+
+    // how --cell picks positions: cost for the cheapest candidate, force to follow net and congestion forces,
+    // gain to make the moves with the highest gain first, cluster to move tightly connected cells together,
+    // gravity to move cells to the center of their nets weighted by routed length
+    #[clap(long)]
+    pub mover: Option<Mover>,
+
+    // rank this many of the cheapest candidates of a move by local congestion and pin-layer supply
+    #[clap(long)]
+    pub lookahead: Option<usize>,
+
+    // plan routes on a chip coarsened by this factor first, then refine them
+    #[clap(long)]
+    pub coarse: Option<usize>,
+
+    // route nets with the same pin pattern together on parallel tracks
+    #[clap(long)]
+    pub bus: bool,
+
+    // thousandths of the remaining supply to reserve for nets not routed yet, where they likely pass
+    #[clap(long)]
+    pub reserve: Option<usize>,
+
+    // route nets whose regions don't overlap concurrently
+    #[clap(long)]
+    pub parallel_routing: bool,
+
+    // slide Steiner points of routed nets to shorten them and leave full GGrids
+    #[clap(long)]
+    pub relocate_steiner: bool,
+
+    // route every net first letting GGrids overflow by at most this much,
+    // then negotiate the overflow away
+    #[clap(long)]
+    pub soft_overflow: Option<usize>,
+
+    // give nets of 2 or 3 pins in small boxes their shortest routes while time remains
+    #[cfg(feature = "exact")]
+    #[clap(long)]
+    pub exact: bool,
+
+    // remember which pass created every route segment, for debugging
+    #[clap(long)]
+    pub trace_routes: bool,
+
+    // compare strategies split by '|', each a list of extra options, and write the best
+    #[cfg(feature = "ab")]
+    #[clap(long, allow_hyphen_values = true)]
+    pub ab: Option<String>,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Clap, Clone, Debug)]
+pub enum Command {
+    // only repair the violations of a solution, leaving the rest of it untouched
+    Fix(Fix),
+
+    // score a solution and report its violations, writing the score as JSON to the output
+    Eval(Eval),
+
+    // write a harder variant of the input to the output instead of optimizing it
+    #[cfg(feature = "stress")]
+    Stress(Stress),
+}
+
+/// Options of the `fix` subcommand, given after the input and output:
+/// `cell_move_router -i case.txt -o fixed.txt fix -s solution.txt`.
+#[derive(Clap, Clone, Debug, Default)]
+pub struct Fix {
+    // solution file to repair
+    #[clap(short, long)]
+    pub solution: String,
+}
+
+/// Options of the `eval` subcommand, given after the input and output:
+/// `cell_move_router -i case.txt -o out.txt eval -s solution.txt --json score.json`.
+/// The output file is left alone.
+#[derive(Clap, Clone, Debug, Default)]
+pub struct Eval {
+    // solution file to score
+    #[clap(short, long)]
+    pub solution: String,
+
+    // file to write the score to as JSON
+    #[clap(long)]
+    pub json: Option<String>,
 }
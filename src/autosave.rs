@@ -0,0 +1,89 @@
+use crate::snapshot::Snapshot;
+use std::{
+    fs,
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Saves the latest legal checkpoint to disk at a fixed interval in a background thread,
+/// so a crash late in a long run loses little progress.
+/// Optimization only hands over a snapshot, rendering and writing happen in the thread.
+/// Every save goes to a temporary file that is renamed over the target,
+/// so the target always holds a complete solution.
+#[derive(Debug)]
+pub struct Autosave {
+    /// the latest snapshot handed over, shared with the thread
+    latest: Arc<Mutex<Option<Arc<Snapshot>>>>,
+    /// dropped to stop the thread
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Autosave {
+    /// Starts saving to `filename` every `interval`.
+    pub fn start(filename: &str, interval: Duration) -> Self {
+        let latest: Arc<Mutex<Option<Arc<Snapshot>>>> = Arc::default();
+        let (stop, stopped) = mpsc::channel();
+
+        let shared = Arc::clone(&latest);
+        let filename = filename.to_string();
+        let handle = thread::spawn(move || {
+            let mut saved: Option<Arc<Snapshot>> = None;
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let snapshot = match shared.lock() {
+                    Ok(latest) => latest.clone(),
+                    Err(_) => return,
+                };
+                let snapshot = match snapshot {
+                    Some(snapshot) => snapshot,
+                    None => continue,
+                };
+                if saved
+                    .as_ref()
+                    .is_some_and(|saved| Arc::ptr_eq(saved, &snapshot))
+                {
+                    continue;
+                }
+
+                match Self::save(&filename, &snapshot) {
+                    Ok(()) => saved = Some(snapshot),
+                    Err(err) => eprintln!("Autosave to {} failed: {}", filename, err),
+                }
+            }
+        });
+
+        Self {
+            latest,
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+
+    /// Hands a snapshot over to be saved next, replacing the one not saved yet.
+    pub fn publish(&self, snapshot: Snapshot) {
+        if let Ok(mut latest) = self.latest.lock() {
+            *latest = Some(Arc::new(snapshot));
+        }
+    }
+
+    /// Writes a snapshot to a temporary file, then renames it to `filename`.
+    fn save(filename: &str, snapshot: &Snapshot) -> std::io::Result<()> {
+        let temp = format!("{}.autosave", filename);
+        fs::write(&temp, snapshot.to_string())?;
+        fs::rename(&temp, filename)
+    }
+}
+
+impl Drop for Autosave {
+    /// Stops the thread, waiting for a save in progress to finish.
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
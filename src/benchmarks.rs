@@ -0,0 +1,34 @@
+use crate::chip::Chip;
+use anyhow::{anyhow, Result};
+
+/// Tiny hand-checked inputs bundled into the library,
+/// so tests and examples don't depend on the official contest cases.
+pub const BENCHMARKS: &[(&str, &str)] = &[
+    // the 3x3 sample of the contest format, routed and legal
+    ("sample", include_str!("../benchmarks/sample.txt")),
+    // the sample without any routes
+    ("unrouted", include_str!("../benchmarks/unrouted.txt")),
+    // the sample with a pin offset section
+    ("pin_offsets", include_str!("../benchmarks/pin_offsets.txt")),
+    // the sample with layers named metal1 to metal3
+    (
+        "named_layers",
+        include_str!("../benchmarks/named_layers.txt"),
+    ),
+    // the sample with a supply of 1 on every GGrid, overflowed
+    ("congested", include_str!("../benchmarks/congested.txt")),
+];
+
+impl Chip {
+    /// Reads a bundled benchmark by name, see `BENCHMARKS`.
+    pub fn from_example(name: &str) -> Result<Self> {
+        let (_, content) = BENCHMARKS
+            .iter()
+            .find(|(benchmark, _)| *benchmark == name)
+            .ok_or_else(|| anyhow!("Unknown benchmark: {}", name))?;
+
+        let mut chip = Self::default();
+        chip.read_str(content)?;
+        Ok(chip)
+    }
+}
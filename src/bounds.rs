@@ -0,0 +1,168 @@
+use crate::{
+    chip::Chip,
+    components::{Direction, Net, Pair, Point},
+};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// How far a net's routes are from the shortest they could possibly be.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct NetGap {
+    /// lower bound of the net's wirelength for the placement at the time,
+    /// see `Chip::lower_bound`
+    pub bound: usize,
+    /// wirelength of the net's routes
+    pub length: usize,
+}
+
+/// Wirelength of the initial and final routes against their lower bounds,
+/// telling whether more tuning can still pay off on a design.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GapReport {
+    /// gap of every net before optimizing, by net id
+    pub initial: Vec<NetGap>,
+    /// gap of every net now, by net id
+    pub current: Vec<NetGap>,
+    /// most nets listed by `Display`
+    pub limit: usize,
+}
+
+impl Chip {
+    /// A lower bound of the wirelength of a net, whatever its routes:
+    /// the GGrids of any connected route are at least its planar positions
+    /// plus its layers minus one, where the positions span the pins' bounding box
+    /// and the layers span the pins' layers and, if the net runs planar,
+    /// a layer of every direction it needs at or above its min layer.
+    /// GGrids only reached by vias are left out if the rules don't count them.
+    pub fn lower_bound(&self, net: &Net, pin_points: &[Point<usize>]) -> usize {
+        let points: Vec<_> = net.pins.iter().map(|&pin| pin_points[pin]).collect();
+        let first = match points.first() {
+            Some(&first) => first,
+            None => return 0,
+        };
+        if points.iter().all(|&point| point == first) {
+            return 0;
+        }
+
+        let flat: Vec<_> = points.iter().map(|point| point.flatten()).collect();
+        let span = |key: fn(&Pair<usize>) -> usize| {
+            let lo = flat.iter().map(key).min().unwrap_or(0);
+            let hi = flat.iter().map(key).max().unwrap_or(0);
+            hi - lo
+        };
+        let (rows, cols) = (span(Pair::x), span(Pair::y));
+        let positions = rows + cols + 1;
+        if !self.rules.count_vias() {
+            return if rows + cols > 0 { positions } else { 0 };
+        }
+
+        let lo = points.iter().map(|point| point.lay()).min().unwrap_or(1);
+        let hi = points.iter().map(|point| point.lay()).max().unwrap_or(1);
+        let needs = |direction: Direction, moves: bool| {
+            move |low: usize, high: usize| {
+                !moves
+                    || self.layers.iter().any(|layer| {
+                        layer.direction == direction
+                            && layer.id >= net.min_layer
+                            && (low..=high).contains(&(layer.id + 1))
+                    })
+            }
+        };
+        let horizontal = needs(Direction::Horizontal, cols > 0);
+        let vertical = needs(Direction::Vertical, rows > 0);
+
+        // The fewest layers covering the pins and a layer of every direction needed.
+        let layers = (1..=lo)
+            .flat_map(|low| (hi..=self.layers.len()).map(move |high| (low, high)))
+            .filter(|&(low, high)| horizontal(low, high) && vertical(low, high))
+            .map(|(low, high)| high - low + 1)
+            .min()
+            .unwrap_or(hi - lo + 1);
+
+        positions + layers - 1
+    }
+
+    /// The gap of every net, by net id, see `NetGap`.
+    pub fn net_gaps(&self) -> Vec<NetGap> {
+        let pin_points = self.pin_points();
+        self.nets
+            .iter()
+            .map(|net| NetGap {
+                bound: self.lower_bound(net, &pin_points),
+                length: self.net_length(net),
+            })
+            .collect()
+    }
+
+    /// Compares the gaps of the nets now with the `initial` ones,
+    /// taken with `net_gaps` before optimizing.
+    pub fn gap_report(&self, initial: Vec<NetGap>) -> GapReport {
+        use crate::consts::MAX_DIAGNOSTICS;
+
+        GapReport {
+            initial,
+            current: self.net_gaps(),
+            limit: self.max_diagnostics.unwrap_or(MAX_DIAGNOSTICS),
+        }
+    }
+}
+
+impl GapReport {
+    /// Sums of the lower bounds and the wirelength of all nets.
+    pub fn totals(gaps: &[NetGap]) -> NetGap {
+        NetGap {
+            bound: gaps.iter().map(|gap| gap.bound).sum(),
+            length: gaps.iter().map(|gap| gap.length).sum(),
+        }
+    }
+
+    /// Percent the wirelength is above the lower bound.
+    fn percent(gap: NetGap) -> f64 {
+        if gap.bound == 0 {
+            0.0
+        } else {
+            100.0 * (gap.length as f64 - gap.bound as f64) / gap.bound as f64
+        }
+    }
+}
+
+impl Display for GapReport {
+    /// The totals, followed by the nets furthest from their bounds, one per line.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let (initial, current) = (Self::totals(&self.initial), Self::totals(&self.current));
+        write!(
+            f,
+            "Initial {} of lower bound {} (+{:.1}%), final {} of lower bound {} (+{:.1}%)",
+            initial.length,
+            initial.bound,
+            Self::percent(initial),
+            current.length,
+            current.bound,
+            Self::percent(current)
+        )?;
+
+        let mut gaps: Vec<_> = self
+            .current
+            .iter()
+            .enumerate()
+            .filter(|(_, gap)| gap.length > gap.bound)
+            .collect();
+        gaps.sort_by_key(|&(id, gap)| (std::cmp::Reverse(gap.length - gap.bound), id));
+        for &(id, gap) in gaps.iter().take(self.limit) {
+            let before = self.initial.get(id).copied().unwrap_or_default();
+            write!(
+                f,
+                "\nN{}: initial {} of {}, final {} of {} (+{})",
+                id + 1,
+                before.length,
+                before.bound,
+                gap.length,
+                gap.bound,
+                gap.length - gap.bound
+            )?;
+        }
+        if gaps.len() > self.limit {
+            write!(f, "\n... {} more not shown", gaps.len() - self.limit)?;
+        }
+        Ok(())
+    }
+}
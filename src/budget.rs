@@ -0,0 +1,114 @@
+use crate::{chip::Chip, components::Pair};
+use anyhow::Result;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+impl Chip {
+    /// Number of cells counted as moved once `moves` are made,
+    /// a cell moved back to its origin frees its slot if the rules say so.
+    /// Later moves of the same cell replace earlier ones.
+    pub fn moved_after(&self, moves: &[(usize, Pair<usize>)]) -> usize {
+        let return_to_origin = self.rules.return_to_origin();
+        let targets: HashMap<_, _> = moves.iter().copied().collect();
+
+        targets
+            .iter()
+            .filter_map(|(&idx, &target)| self.cells.get(idx).map(|cell| (cell, target)))
+            .fold(self.already_moved, |moved, (cell, target)| {
+                let after = !return_to_origin || target != cell.origin;
+                match (cell.moved, after) {
+                    (false, true) => moved + 1,
+                    (true, false) => moved.saturating_sub(1),
+                    _ => moved,
+                }
+            })
+    }
+
+    /// Makes several moves speculatively: they are only made if the budget has a slot
+    /// for every cell they move, all at once or none, see `moved_after`,
+    /// and every target has the supply for the cell's blockages, see `admits_blockages`.
+    /// Moves freeing a slot are made first, so the budget is never exceeded in between.
+    /// Only the nets of the moved cells are rerouted, see `reroute_nets_in`,
+    /// and everything is rolled back unless all of them could be and the `quality` improved;
+    /// other nets, dirty or not, are left alone.
+    /// Returns whether the moves were kept.
+    pub fn speculate_moves(&mut self, moves: &[(usize, Pair<usize>)]) -> Result<bool> {
+        if moves.is_empty() || self.moved_after(moves) > self.max_move {
+            return Ok(false);
+        }
+        if !moves
+            .iter()
+            .all(|&(idx, target)| self.admits_blockages(idx, target))
+        {
+            return Ok(false);
+        }
+
+        let before = self.quality();
+        let snapshot = self.snapshot();
+        let dirty = self.dirty_nets.clone();
+        let mut nets: Vec<_> = moves
+            .iter()
+            .filter(|&&(idx, _)| idx < self.cells.len())
+            .flat_map(|&(idx, _)| self.cell_nets(idx))
+            .collect();
+        nets.sort_unstable();
+        nets.dedup();
+
+        let mut ordered = moves.to_vec();
+        ordered.sort_by_key(|&(idx, target)| {
+            self.cells.get(idx).is_none_or(|cell| target != cell.origin)
+        });
+        for (idx, target) in ordered {
+            if self.move_cell(idx, target).is_err() {
+                self.restore(&snapshot)?;
+                self.dirty_nets = dirty;
+                return Ok(false);
+            }
+        }
+
+        self.reroute_nets_in(nets.clone(), Instant::now(), Duration::MAX)?;
+        let unrouted = nets.iter().any(|id| self.dirty_nets.contains(id));
+        if unrouted || self.quality() >= before {
+            self.restore(&snapshot)?;
+            self.dirty_nets = dirty;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Puts back the moves closest to their origin until no more cells are moved
+    /// than `max_move` allows, marking their nets dirty. Demand is not updated.
+    /// Returns the number of moves put back.
+    pub(crate) fn revert_excess_moves(&mut self) -> usize {
+        if self.already_moved <= self.max_move {
+            return 0;
+        }
+
+        let mut moved: Vec<_> = (0..self.cells.len())
+            .filter(|&idx| self.cells[idx].moved)
+            .collect();
+        moved.sort_by_key(|&idx| {
+            let cell = &self.cells[idx];
+            (cell.origin.distance(&cell.position), idx)
+        });
+        let excess = self.already_moved - self.max_move;
+        for &idx in moved.iter().take(excess) {
+            self.revert_move(idx);
+        }
+        excess.min(moved.len())
+    }
+
+    /// Makes sure no more cells are moved than `max_move` allows,
+    /// putting back the moves closest to their origin and rerouting their nets.
+    /// Returns the number of moves put back.
+    pub fn enforce_move_budget(&mut self) -> Result<usize> {
+        let reverted = self.revert_excess_moves();
+        if reverted > 0 {
+            self.update_demand()?;
+            self.reroute_dirty_nets()?;
+        }
+        Ok(reverted)
+    }
+}
@@ -1,18 +1,34 @@
 use crate::{
     args::Args,
+    autosave::Autosave,
     components::{
-        Blockage, Cell, CellType, Conflict, ConflictType, Direction, FactoryID, Layer, MasterCell,
-        MasterPin, Net, Pair, Route,
+        Cell, CellType, Conflict, FactoryID, Layer, MasterCell, Net, Pair, Point, Provenance, Route,
     },
-    utilities,
+    force::Mover,
+    history::RouteHistory,
+    io,
+    lookahead::CandidateScore,
+    metrics::Counters,
+    output::OutputVersion,
+    placement::Skip,
+    raw::RawDesign,
+    router::{
+        Congestion, Corridor, CostModel, NetOrder, Search, SteinerTable, TieBreak, WeightedCost,
+    },
+    rudy::Reservation,
+    rules::{Iccad2020, RuleSet},
+    schedule::{Schedule, Strategy},
+    snapshot::Snapshot,
+    throttle::Throttle,
+    utilities::{self, BoundsError, ThreadPool},
 };
-use anyhow::{anyhow, Result};
-use rayon::prelude::*;
+use anyhow::{anyhow, Error, Result};
 use std::{
     collections::{HashMap, HashSet},
-    fmt::{Display, Error as FmtError, Formatter, Result as FmtResult},
+    fmt::{Display, Formatter, Result as FmtResult, Write},
     fs,
-    time::{Duration, Instant},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 #[derive(Default, Debug)]
@@ -31,8 +47,100 @@ pub struct Chip {
     pub cells: Vec<Cell>,
     /// all nets
     pub nets: Vec<Net>,
+    /// owning cell and master pin of every pin, indexed by pin id
+    pub pin_owners: Vec<(usize, usize)>,
+    /// net of every pin, indexed by pin id
+    pub pin_nets: Vec<Option<usize>>,
+    /// nets whose pins moved since they were last routed
+    pub dirty_nets: HashSet<usize>,
     /// all conflicts
     pub conflicts: HashMap<usize, HashSet<Conflict>>,
+    /// layer ids indexed by layer names
+    pub layer_ids: HashMap<String, usize>,
+    /// maximum distance a cell may move from its origin
+    pub max_displacement: Option<usize>,
+    /// the highest cell density a GGrid should reach
+    pub target_density: Option<f64>,
+    /// whether routes may pass through the GGrids of a net's own pins regardless of capacity
+    pub pin_access_exempt: bool,
+    /// whether nets are routed in 2D first and assigned to layers afterwards
+    pub two_phase: bool,
+    /// seed perturbing the net and move order of a restart, `None` keeps them as configured
+    pub perturbation: Option<u64>,
+    /// prices the steps of cost-driven searches
+    pub cost_model: Box<dyn CostModel>,
+    /// the search used by the maze router
+    pub search: Search,
+    /// how searches and candidate selection break ties
+    pub tie_break: TieBreak,
+    /// the order nets are rerouted in
+    pub net_order: NetOrder,
+    /// how cells pick the positions they move to
+    pub mover: Mover,
+    /// number of the cheapest candidates of a move ranked by `candidate_score`, `None` ranks by cost alone
+    pub lookahead: Option<usize>,
+    /// ranks candidate positions of cell moves by routability
+    pub candidate_score: Box<dyn CandidateScore>,
+    /// version of the output written by `write_file`
+    pub output_version: OutputVersion,
+    /// whether nets with the same pin pattern are routed on parallel tracks
+    pub bus_routing: bool,
+    /// whether nets with disjoint regions are routed concurrently
+    pub parallel_routing: bool,
+    /// whether Steiner points of routed nets are slid to shorten them, see `relocate_steiner_points`
+    pub steiner_relocation: bool,
+    /// overflow every GGrid may take in the first routing pass, see `soft_route_nets`
+    pub soft_overflow: Option<usize>,
+    /// whether tiny nets are routed exactly after negotiating, see `exact_route_nets`
+    #[cfg(feature = "exact")]
+    pub exact_routing: bool,
+    /// thousandths of the remaining supply reserved for unrouted nets, see `Reservation`
+    pub reserve: Option<usize>,
+    /// supply reserved during a routing pass
+    pub reservation: Option<Reservation>,
+    /// super-GGrids every net is routed in first, planned on a coarse chip, empty if not planned
+    pub corridors: Vec<Corridor>,
+    /// number of GGrids along each side of a super-GGrid for coarse routing, if enabled
+    pub coarse_factor: Option<usize>,
+    /// Steiner topologies of small nets
+    pub steiner_table: SteinerTable,
+    /// threads the chip's parallel work runs on
+    pub threads: ThreadPool,
+    /// live counters of the run, shared with observers
+    pub counters: Arc<Counters>,
+    /// congestion history learned by rip-up iterations, priced by the router
+    pub congestion: Congestion,
+    /// which pass created every route segment, if traced
+    pub history: Option<RouteHistory>,
+    /// pauses work with diminishing returns
+    pub throttle: Option<Throttle>,
+    /// rules used in demand calculation, verification and scoring
+    pub rules: Box<dyn RuleSet>,
+    /// most errors and warnings reported at once, `MAX_DIAGNOSTICS` by default
+    pub max_diagnostics: Option<usize>,
+    /// where the input came from
+    pub provenance: Provenance,
+    /// the state to roll back to when the result is not legal
+    pub fallback: Option<Snapshot>,
+    /// the unmodified input, written when optimization fails
+    pub original: Option<Snapshot>,
+    /// whether a checkpoint is made after every phase, see `phase_checkpoint`
+    pub checkpoints: bool,
+    /// GGrids whose supply the input made negative, clamped to 0
+    pub clamped_supply: Vec<Point<usize>>,
+    /// saves the latest legal checkpoint in the background
+    pub autosave: Option<Autosave>,
+}
+
+/// What `Chip::run` kept and the failures it recovered from, for the caller to report.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RunReport {
+    /// generation of the variant kept by `--population`, see `evolve`
+    pub generation: Option<usize>,
+    /// round of the restart kept by `--restarts`, see `run_restarts`
+    pub restart: Option<usize>,
+    /// failures that didn't stop the run
+    pub failures: Vec<String>,
 }
 
 impl Chip {
@@ -40,432 +148,636 @@ impl Chip {
     /// This function reads the input file and stores it into `self`.
     pub fn read_file(&mut self, filename: &str) -> Result<()> {
         let content: String = fs::read_to_string(filename)?;
-        self.read_str(&content)
+        self.read_str(&content)?;
+        self.provenance.path = Some(filename.to_string());
+        Ok(())
     }
 
     /// Reads the content of a string into memory
     /// This function reads the input string and stores it into `self`
     pub fn read_str(&mut self, content: &str) -> Result<()> {
-        use utilities::{check_eq, parse_numeric, parse_string};
-
-        let content = &mut content.split_whitespace();
+        let parsed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+        let hash = utilities::fnv1a(content.as_bytes());
+        let size = content.len();
+
+        let raw = RawDesign::parse(content)?;
+        self.compile(&raw)?;
+
+        self.provenance = Provenance {
+            path: None,
+            parsed_at,
+            hash,
+            size,
+            counts: raw.counts(),
+        };
 
-        // MaxCellMove <maxMoveCount>
-        let keyword = parse_string(content)?;
-        check_eq(keyword, "MaxCellMove")?;
-        let max_move: usize = parse_numeric(content)?;
-        self.max_move = max_move;
+        Ok(())
+    }
 
-        // GGridBoundaryIdx <rowBeginIdx> <colBeginIdx> <rowEndIdx> <colEndIdx>
-        let keyword = parse_string(content)?;
-        check_eq(keyword, "GGridBoundaryIdx")?;
+    pub(crate) fn duration(args: &Args) -> Duration {
+        use crate::consts::*;
 
-        let row_beg: usize = parse_numeric(content)?;
-        let col_beg: usize = parse_numeric(content)?;
+        // By default duration is equal to 1 hr
+        match args {
+            Args {
+                sec: Some(sec),
+                min: None,
+                hr: None,
+                ..
+            } => Duration::from_secs(*sec as u64),
+            Args {
+                sec: None,
+                min: Some(min),
+                hr: None,
+                ..
+            } => Duration::from_secs(SECS_PER_MIN * *min as u64),
+            Args {
+                sec: None,
+                min: None,
+                hr: Some(hr),
+                ..
+            } => Duration::from_secs(SECS_PER_HR * *hr as u64),
+            _ => Duration::from_secs(SECS_PER_HR),
+        }
+    }
 
-        check_eq(row_beg, 1)?;
-        check_eq(col_beg, 1)?;
+    pub(crate) fn check_time(start: Instant, duration: Duration) -> Result<()> {
+        let now = Instant::now();
+        if now - start < duration {
+            Ok(())
+        } else {
+            Err(anyhow!("Time's up!"))
+        }
+    }
 
-        let row_end: usize = parse_numeric(content)?;
-        let col_end: usize = parse_numeric(content)?;
+    /// Number of cells that may still be moved before the budget is spent.
+    pub fn moves_remaining(&self) -> usize {
+        self.max_move.saturating_sub(self.already_moved)
+    }
 
-        let num_rows = row_end;
-        let num_cols = col_end;
+    /// Checks if a cell is allowed to move to `target`, see `move_rejection`.
+    pub fn can_move(&self, idx: usize, target: Pair<usize>) -> bool {
+        self.move_rejection(idx, target).is_none()
+    }
 
-        self.dim = Pair(num_rows, num_cols);
+    /// Why a cell isn't allowed to move to `target`, `None` if it is.
+    /// The target must be on the chip, the cell must be movable,
+    /// the move budget must not be exceeded,
+    /// and the target must be within `max_displacement` of the cell's origin.
+    pub fn move_rejection(&self, idx: usize, target: Pair<usize>) -> Option<Skip> {
+        let cell = match self.cells.get(idx) {
+            Some(cell) => cell,
+            None => return Some(Skip::OffChip),
+        };
 
-        // NumLayer <LayerCount>
-        let keyword = parse_string(content)?;
-        check_eq(keyword, "NumLayer")?;
+        if !self.in_bounds(target.with(1)) {
+            return Some(Skip::OffChip);
+        }
 
-        let num_layers: usize = parse_numeric(content)?;
+        if !matches!(cell.movable, CellType::Movable) {
+            return Some(Skip::Fixed);
+        }
 
-        // Lay <layerName> <Idx> <RoutingDirection> <defaultSupplyOfOneGGrid>
-        for idx in 0..num_layers {
-            let keyword = parse_string(content)?;
-            check_eq(keyword, "Lay")?;
+        if !cell.moved && self.already_moved >= self.max_move {
+            return Some(Skip::Budget);
+        }
 
-            let name = parse_string(content)?;
-            let layer_id: usize = parse_numeric(content)?;
-            let id: usize = Layer::from_str(name)?;
+        match self.max_displacement {
+            Some(limit) if cell.origin.distance(&target) > limit => Some(Skip::Displacement),
+            _ => None,
+        }
+    }
 
-            check_eq(layer_id, id + 1)?;
+    /// Demand footprint of every mastercell, by mastercell id, see `MasterCell::demand_footprint`.
+    pub fn demand_footprints(&self) -> Vec<Vec<usize>> {
+        self.mastercells
+            .iter()
+            .map(|mc| mc.demand_footprint(self.conflicts.get(&mc.id), self.layers.len()))
+            .collect()
+    }
 
-            let dir_str = parse_string(content)?;
-            let direction = if dir_str == "H" {
-                Direction::Horizontal
-            } else {
-                check_eq(dir_str, "V")?;
-                Direction::Vertical
-            };
+    /// Checks if every layer of a GGrid has the supply left for a `footprint`.
+    /// Nothing fits off the chip.
+    pub fn fits_footprint(&self, position: Pair<usize>, footprint: &[usize]) -> bool {
+        if !self.in_bounds(position.with(1)) {
+            return false;
+        }
 
-            let supply: usize = parse_numeric(content)?;
-            let grid_size = self.dim.size();
-            let capacity = vec![supply; grid_size];
-            let dim = self.dim;
+        let Pair(row, col) = position;
+        self.layers.iter().zip(footprint).all(|(layer, &demand)| {
+            demand == 0
+                || match (
+                    layer.get_capacity(row - 1, col - 1),
+                    layer.get_demand(row - 1, col - 1),
+                ) {
+                    (Some(&capacity), Some(&used)) => capacity.saturating_sub(used) >= demand,
+                    _ => false,
+                }
+        })
+    }
 
-            self.layers.push(Layer {
-                id: idx,
-                direction,
-                dim,
-                capacity,
-            });
+    /// Checks if the blockages of a cell's mastercell fit the supply left at `target`
+    /// on their layers, so a move there doesn't overflow a GGrid right away.
+    /// A cell always fits where it is.
+    pub fn admits_blockages(&self, idx: usize, target: Pair<usize>) -> bool {
+        let cell = match self.cells.get(idx) {
+            Some(cell) => cell,
+            None => return false,
+        };
+        if cell.position == target {
+            return true;
         }
 
-        // NumNonDefaultSupplyGGrid <nonDefaultSupplyGGridCount>
-        let keyword = parse_string(content)?;
-        check_eq(keyword, "NumNonDefaultSupplyGGrid")?;
-        let num_non_default: usize = parse_numeric(content)?;
-        for _ in 0..num_non_default {
-            // <rowIdx> <colIdx> <LayIdx> <incrOrDecrValue>
-            let r: usize = parse_numeric(content)?;
-            let c: usize = parse_numeric(content)?;
-            let l: usize = parse_numeric(content)?;
-            let val: isize = parse_numeric(content)?;
+        let mut demand = vec![0; self.layers.len()];
+        for blkg in self.mastercells[cell.mastercell].blkgs() {
+            if let Some(total) = demand.get_mut(blkg.layer) {
+                *total += blkg.demand;
+            }
+        }
+        self.fits_footprint(target, &demand)
+    }
 
-            // - 1 is required in converting from name to id.
-            // It is only written explicitly here  because other parts of the code
-            // do it implicityly in the `FactoryID::from_str` trait method.
-            let (r, c, l) = (r - 1, c - 1, l - 1);
+    /// Generates all positions a cell is allowed to move to.
+    /// Only positions accepted by `can_move` are generated,
+    /// and positions without the supply left for the cell's demand footprint,
+    /// or for the demand of its conflicts with the cells around, see `placement_fits`, are skipped.
+    pub fn candidates(&self, idx: usize) -> Vec<Pair<usize>> {
+        let occupants = self.cells_by_position();
+        self.candidate_checks(idx, &occupants)
+            .into_iter()
+            .filter_map(|(target, skip)| skip.is_none().then_some(target))
+            .collect()
+    }
 
-            let dim = self.dim;
-            let layer_mut = self.get_layer_mut(l).expect("Layer index out of bounds");
+    /// Half-perimeter wirelength of the nets connected to a cell if it were moved to `target`,
+    /// in thousandths of a GGrid.
+    /// Pin offsets inside the GGrids are taken into account, so it breaks ties
+    /// between candidate positions with the same GGrid-level wirelength.
+    pub fn fine_hpwl(&self, idx: usize, target: Pair<usize>) -> usize {
+        use crate::consts::OFFSETS_PER_GGRID;
+
+        let fine_position = |pin: usize| -> Pair<isize> {
+            let (cell_id, mp) = self.pin_owners[pin];
+            let cell = &self.cells[cell_id];
+            let Pair(row, col) = if cell_id == idx {
+                target
+            } else {
+                cell.position
+            };
+            let Pair(drow, dcol) = self.mastercells[cell.mastercell]
+                .pin(mp)
+                .and_then(|pin| pin.offset)
+                .unwrap_or_default();
+            Pair(
+                row as isize * OFFSETS_PER_GGRID + drow,
+                col as isize * OFFSETS_PER_GGRID + dcol,
+            )
+        };
 
-            debug_assert_eq!(dim, layer_mut.dim);
+        let cell_pins: HashSet<_> = match self.cells.get(idx) {
+            Some(cell) => cell.pins.iter().copied().collect(),
+            None => return 0,
+        };
 
-            let cell_capacity = layer_mut
-                .get_capacity_mut(r, c)
-                .expect("Cell index out of bounds");
+        self.nets
+            .iter()
+            .filter(|net| net.pins.iter().any(|pin| cell_pins.contains(pin)))
+            .map(|net| {
+                let positions: Vec<_> = net.pins.iter().map(|&pin| fine_position(pin)).collect();
+                let rows = positions.iter().map(Pair::x);
+                let cols = positions.iter().map(Pair::y);
+                let height = rows.clone().max().unwrap_or(0) - rows.min().unwrap_or(0);
+                let width = cols.clone().max().unwrap_or(0) - cols.min().unwrap_or(0);
+                (height + width) as usize
+            })
+            .sum()
+    }
 
-            *cell_capacity = (*cell_capacity as isize + val) as usize;
+    /// Moves a cell to `target` and updates the move count and demand.
+    /// The nets connected to the cell become dirty until they are rerouted.
+    /// Returns an error if `can_move` rejects the move, fixed cells are never moved.
+    pub fn move_cell(&mut self, idx: usize, target: Pair<usize>) -> Result<()> {
+        self.check_bounds(target.with(1))?;
+
+        if let Some(skip) = self.move_rejection(idx, target) {
+            Counters::bump(&self.counters.moves_rejected);
+            return Err(anyhow!(
+                "Cannot move cell {} to {}: {}",
+                idx + 1,
+                target,
+                skip.name()
+            ));
         }
 
-        // NumMasterCell <masterCellCount>
-        let keyword = parse_string(content)?;
-        check_eq(keyword, "NumMasterCell")?;
-        let num_master_cell: usize = parse_numeric(content)?;
-        // MasterCell <masterCellName> <pinCount> <blockageCount>
-
-        for idx in 0..num_master_cell {
-            let keyword = parse_string(content)?;
-            check_eq(keyword, "MasterCell")?;
+        // Only the demand around the old and the new position changes.
+        let around = [self.cells[idx].position, target];
+        let before = self.cell_demand(idx, &around);
 
-            let name = parse_string(content)?;
-            check_eq(MasterCell::from_str(name)?, idx)?;
+        let return_to_origin = self.rules.return_to_origin();
+        let cell = &mut self.cells[idx];
+        cell.position = target;
 
-            let num_pins: usize = parse_numeric(content)?;
-            let num_blkgs: usize = parse_numeric(content)?;
-
-            let mut pins = HashSet::with_capacity(num_pins);
-            // Pin <pinName> <pinLayer>
-            for _ in 0..num_pins {
-                let keyword = parse_string(content)?;
-                check_eq(keyword, "Pin")?;
-
-                let pin_name = parse_string(content)?;
-                let pin_layer = parse_string(content)?;
+        let moved = !return_to_origin || cell.position != cell.origin;
+        match (cell.moved, moved) {
+            (false, true) => self.already_moved += 1,
+            (true, false) => self.already_moved -= 1,
+            _ => {}
+        }
+        cell.moved = moved;
 
-                let pin_id = MasterPin::from_str(pin_name)?;
-                let layer_id = Layer::from_str(pin_layer)?;
+        self.dirty_nets.extend(self.cell_nets(idx));
 
-                let avail = pins.insert(MasterPin {
-                    id: pin_id,
-                    layer: layer_id,
-                });
+        Counters::bump(&self.counters.moves_accepted);
+        for (point, amount) in before {
+            self.remove_demand(point, amount)?;
+        }
+        for (point, amount) in self.cell_demand(idx, &around) {
+            self.add_demand(point, amount)?;
+        }
+        Ok(())
+    }
 
-                debug_assert!(avail);
+    /// Applies the options in `args` that change the chip's behavior.
+    pub fn configure(&mut self, args: &Args) -> Result<()> {
+        if let Some(threads) = args.threads {
+            if self.threads.current_num_threads() != threads {
+                self.threads = ThreadPool::new(threads)?;
             }
+        }
+        self.max_diagnostics = args.max_diagnostics;
+        self.max_displacement = args.displacement;
+        self.target_density = args.density;
+        self.search = args.search.unwrap_or_default();
+        self.tie_break = args.tie_break.clone().unwrap_or_default();
+        if args.via_cost.is_some() || args.bend_cost.is_some() {
+            let default = WeightedCost::default();
+            self.cost_model = Box::new(WeightedCost {
+                via: args.via_cost.unwrap_or(default.via),
+                bend: args.bend_cost.unwrap_or(default.bend),
+                ..default
+            });
+        }
+        self.net_order = args.net_order.unwrap_or_default();
+        self.mover = args.mover.unwrap_or_default();
+        self.lookahead = args.lookahead;
+        self.output_version = args.output_version.unwrap_or_default();
+        self.bus_routing = args.bus;
+        self.parallel_routing = args.parallel_routing;
+        self.steiner_relocation = args.relocate_steiner;
+        self.soft_overflow = args.soft_overflow;
+        #[cfg(feature = "exact")]
+        {
+            self.exact_routing = args.exact;
+        }
+        self.reserve = args.reserve;
+        self.coarse_factor = args.coarse;
+        if args.trace_routes {
+            self.trace_routes();
+        }
+        self.pin_access_exempt = args.pin_access_exempt;
+        self.two_phase = args.two_phase;
+        self.throttle = args.min_gain.map(|min_gain| {
+            use crate::consts::THROTTLE_WINDOW;
+            Throttle::new(
+                self.threads.current_num_threads(),
+                THROTTLE_WINDOW,
+                min_gain,
+            )
+        });
+        self.checkpoints = args.strict_legal || args.autosave.is_some();
+        self.autosave = args.autosave.map(|minutes| {
+            use crate::consts::SECS_PER_MIN;
+            Autosave::start(&args.outfile, Duration::from_secs(minutes * SECS_PER_MIN))
+        });
+
+        // Only override the rules when asked to.
+        if let Some(pin_demand) = args.pin_demand {
+            self.rules = Box::new(Iccad2020 { pin_demand });
+            self.update_demand()?;
+        }
 
-            let mut blkgs = HashSet::with_capacity(num_blkgs);
+        Ok(())
+    }
 
-            // Blkg <blockageName> <blockageLayer> <demand>
-            for _ in 0..num_blkgs {
-                let keyword = parse_string(content)?;
-                check_eq(keyword, "Blkg")?;
+    /// Runs all operations.
+    /// Returns what was kept and the failures recovered from, see `RunReport`.
+    pub fn run(&mut self, args: &Args) -> Result<RunReport> {
+        let start = Instant::now();
+        let duration = Self::duration(args).saturating_sub(self.write_margin());
 
-                let blkg_name = parse_string(content)?;
-                let blkg_layer = parse_string(content)?;
-                let blkg_demand: usize = parse_numeric(content)?;
+        self.configure(args)?;
 
-                let layer_id = Layer::from_str(blkg_layer)?;
-                let blkg_id = Blockage::from_str(blkg_name)?;
+        if args.spread {
+            let phase = Instant::now();
+            self.begin_pass("spread");
+            self.spread_conflicts()?;
+            self.counters.record_phase("spread", phase.elapsed());
+        }
 
-                let avail = blkgs.insert(Blockage {
-                    id: blkg_id,
-                    layer: layer_id,
-                    demand: blkg_demand,
-                });
+        if let Some(population) = args.population.filter(|&population| population > 0) {
+            let (generation, failures) = self.evolve(args, population)?;
+            self.route_phases(start, duration)?;
+            return Ok(RunReport {
+                generation: Some(generation),
+                failures,
+                ..RunReport::default()
+            });
+        }
 
-                debug_assert!(avail);
+        match args.restarts {
+            Some(restarts) if restarts > 0 => {
+                let seed = args.seed.unwrap_or_default();
+                let (round, failures) = self.run_restarts(args, restarts, seed)?;
+                Ok(RunReport {
+                    restart: Some(round),
+                    failures,
+                    ..RunReport::default()
+                })
+            }
+            _ => {
+                self.optimize(args, start, duration)?;
+                Ok(RunReport::default())
             }
-
-            self.mastercells.push(MasterCell {
-                id: idx,
-                pins,
-                blkgs,
-            })
         }
+    }
 
-        // NumNeighborCellExtraDemand <count>
-        let keyword = parse_string(content)?;
-        check_eq(keyword, "NumNeighborCellExtraDemand")?;
-        let extra_count: usize = parse_numeric(content)?;
+    /// Moves cells and routes nets as `args` asks, until `duration` after `start`.
+    /// With `--cell`, moves alternate with rerouting, see `move_and_route`,
+    /// and the routing phases run at the end either way.
+    pub(crate) fn optimize(
+        &mut self,
+        args: &Args,
+        start: Instant,
+        duration: Duration,
+    ) -> Result<()> {
+        if let Some(pipeline) = &args.pipeline {
+            self.run_pipeline(pipeline, start, duration)?;
+            return Ok(());
+        }
 
-        self.conflicts.reserve(2 * extra_count);
+        if !args.cell && !args.net {
+            return Err(anyhow!("Do nothing."));
+        }
 
-        let mut is_same: usize = 0;
+        if args.cell {
+            if args.partition {
+                let phase = Instant::now();
+                self.begin_pass("partition");
+                self.partition_cells(start, duration)?;
+                self.counters.record_phase("partition", phase.elapsed());
+            }
+            if args.median {
+                let phase = Instant::now();
+                self.begin_pass("median");
+                self.move_cells_to_medians(start, duration)?;
+                self.counters.record_phase("median", phase.elapsed());
+            }
+            self.move_and_route(start, duration)?;
+            if args.windows {
+                let phase = Instant::now();
+                self.begin_pass("windows");
+                self.place_windows(start, duration)?;
+                self.counters.record_phase("windows", phase.elapsed());
+            }
+        }
+        self.route_phases(start, duration)
+    }
 
-        // sameGGrid <masterCellName1> <masterCellName2> <layerName> <demand>
-        // adjHGGrid <masterCellName1> <masterCellName2> <layerName> <demand>
-        for _ in 0..extra_count {
-            let grid_type_str = parse_string(content)?;
-            let adj_grid = if grid_type_str == "adjHGGrid" {
-                ConflictType::AdjHGGrid
-            } else {
-                check_eq(grid_type_str, "sameGGrid")?;
-                ConflictType::SameGGrid
+    /// Alternates cell moves with rerouting until time runs out,
+    /// so later moves are priced on routes that caught up with the earlier ones.
+    /// Every round makes one pass of the configured `Mover`,
+    /// then reroutes the nets of the cells it moved, keeping only better routes, see `reroute_nets_in`.
+    /// Stops after a round without moves, or once the schedule of the cost mover turns to rerouting.
+    /// Returns the number of rounds that moved cells.
+    pub fn move_and_route(&mut self, start: Instant, duration: Duration) -> Result<usize> {
+        let mut schedule = Schedule::default();
+        let mut rounds = 0;
+        while Self::check_time(start, duration).is_ok() {
+            let positions: Vec<_> = self.cells.iter().map(|cell| cell.position).collect();
+
+            let phase = Instant::now();
+            self.begin_pass("move");
+            let moved = match self.mover {
+                Mover::Cost => {
+                    let remaining = duration.saturating_sub(start.elapsed());
+                    if schedule.strategy(self.moves_remaining(), remaining) == Strategy::Reroute {
+                        0
+                    } else {
+                        self.move_cells(start, duration, &mut schedule)?
+                    }
+                }
+                Mover::Force => self.move_cells_force(start, duration)?,
+                Mover::Gain => self.move_cells_by_gain(start, duration)?,
+                Mover::Cluster => self.move_clusters(start, duration)?,
+                Mover::Gravity => self.move_cells_by_gravity(start, duration)?,
             };
-
-            let master_cell_1 = parse_string(content)?;
-            let master_cell_2 = parse_string(content)?;
-
-            let layer_name = parse_string(content)?;
-            let layer_demand: usize = parse_numeric(content)?;
-
-            let mc_id_1 = MasterCell::from_str(master_cell_1)?;
-            let mc_id_2 = MasterCell::from_str(master_cell_2)?;
-
-            let layer_id = Layer::from_str(layer_name)?;
-
-            self.conflicts
-                .entry(mc_id_1)
-                .or_insert_with(HashSet::new)
-                .insert(Conflict {
-                    kind: adj_grid,
-                    id: mc_id_2,
-                    layer: layer_id,
-                    demand: layer_demand,
-                });
-
-            if mc_id_1 == mc_id_2 {
-                is_same += 1;
-            } else {
-                self.conflicts
-                    .entry(mc_id_2)
-                    .or_insert_with(HashSet::new)
-                    .insert(Conflict {
-                        kind: adj_grid,
-                        id: mc_id_1,
-                        layer: layer_id,
-                        demand: layer_demand,
-                    });
+            self.counters.record_phase("move", phase.elapsed());
+            if moved == 0 {
+                break;
             }
+            rounds += 1;
+
+            let mut nets: Vec<_> = positions
+                .iter()
+                .enumerate()
+                .filter(|&(idx, &position)| self.cells[idx].position != position)
+                .flat_map(|(idx, _)| self.cell_nets(idx))
+                .collect();
+            nets.sort_unstable();
+            nets.dedup();
+
+            let phase = Instant::now();
+            self.begin_pass("reroute");
+            self.reroute_nets_in(nets, start, duration)?;
+            self.counters.record_phase("reroute", phase.elapsed());
+            self.phase_checkpoint();
         }
 
-        let num_elements: usize = self
-            .conflicts
-            .iter()
-            .map(|(_, set)| set)
-            .map(HashSet::len)
-            .sum();
-
-        debug_assert_eq!(num_elements + is_same, 2 * extra_count);
-
-        // NumCellInst <cellInstCount>
-        let keyword = parse_string(content)?;
-        check_eq(keyword, "NumCellInst")?;
-        let cell_count: usize = parse_numeric(content)?;
-
-        let mut pin_cell = Vec::new();
-
-        let mut pin_count = 0;
-        // CellInst <instName> <masterCellName> <gGridRowIdx> <gGridColIdx> <movableCstr>
-        for idx in 0..cell_count {
-            let keyword = parse_string(content)?;
-            check_eq(keyword, "CellInst")?;
-
-            let cell_name = parse_string(content)?;
-            let id = Cell::from_str(cell_name)?;
-            check_eq(id, idx)?;
-
-            let master_cell_name = parse_string(content)?;
-
-            let mc_id = MasterCell::from_str(master_cell_name)?;
-
-            let row: usize = parse_numeric(content)?;
-            let col: usize = parse_numeric(content)?;
-            let position = Pair(row, col);
-
-            let move_str = parse_string(content)?;
-            let movable = if move_str == "Movable" {
-                CellType::Movable
-            } else {
-                check_eq(move_str, "Fixed")?;
-                CellType::Fixed
-            };
+        Ok(rounds)
+    }
 
-            let mc = self.mastercells.get(mc_id).expect("MasterCell not found");
-            let length = mc.pins.len();
-            let pins: Vec<_> = (pin_count..pin_count + length).collect();
-            pin_count += length;
+    /// Reroutes all nets, negotiates away the overflow, cleans up the routes
+    /// and removes needless vias.
+    pub(crate) fn route_phases(&mut self, start: Instant, duration: Duration) -> Result<()> {
+        if let Some(factor) = self.coarse_factor {
+            let phase = Instant::now();
+            self.begin_pass("coarse");
+            self.plan_coarse_routes(factor);
+            self.counters.record_phase("coarse", phase.elapsed());
+        }
 
-            pin_cell.push(pin_count);
+        let phase = Instant::now();
+        self.begin_pass("route");
+        if let Some(bound) = self.soft_overflow {
+            self.soft_route_nets(bound, start, duration)?;
+        } else if self.parallel_routing {
+            self.reroute_nets_parallel(start, duration)?;
+        } else {
+            self.reroute_nets(start, duration)?;
+        }
+        self.counters.record_phase("route", phase.elapsed());
+        self.phase_checkpoint();
+
+        let phase = Instant::now();
+        self.begin_pass("negotiate");
+        self.negotiate(start, duration)?;
+        self.counters.record_phase("negotiate", phase.elapsed());
+        self.phase_checkpoint();
+
+        #[cfg(feature = "exact")]
+        if self.exact_routing {
+            let phase = Instant::now();
+            self.begin_pass("exact");
+            self.exact_route_nets(start, duration)?;
+            self.counters.record_phase("exact", phase.elapsed());
+        }
 
-            self.cells.push(Cell {
-                id,
-                movable,
-                moved: false,
-                position,
-                pins,
-            });
+        if self.steiner_relocation {
+            let phase = Instant::now();
+            self.begin_pass("relocate");
+            self.relocate_all_steiner_points()?;
+            self.counters.record_phase("relocate", phase.elapsed());
         }
 
-        // NumNets <netCount>
-        let keyword = parse_string(content)?;
-        check_eq(keyword, "NumNets")?;
-        let net_count: usize = parse_numeric(content)?;
+        let phase = Instant::now();
+        self.begin_pass("cleanup");
+        self.clean_up_all_routes()?;
+        self.counters.record_phase("cleanup", phase.elapsed());
 
-        let mut net_layers = Vec::with_capacity(net_count);
-        let mut net_pins = Vec::with_capacity(net_count);
-        // Net <netName> <numPins> <minRoutingLayConstraint>
-        for idx in 0..net_count {
-            let keyword = parse_string(content)?;
-            check_eq(keyword, "Net")?;
+        let phase = Instant::now();
+        self.begin_pass("vias");
+        self.minimize_all_vias()?;
+        self.counters.record_phase("vias", phase.elapsed());
+        self.phase_checkpoint();
+        Ok(())
+    }
 
-            let net_name = parse_string(content)?;
-            check_eq(Net::from_str(net_name)?, idx)?;
+    /// Merges collinear and overlapping route segments and removes duplicates in every net,
+    /// so the output has as few segments as possible.
+    pub fn canonicalize_routes(&mut self) {
+        let (threads, nets) = (&self.threads, &mut self.nets);
+        threads.install(|| utilities::par_for_each_mut(nets, Net::canonicalize));
+    }
 
-            let num_pins: usize = parse_numeric(content)?;
-            let layer = parse_string(content)?;
+    /// Estimates the size of the output in bytes.
+    /// Every moved cell and route segment is counted exactly,
+    /// route segments are exact once canonicalized.
+    pub fn estimated_output_size(&self) -> usize {
+        use utilities::num_digits;
 
-            let min_layer = if layer == "NoCstr" {
-                0
-            } else {
-                Layer::from_str(layer)?
-            };
+        let header = |keyword: &str, count: usize| keyword.len() + 1 + num_digits(count) + 1;
 
-            let mut pins = Vec::with_capacity(num_pins);
-            // Pin <instName>/<masterPinName>
-            for _ in 0..num_pins {
-                let keyword = parse_string(content)?;
-                check_eq(keyword, "Pin")?;
-
-                let next = parse_string(content)?;
-                let pin_info = &mut next.split('/');
-                let cell_name = parse_string(pin_info)?;
-                let pin_name = parse_string(pin_info)?;
-                check_eq(pin_info.next(), None)?;
-
-                let cell_id = Cell::from_str(cell_name)?;
-                let pin_id = MasterPin::from_str(pin_name)?;
-
-                pins.push(
-                    *self
-                        .cells
-                        .get(cell_id)
-                        .expect("Cell not found")
-                        .pins
-                        .get(pin_id)
-                        .expect("Pin not found"),
-                );
-            }
+        // CellInst <instName> <gGridRowIdx> <gGridColIdx>
+        let cells: usize = self
+            .cells
+            .iter()
+            .filter(|cell| cell.moved)
+            .map(|cell| {
+                let Pair(row, col) = cell.position;
+                "CellInst C".len() + num_digits(cell.id + 1) + num_digits(row) + num_digits(col) + 3
+            })
+            .sum();
 
-            net_layers.push(min_layer);
-            net_pins.push(pins);
-        }
-        // NumRoutes <routeSegmentCount>
-        let keyword = parse_string(content)?;
-        check_eq(keyword, "NumRoutes")?;
-        let num_segments: usize = parse_numeric(content)?;
+        // <sRowIdx> <sColIdx> <sLayIdx> <eRowIdx> <eColIdx> <eLayIdx> <netName>
+        let mut num_routes = 0;
+        let routes: usize = self
+            .nets
+            .iter()
+            .map(|net| {
+                num_routes += net.routes.len();
+                let name = "N".len() + num_digits(net.id + 1);
+                net.routes
+                    .iter()
+                    .map(|&Route(source, target)| {
+                        let Point(srow, scol, slay) = source;
+                        let Point(erow, ecol, elay) = target;
+                        [srow, scol, slay, erow, ecol, elay]
+                            .iter()
+                            .map(|&n| num_digits(n) + 1)
+                            .sum::<usize>()
+                            + name
+                            + 1
+                    })
+                    .sum::<usize>()
+            })
+            .sum();
+
+        header("NumMovedCellInst", self.already_moved)
+            + cells
+            + header("NumRoutes", num_routes)
+            + routes
+    }
 
-        let mut routes = vec![HashSet::new(); net_count];
+    /// Time reserved for writing the output, derived from its estimated size.
+    pub fn write_margin(&self) -> Duration {
+        use crate::consts::{MIN_WRITE_MARGIN_MS, WRITE_BYTES_PER_SEC};
 
-        // <sRowIdx> <sColIdx> <sLayIdx> <eRowIdx> <eColIdx> <eLayIdx> <netName>
-        for _ in 0..num_segments {
-            let srow: usize = parse_numeric(content)?;
-            let scol: usize = parse_numeric(content)?;
-            let slay: usize = parse_numeric(content)?;
-            let erow: usize = parse_numeric(content)?;
-            let ecol: usize = parse_numeric(content)?;
-            let elay: usize = parse_numeric(content)?;
-            let net_name = parse_string(content)?;
-            let net_id = Net::from_str(net_name)?;
+        let size = self.estimated_output_size() as u64;
+        Duration::from_millis(MIN_WRITE_MARGIN_MS)
+            + Duration::from_secs_f64(size as f64 / WRITE_BYTES_PER_SEC as f64)
+    }
 
-            let route = Route::raw(srow, scol, slay, erow, ecol, elay);
-            routes
-                .get_mut(net_id)
-                .expect("Index out of bounds")
-                .insert(route);
+    /// Write the content stored in memory to a file
+    /// The content is written to a temporary file first and then renamed,
+    /// so a failure never leaves a half-written file behind.
+    /// The autosave is stopped first, so it can't replace the file afterwards.
+    /// Moves beyond the budget are put back first, see `enforce_move_budget`.
+    /// The output is written as `output_version` says.
+    pub fn write_file(&mut self, filename: &str) -> Result<()> {
+        self.autosave = None;
+        self.enforce_move_budget()?;
+        if self.output_version.merges_segments() {
+            self.canonicalize_routes();
         }
 
-        check_eq(routes.len(), net_count)?;
+        let mut content = String::with_capacity(self.estimated_output_size());
+        write!(content, "{}", self)?;
+        let temp = format!("{}.tmp", filename);
 
-        // TODO: self.nets
+        fs::write(&temp, content)?;
+        fs::rename(&temp, filename)?;
 
-        // parsing ends here
-        check_eq(content.next(), None)?;
         Ok(())
     }
 
-    fn duration(args: &Args) -> Duration {
-        use crate::consts::*;
-
-        // By default duration is equal to 1 hr
-        match args {
-            Args {
-                sec: Some(sec),
-                min: None,
-                hr: None,
-                ..
-            } => Duration::from_secs(*sec as u64),
-            Args {
-                sec: None,
-                min: Some(min),
-                hr: None,
-                ..
-            } => Duration::from_secs(SECS_PER_MIN * *min as u64),
-            Args {
-                sec: None,
-                min: None,
-                hr: Some(hr),
-                ..
-            } => Duration::from_secs(SECS_PER_HR * *hr as u64),
-            _ => Duration::from_secs(SECS_PER_HR),
-        }
+    /// Checks if a position is on a grid of dimensions `dim`,
+    /// indexed the same way as in the input file, starting from 1.
+    pub fn in_grid(dim: Pair<usize>, position: Pair<usize>) -> bool {
+        let Pair(rows, cols) = dim;
+        let Pair(row, col) = position;
+        (1..=rows).contains(&row) && (1..=cols).contains(&col)
     }
 
-    fn check_time(start: Instant, duration: Duration) -> Result<()> {
-        let now = Instant::now();
-        if now - start >= duration {
-            Ok(())
-        } else {
-            Err(anyhow!("Time's up!"))
-        }
+    /// Converts a position on a grid of dimensions `dim` to the index into its row-major arrays.
+    /// `None` if the position is off the grid, see `in_grid`.
+    pub fn grid_index(dim: Pair<usize>, position: Pair<usize>) -> Option<usize> {
+        let Pair(row, col) = position;
+        Self::in_grid(dim, position).then(|| (row - 1) * dim.y() + col - 1)
     }
 
-    /// Runs all operations.
-    pub fn run(&mut self, args: &Args) -> Result<()> {
-        let start = Instant::now();
-        let duration = Self::duration(args);
+    /// Checks if a point is on the chip.
+    /// `point` is indexed the same way as in the input file, starting from 1.
+    pub fn in_bounds(&self, point: Point<usize>) -> bool {
+        Self::in_grid(self.dim, point.flatten()) && (1..=self.layers.len()).contains(&point.lay())
+    }
 
-        match args {
-            Args { cell: true, .. } => loop {
-                Self::check_time(start, duration)?;
-                todo!()
-            },
-            Args { net: true, .. } => loop {
-                Self::check_time(start, duration)?;
-                todo!()
-            },
-            _ => Err(anyhow!("Do nothing.")),
+    /// Returns `Ok(())` if `point` is on the chip.
+    /// Returns `Err(BoundsError)` otherwise.
+    pub fn check_bounds(&self, point: Point<usize>) -> Result<()> {
+        if self.in_bounds(point) {
+            Ok(())
+        } else {
+            Err(Error::from(BoundsError(point)))
         }
     }
 
-    /// Write the content stored in memory to a file
-    pub fn write_file(&mut self, filename: &str) -> Result<()> {
-        fs::write(filename, format!("{}\n", self))?;
-
-        Ok(())
+    /// Looks up the id of a layer by its name.
+    /// Returns `Err(NameError)` if no layer has that name.
+    pub fn layer_id(&self, name: &str) -> Result<usize> {
+        io::layer_id(&self.layer_ids, name)
     }
 
     /// Returns a reference to a layer
@@ -480,36 +792,42 @@ impl Chip {
 }
 
 impl Display for Chip {
+    /// The chip in the output format of its `output_version`.
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        let accumulate = |mut acc: String, s: String| {
-            acc.push_str(&s);
-            acc
-        };
+        let version = self.output_version;
 
         // NumMovedCellInst <movedCellInstCount>
-        writeln!(f, "NumMovedCellInst {}", self.already_moved)?;
-
-        let mut num_moved = 0;
-        for cell in self.cells.iter() {
-            if cell.moved {
-                num_moved += 1;
-            }
+        let moved: Vec<_> = self
+            .cells
+            .iter()
+            .filter(|cell| version.lists_cell(cell))
+            .collect();
+        writeln!(f, "NumMovedCellInst {}", moved.len())?;
+        for cell in moved {
             writeln!(f, "{}", cell)?;
         }
-        debug_assert_eq!(num_moved, self.already_moved);
+        debug_assert!(
+            version != OutputVersion::V2
+                || self.cells.iter().filter(|cell| cell.moved).count() == self.already_moved
+        );
 
         // NumRoutes <routeSegmentCount>
-        writeln!(f, "NumRoutes {}", self.nets.len())?;
-
-        // `fold_with + reduce_with` is the parallel iterators' equivalent to `fold_with` of iterators
-        let names: String = self
-            .nets
-            .par_iter()
-            .map(ToString::to_string)
-            .fold_with(String::new(), accumulate)
-            .reduce_with(accumulate)
-            .ok_or(FmtError)?;
+        let nets = self
+            .threads
+            .install(|| utilities::par_map(&self.nets, |net| (net.id, version.routes(net))));
+        let num_routes: usize = nets.iter().map(|(_, routes)| routes.len()).sum();
+        writeln!(f, "NumRoutes {}", num_routes)?;
+
+        let lines = self.threads.install(|| {
+            utilities::par_concat(&nets, |(id, routes)| {
+                let name = Net::from_num(*id).unwrap_or_default();
+                routes
+                    .iter()
+                    .map(|route| format!("{} {}\n", route, name))
+                    .collect()
+            })
+        });
 
-        write!(f, "{}", names)
+        write!(f, "{}", lines)
     }
 }
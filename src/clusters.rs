@@ -0,0 +1,170 @@
+use crate::{
+    chip::Chip,
+    components::{CellType, Pair},
+    utilities::UnionFind,
+};
+use anyhow::Result;
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+impl Chip {
+    /// Groups of tightly connected movable cells, every one sorted, ordered by their first cell.
+    /// Two cells are tight if they share at least `CLUSTER_MIN_SHARED_NETS` nets
+    /// of at most `CLUSTER_MAX_NET_PINS` pins. The tightest pairs are joined first,
+    /// and groups stop growing at `CLUSTER_MAX_CELLS` cells. Cells without a tight partner are left out.
+    pub fn cell_clusters(&self) -> Vec<Vec<usize>> {
+        use crate::consts::{CLUSTER_MAX_CELLS, CLUSTER_MAX_NET_PINS, CLUSTER_MIN_SHARED_NETS};
+
+        let mut shared: HashMap<(usize, usize), usize> = HashMap::new();
+        for net in self.nets.iter() {
+            if net.pins.len() > CLUSTER_MAX_NET_PINS {
+                continue;
+            }
+            let mut owners: Vec<_> = net
+                .pins
+                .iter()
+                .filter_map(|&pin| self.pin_owners.get(pin).map(|&(owner, _)| owner))
+                .filter(|&owner| matches!(self.cells[owner].movable, CellType::Movable))
+                .collect();
+            owners.sort_unstable();
+            owners.dedup();
+            for (i, &a) in owners.iter().enumerate() {
+                for &b in &owners[i + 1..] {
+                    *shared.entry((a, b)).or_default() += 1;
+                }
+            }
+        }
+
+        let mut pairs: Vec<_> = shared
+            .into_iter()
+            .filter(|&(_, nets)| nets >= CLUSTER_MIN_SHARED_NETS)
+            .collect();
+        pairs.sort_unstable_by_key(|&((a, b), nets)| (Reverse(nets), a, b));
+
+        let mut union_find = UnionFind::new(self.cells.len());
+        let mut sizes = vec![1; self.cells.len()];
+        for ((a, b), _) in pairs {
+            let (root_a, root_b) = match (union_find.find_mut(a), union_find.find_mut(b)) {
+                (Some(root_a), Some(root_b)) => (root_a, root_b),
+                _ => continue,
+            };
+            let size = sizes[root_a] + sizes[root_b];
+            if root_a == root_b || size > CLUSTER_MAX_CELLS {
+                continue;
+            }
+            union_find.union(a, b);
+            if let Some(root) = union_find.find_mut(a) {
+                sizes[root] = size;
+            }
+        }
+
+        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (idx, root) in union_find.belongs().into_iter().enumerate() {
+            groups.entry(root).or_default().push(idx);
+        }
+        let mut clusters: Vec<_> = groups
+            .into_values()
+            .filter(|cluster| cluster.len() > 1)
+            .collect();
+        clusters.sort_unstable();
+        clusters
+    }
+
+    /// Shift in GGrids, by row and column, that takes the centroid of a cluster's cells
+    /// to the centroid of the other pins on their nets. Every net weighs the same, like in `cell_force`.
+    /// `None` if the cluster's nets connect to no other cell.
+    pub fn cluster_shift(&self, cluster: &[usize]) -> Option<(isize, isize)> {
+        let members: HashSet<_> = cluster.iter().copied().collect();
+        let mut nets: Vec<_> = cluster
+            .iter()
+            .flat_map(|&idx| self.cell_nets(idx))
+            .collect();
+        nets.sort_unstable();
+        nets.dedup();
+
+        let (mut weight, mut row, mut col) = (0.0, 0.0, 0.0);
+        for id in nets {
+            let others: Vec<_> = self.nets[id]
+                .pins
+                .iter()
+                .filter_map(|&pin| {
+                    let (owner, _) = *self.pin_owners.get(pin)?;
+                    (!members.contains(&owner)).then(|| self.cells[owner].position)
+                })
+                .collect();
+            let share = 1.0 / others.len().max(1) as f64;
+            for Pair(r, c) in others {
+                weight += share;
+                row += share * r as f64;
+                col += share * c as f64;
+            }
+        }
+        if weight == 0.0 {
+            return None;
+        }
+
+        let count = cluster.len() as f64;
+        let own_row = cluster
+            .iter()
+            .map(|&idx| self.cells[idx].position.x() as f64)
+            .sum::<f64>();
+        let own_col = cluster
+            .iter()
+            .map(|&idx| self.cells[idx].position.y() as f64)
+            .sum::<f64>();
+        Some((
+            (row / weight - own_row / count).round() as isize,
+            (col / weight - own_col / count).round() as isize,
+        ))
+    }
+
+    /// Moves every cluster of cells, see `cell_clusters`, as a unit by its shift,
+    /// see `cluster_shift`, so the cells keep their relative placement.
+    /// A cluster only moves if every cell lands on the chip within `max_displacement`
+    /// with room for its demand footprint, and the moves are kept only if they fit the budget
+    /// and improve the `quality`, see `speculate_moves`.
+    /// Returns the number of cells moved.
+    pub fn move_clusters(&mut self, start: Instant, duration: Duration) -> Result<usize> {
+        let footprints = self.demand_footprints();
+
+        let mut moved = 0;
+        for cluster in self.cell_clusters() {
+            if Self::check_time(start, duration).is_err() {
+                break;
+            }
+
+            let (shift_row, shift_col) = match self.cluster_shift(&cluster) {
+                Some(shift) if shift != (0, 0) => shift,
+                _ => continue,
+            };
+            let moves: Option<Vec<_>> = cluster
+                .iter()
+                .map(|&idx| {
+                    let cell = &self.cells[idx];
+                    let Pair(row, col) = cell.position;
+                    let (row, col) = (row as isize + shift_row, col as isize + shift_col);
+                    if row < 1 || col < 1 {
+                        return None;
+                    }
+                    let target = Pair(row as usize, col as usize);
+                    let fits = footprints
+                        .get(cell.mastercell)
+                        .is_some_and(|footprint| self.fits_footprint(target, footprint));
+                    (fits && self.within_displacement(idx, target) == target)
+                        .then_some((idx, target))
+                })
+                .collect();
+
+            if let Some(moves) = moves {
+                if self.speculate_moves(&moves)? {
+                    moved += moves.len();
+                }
+            }
+        }
+
+        Ok(moved)
+    }
+}
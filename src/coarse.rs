@@ -0,0 +1,288 @@
+use crate::{
+    chip::Chip,
+    components::{CellType, Pair},
+    router::spanning_tree,
+};
+use anyhow::Result;
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+/// A downsampled chip where every super-GGrid covers `factor` x `factor` GGrids.
+/// Planning on a `CoarseChip` is fast, the plans are then refined on the full chip.
+/// Super-GGrids are indexed starting from 1, the same way as GGrids.
+#[derive(Clone, Debug, Default)]
+pub struct CoarseChip {
+    /// number of GGrids along each side of a super-GGrid
+    pub factor: usize,
+    /// dimensions in super-GGrids
+    pub dim: Pair<usize>,
+    /// capacity of every super-GGrid, per layer
+    pub capacity: Vec<Vec<usize>>,
+    /// demand of every super-GGrid, per layer
+    pub demand: Vec<Vec<usize>>,
+    /// super-GGrid of every cell
+    pub cells: Vec<Pair<usize>>,
+    /// whether every cell is movable
+    pub movable: Vec<bool>,
+    /// super-GGrids of every net's pins
+    pub nets: Vec<Vec<Pair<usize>>>,
+    /// nets connected to every cell
+    pub cell_nets: Vec<Vec<usize>>,
+}
+
+impl Chip {
+    /// Aggregates supply and demand into super-GGrids of `factor` x `factor` GGrids.
+    pub fn coarsen(&self, factor: usize) -> CoarseChip {
+        let factor = factor.max(1);
+        let Pair(rows, cols) = self.dim;
+        let dim = Pair(rows.div_ceil(factor), cols.div_ceil(factor));
+        let tile =
+            |Pair(row, col): Pair<usize>| Pair((row - 1) / factor + 1, (col - 1) / factor + 1);
+        let index = |Pair(row, col): Pair<usize>| (row - 1) * dim.y() + col - 1;
+
+        let mut capacity = vec![vec![0usize; dim.size()]; self.layers.len()];
+        let mut demand = vec![vec![0usize; dim.size()]; self.layers.len()];
+        for (layer, (cap, dem)) in self
+            .layers
+            .iter()
+            .zip(capacity.iter_mut().zip(demand.iter_mut()))
+        {
+            for row in 1..=rows {
+                for col in 1..=cols {
+                    let idx = index(tile(Pair(row, col)));
+                    let supply = layer.get_capacity(row - 1, col - 1).copied().unwrap_or(0);
+                    let used = layer.get_demand(row - 1, col - 1).copied().unwrap_or(0);
+                    cap[idx] = cap[idx].saturating_add(supply);
+                    dem[idx] = dem[idx].saturating_add(used);
+                }
+            }
+        }
+
+        let owners = &self.pin_owners;
+        let nets = self
+            .nets
+            .iter()
+            .map(|net| {
+                net.pins
+                    .iter()
+                    .map(|&pin| tile(self.cells[owners[pin].0].position))
+                    .collect()
+            })
+            .collect();
+
+        let mut cell_nets = vec![Vec::new(); self.cells.len()];
+        for net in self.nets.iter() {
+            let connected: HashSet<_> = net.pins.iter().map(|&pin| owners[pin].0).collect();
+            for cell in connected {
+                cell_nets[cell].push(net.id);
+            }
+        }
+
+        CoarseChip {
+            factor,
+            dim,
+            capacity,
+            demand,
+            cells: self.cells.iter().map(|cell| tile(cell.position)).collect(),
+            movable: self
+                .cells
+                .iter()
+                .map(|cell| matches!(cell.movable, CellType::Movable))
+                .collect(),
+            nets,
+            cell_nets,
+        }
+    }
+
+    /// Refines a coarse plan: the candidates of a cell inside a super-GGrid.
+    pub fn candidates_within(
+        &self,
+        idx: usize,
+        coarse: &CoarseChip,
+        tile: Pair<usize>,
+    ) -> Vec<Pair<usize>> {
+        let (Pair(rlo, clo), Pair(rhi, chi)) = coarse.region(tile);
+        self.candidates(idx)
+            .into_iter()
+            .filter(|&Pair(row, col)| (rlo..=rhi).contains(&row) && (clo..=chi).contains(&col))
+            .collect()
+    }
+
+    /// Plans the super-GGrid of every cell on the chip coarsened by `factor`, see `plan_regions`,
+    /// then moves every cell planned elsewhere to its candidate inside the planned super-GGrid
+    /// with the shortest nets, see `candidates_within` and `fine_hpwl`, if that shortens them.
+    /// The moves shortening the nets most are made first while the move budget and time allow,
+    /// and a move whose nets can't be rerouted is undone.
+    /// Returns the number of cells moved.
+    pub fn move_cells_to_tiles(
+        &mut self,
+        factor: usize,
+        start: Instant,
+        duration: Duration,
+    ) -> Result<usize> {
+        let coarse = self.coarsen(factor);
+        let plan = coarse.plan_regions();
+
+        let mut moves: Vec<_> = (0..self.cells.len())
+            .filter(|&idx| plan[idx] != coarse.cells[idx])
+            .filter_map(|idx| {
+                let before = self.fine_hpwl(idx, self.cells[idx].position);
+                let (after, target) = self
+                    .candidates_within(idx, &coarse, plan[idx])
+                    .into_iter()
+                    .map(|target| (self.fine_hpwl(idx, target), target))
+                    .min_by_key(|&(hpwl, Pair(row, col))| (hpwl, row, col))?;
+                (after < before).then_some((before - after, idx, target))
+            })
+            .collect();
+        moves.sort_by_key(|&(gain, idx, _)| (Reverse(gain), idx));
+
+        let mut moved = 0;
+        for (_, idx, target) in moves {
+            if Self::check_time(start, duration).is_err() || self.moves_remaining() == 0 {
+                break;
+            }
+            // Earlier moves may have taken the room.
+            if self.can_move(idx, target) && self.move_and_reroute(idx, target)? {
+                moved += 1;
+            }
+        }
+
+        Ok(moved)
+    }
+}
+
+impl CoarseChip {
+    /// The super-GGrid a GGrid belongs to.
+    pub fn tile(&self, position: Pair<usize>) -> Pair<usize> {
+        let Pair(row, col) = position;
+        Pair(
+            row.saturating_sub(1) / self.factor + 1,
+            col.saturating_sub(1) / self.factor + 1,
+        )
+    }
+
+    /// The GGrids covered by a super-GGrid, as the lowest and highest corners.
+    pub fn region(&self, tile: Pair<usize>) -> (Pair<usize>, Pair<usize>) {
+        let Pair(row, col) = tile;
+        let lo = Pair(
+            row.saturating_sub(1) * self.factor + 1,
+            col.saturating_sub(1) * self.factor + 1,
+        );
+        let hi = Pair(row * self.factor, col * self.factor);
+        (lo, hi)
+    }
+
+    /// Remaining supply of a super-GGrid summed over all layers, negative if overflowed.
+    /// Super-GGrids off the chip have none.
+    pub fn slack(&self, tile: Pair<usize>) -> isize {
+        let idx = match Chip::grid_index(self.dim, tile) {
+            Some(idx) => idx,
+            None => return 0,
+        };
+        self.capacity
+            .iter()
+            .zip(self.demand.iter())
+            .map(|(cap, dem)| cap[idx] as isize - dem[idx] as isize)
+            .sum()
+    }
+
+    /// Plans the super-GGrid every cell should move into.
+    /// Movable cells are pulled towards the median of their nets' other pins,
+    /// unless the target has less slack than the current super-GGrid.
+    pub fn plan_regions(&self) -> Vec<Pair<usize>> {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(idx, &current)| {
+                if !self.movable[idx] {
+                    return current;
+                }
+
+                let mut rows = Vec::new();
+                let mut cols = Vec::new();
+                for &net in self.cell_nets[idx].iter() {
+                    for &Pair(row, col) in self.nets[net].iter() {
+                        rows.push(row);
+                        cols.push(col);
+                    }
+                }
+
+                if rows.is_empty() {
+                    return current;
+                }
+
+                rows.sort_unstable();
+                cols.sort_unstable();
+                let target = Pair(rows[rows.len() / 2], cols[cols.len() / 2]);
+
+                if self.slack(target) >= self.slack(current) {
+                    target
+                } else {
+                    current
+                }
+            })
+            .collect()
+    }
+
+    /// Plans a rough corridor of super-GGrids for every net.
+    /// Pins are connected by a minimum spanning tree,
+    /// and every edge takes the L-shape with more slack.
+    /// Every planned net takes one unit of slack from the super-GGrids it crosses,
+    /// so later nets spread out.
+    pub fn plan_corridors(&self) -> Vec<HashSet<Pair<usize>>> {
+        let mut used: HashMap<Pair<usize>, isize> = HashMap::new();
+        self.nets
+            .iter()
+            .map(|pins| {
+                let tiles: Vec<_> = pins
+                    .iter()
+                    .copied()
+                    .collect::<HashSet<_>>()
+                    .into_iter()
+                    .collect();
+
+                let mut corridor: HashSet<_> = tiles.iter().copied().collect();
+                for (a, b) in Self::spanning_edges(&tiles) {
+                    let bends = [Pair(a.x(), b.y()), Pair(b.x(), a.y())];
+                    let paths = bends.iter().map(|&bend| {
+                        let mut path = Self::straight(a, bend);
+                        path.extend(Self::straight(bend, b));
+                        path
+                    });
+                    let slack = |tile: &Pair<usize>| {
+                        self.slack(*tile) - used.get(tile).copied().unwrap_or(0)
+                    };
+                    let best = paths
+                        .max_by_key(|path| path.iter().map(slack).min())
+                        .unwrap_or_default();
+                    corridor.extend(best);
+                }
+                for &tile in corridor.iter() {
+                    *used.entry(tile).or_default() += 1;
+                }
+                corridor
+            })
+            .collect()
+    }
+
+    /// Edges of a minimum spanning tree (Manhattan distance) over `tiles`.
+    fn spanning_edges(tiles: &[Pair<usize>]) -> Vec<(Pair<usize>, Pair<usize>)> {
+        spanning_tree(tiles)
+            .into_iter()
+            .map(|(a, b)| (tiles[a], tiles[b]))
+            .collect()
+    }
+
+    /// Super-GGrids on a straight line between `a` and `b`, which share a row or a column.
+    fn straight(a: Pair<usize>, b: Pair<usize>) -> Vec<Pair<usize>> {
+        let Pair(ar, ac) = a;
+        let Pair(br, bc) = b;
+        (ar.min(br)..=ar.max(br))
+            .flat_map(|row| (ac.min(bc)..=ac.max(bc)).map(move |col| Pair(row, col)))
+            .collect()
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{
+    chip::Chip,
+    components::{Blockage, Cell, Conflict, FactoryID, Layer, MasterCell, MasterPin, Net, Point},
+    consts::MAX_DIAGNOSTICS,
+    io,
+    raw::RawDesign,
+    report::Report,
+};
+use anyhow::{anyhow, Error, Result};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+impl Chip {
+    /// Compiles a `RawDesign` into the runtime representation stored in `self`.
+    /// Names are resolved into ids, positions are checked against the boundary,
+    /// pins are assigned sequential global ids and demand is calculated.
+    /// If anything cannot be resolved, fails with a `Report` of every problem found.
+    pub fn compile(&mut self, raw: &RawDesign) -> Result<()> {
+        // Every problem is collected first, so they can all be reported together.
+        let mut report = Report::new(self.max_diagnostics.unwrap_or(MAX_DIAGNOSTICS));
+
+        if raw.dim.x().checked_mul(raw.dim.y()).is_none() {
+            return Err(anyhow!("Grid of {} GGrids is too large", raw.dim));
+        }
+
+        self.max_move = raw.max_move;
+        self.dim = raw.dim;
+        let num_layers = raw.layers.len();
+
+        self.layers = Vec::with_capacity(num_layers);
+        self.layer_ids = HashMap::with_capacity(num_layers);
+        for (idx, layer) in raw.layers.iter().enumerate() {
+            if self.layer_ids.insert(layer.name.clone(), idx).is_some() {
+                report.error(format!("Layer {} is declared twice", layer.name));
+            }
+
+            self.layers.push(Layer {
+                id: idx,
+                name: layer.name.clone(),
+                direction: layer.direction,
+                dim: self.dim,
+                capacity: Arc::new(vec![layer.supply; self.dim.size()]),
+                demand: vec![0; self.dim.size()],
+                base_demand: vec![0; self.dim.size()],
+            });
+        }
+
+        self.clamped_supply = Vec::new();
+        for supply in raw.non_default_supply.iter() {
+            let Point(r, c, l) = supply.point;
+            let capacity = match self.check_bounds(supply.point) {
+                Ok(()) => self.layers[l - 1].get_capacity_mut(r - 1, c - 1),
+                Err(_) => None,
+            };
+            match capacity {
+                Some(capacity) => {
+                    let total = *capacity as isize + supply.delta;
+                    if total < 0 {
+                        report.warning(format!(
+                            "Supply at {} would be {}, clamped to 0",
+                            supply.point, total
+                        ));
+                        self.clamped_supply.push(supply.point);
+                    }
+                    *capacity = total.max(0) as usize;
+                }
+                None => report.error(format!("Supply at {} is out of bounds", supply.point)),
+            }
+        }
+
+        let layer_ids = &self.layer_ids;
+        let layer_id = |report: &mut Report, owner: &str, layer: &str| {
+            let id = io::layer_id(layer_ids, layer).ok();
+            if id.is_none() {
+                report.error(format!("{} is on unknown layer {}", owner, layer));
+            }
+            id
+        };
+
+        self.mastercells = Vec::with_capacity(raw.mastercells.len());
+        for (idx, mc) in raw.mastercells.iter().enumerate() {
+            if MasterCell::from_str(&mc.name).ok() != Some(idx) {
+                report.error(format!("MasterCell {} is out of order", mc.name));
+            }
+
+            let mut pins = Vec::with_capacity(mc.pins.len());
+            for (pin_idx, pin) in mc.pins.iter().enumerate() {
+                let owner = format!("Pin {} of {}", pin.name, mc.name);
+                let id = MasterPin::from_str(&pin.name).ok();
+                if id.is_none() {
+                    report.error(format!("{} has an invalid name", owner));
+                } else if id != Some(pin_idx) {
+                    report.error(format!("{} is out of order", owner));
+                }
+                if let (Some(id), Some(layer)) = (id, layer_id(&mut report, &owner, &pin.layer)) {
+                    pins.push(MasterPin {
+                        id,
+                        layer,
+                        offset: None,
+                    });
+                }
+            }
+
+            let mut blkgs = Vec::with_capacity(mc.blkgs.len());
+            for blkg in mc.blkgs.iter() {
+                let owner = format!("Blockage {} of {}", blkg.name, mc.name);
+                let id = Blockage::from_str(&blkg.name).ok();
+                if id.is_none() {
+                    report.error(format!("{} has an invalid name", owner));
+                }
+                if let (Some(id), Some(layer)) = (id, layer_id(&mut report, &owner, &blkg.layer)) {
+                    blkgs.push(Blockage {
+                        id,
+                        layer,
+                        demand: blkg.demand,
+                    });
+                }
+            }
+
+            self.mastercells.push(MasterCell::new(idx, pins, blkgs));
+        }
+
+        // Every conflict is stored under both of its mastercells.
+        self.conflicts = HashMap::with_capacity(2 * raw.conflicts.len());
+        for conflict in raw.conflicts.iter() {
+            let (a, b) = &conflict.mastercells;
+            let owner = format!("Conflict between {} and {}", a, b);
+            let mc_ids = match (MasterCell::from_str(a), MasterCell::from_str(b)) {
+                (Ok(mc_id_1), Ok(mc_id_2)) => Some((mc_id_1, mc_id_2)),
+                _ => {
+                    report.error(format!("{} names an invalid mastercell", owner));
+                    None
+                }
+            };
+            let layer = layer_id(&mut report, &owner, &conflict.layer);
+
+            if let (Some((mc_id_1, mc_id_2)), Some(layer)) = (mc_ids, layer) {
+                for (mc, other) in [(mc_id_1, mc_id_2), (mc_id_2, mc_id_1)] {
+                    self.conflicts.entry(mc).or_default().insert(Conflict {
+                        kind: conflict.kind,
+                        id: other,
+                        layer,
+                        demand: conflict.demand,
+                    });
+                }
+            }
+        }
+
+        self.cells = Vec::with_capacity(raw.cells.len());
+        let mut pin_count = 0;
+        for (idx, cell) in raw.cells.iter().enumerate() {
+            let id = Cell::from_str(&cell.name).ok();
+            if id != Some(idx) {
+                report.error(format!("CellInst {} is out of order", cell.name));
+            }
+            if !Self::in_grid(self.dim, cell.position) {
+                report.error(format!("CellInst {} is out of bounds", cell.name));
+            }
+
+            let mc = MasterCell::from_str(&cell.mastercell)
+                .ok()
+                .and_then(|mc_id| self.mastercells.get(mc_id));
+            let mc = match mc {
+                Some(mc) => mc,
+                None => {
+                    report.error(format!(
+                        "CellInst {} has unknown mastercell {}",
+                        cell.name, cell.mastercell
+                    ));
+                    continue;
+                }
+            };
+
+            let length = mc.num_pins();
+            let pins: Vec<_> = (pin_count..pin_count + length).collect();
+            pin_count += length;
+
+            self.cells.push(Cell {
+                id: idx,
+                movable: cell.movable,
+                mastercell: mc.id,
+                moved: false,
+                position: cell.position,
+                origin: cell.position,
+                pins,
+            });
+        }
+
+        self.pin_owners = self.collect_pin_owners();
+
+        self.nets = Vec::with_capacity(raw.nets.len());
+        for (idx, net) in raw.nets.iter().enumerate() {
+            if Net::from_str(&net.name).ok() != Some(idx) {
+                report.error(format!("Net {} is out of order", net.name));
+            }
+
+            let owner = format!("Net {}", net.name);
+            let min_layer = match &net.min_layer {
+                Some(layer) => layer_id(&mut report, &owner, layer).unwrap_or(0),
+                None => 0,
+            };
+
+            let mut pins = Vec::with_capacity(net.pins.len());
+            for (cell, pin) in net.pins.iter() {
+                let found = Cell::from_str(cell)
+                    .ok()
+                    .zip(MasterPin::from_str(pin).ok())
+                    .and_then(|(cell_id, pin_id)| self.cells.get(cell_id)?.pins.get(pin_id));
+                match found {
+                    Some(&pin) => pins.push(pin),
+                    None => report.error(format!("{} has unknown pin {}/{}", owner, cell, pin)),
+                }
+            }
+
+            self.nets.push(Net {
+                id: idx,
+                min_layer,
+                pins,
+                routes: HashSet::new(),
+            });
+        }
+
+        self.pin_nets = self.collect_pin_nets();
+        self.dirty_nets.clear();
+
+        for route in raw.routes.iter() {
+            let in_bounds = self
+                .check_bounds(route.route.source())
+                .and_then(|_| self.check_bounds(route.route.target()));
+            if in_bounds.is_err() {
+                report.error(format!(
+                    "Route {} of {} is out of bounds",
+                    route.route, route.net
+                ));
+                continue;
+            }
+
+            let net = Net::from_str(&route.net)
+                .ok()
+                .and_then(|net_id| self.nets.get_mut(net_id));
+            match net {
+                Some(net) => {
+                    net.routes.insert(route.route);
+                }
+                None => report.error(format!(
+                    "Route {} has unknown net {}",
+                    route.route, route.net
+                )),
+            }
+        }
+
+        // Split and merged segments are accepted alike, and canonicalized on load.
+        self.canonicalize_routes();
+
+        for offset in raw.pin_offsets.iter() {
+            let set = MasterCell::from_str(&offset.mastercell)
+                .ok()
+                .zip(MasterPin::from_str(&offset.pin).ok())
+                .and_then(|(mc_id, pin_id)| {
+                    self.mastercells
+                        .get_mut(mc_id)?
+                        .set_pin_offset(pin_id, offset.offset)
+                });
+            if set.is_none() {
+                report.error(format!(
+                    "PinOffset of unknown pin {} of {}",
+                    offset.pin, offset.mastercell
+                ));
+            }
+        }
+
+        // Demand can only be calculated on a consistent chip.
+        if report.errors > 0 {
+            return Err(Error::from(report));
+        }
+
+        self.bake_base_demand()?;
+        self.update_demand()?;
+
+        // The unmodified input is the fallback until a legal checkpoint is made.
+        self.original = Some(self.snapshot());
+        self.fallback = self.original.clone();
+
+        Ok(())
+    }
+}
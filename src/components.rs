@@ -1,12 +1,14 @@
+use crate::utilities::{NameError, ShapeError};
 use anyhow::{Error, Result};
 use num::Num;
 use std::{
     cmp,
-    collections::HashSet,
+    collections::{BTreeSet, HashMap, HashSet},
     fmt::{Display, Error as FmtError, Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
     ops,
     str::FromStr,
-    usize,
+    sync::Arc,
 };
 
 /// FactoryID provides three methods.
@@ -28,8 +30,9 @@ pub trait FactoryID {
         // subtracted by one because of the offset
         let length = Self::prefix().len();
 
-        let parsednum = name[length..].parse::<usize>().map_err(Error::from)?;
-        let id = parsednum - 1;
+        let digits = name.get(length..).ok_or(NameError)?;
+        let parsednum = digits.parse::<usize>().map_err(Error::from)?;
+        let id = parsednum.checked_sub(1).ok_or(NameError)?;
         Ok(id)
     }
 
@@ -56,7 +59,7 @@ pub enum ConflictType {
 }
 
 /// Whether a cell is movable
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum CellType {
     Movable,
     Fixed,
@@ -80,28 +83,37 @@ where
     T: Copy + Num;
 
 /// A 3-dimension tuple representing a Point.
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+/// Points are ordered by row, then column, then layer.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Point<T>(pub T, pub T, pub T)
 where
     T: Copy + Num;
 
 /// A source point and a target point representing a Route.
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+/// A segment is the same in either direction: equality and hashing use its normalized form,
+/// and routes made by `new` or `raw` are stored normalized.
+#[derive(Clone, Copy, Debug, Default)]
 pub struct Route<T>(pub Point<T>, pub Point<T>)
 where
     T: Copy + Num;
 
 /// Some information about a Layer.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Layer {
     /// layer id (starts from 0)
     pub id: usize,
+    /// layer name
+    pub name: String,
     /// horizontal or vertical
     pub direction: Direction,
     /// dimensions
     pub dim: Pair<usize>,
-    /// all grids' capacity
-    pub capacity: Vec<usize>,
+    /// all grids' capacity, shared with views of the chip, see `ChipView`
+    pub capacity: Arc<Vec<usize>>,
+    /// all grids' demand, including `base_demand`
+    pub demand: Vec<usize>,
+    /// all grids' demand from fixed cells, which never changes
+    pub base_demand: Vec<usize>,
 }
 
 /// Some information about a MasterPin.
@@ -111,6 +123,8 @@ pub struct MasterPin {
     pub id: usize,
     /// layer on which the pin is on
     pub layer: usize,
+    /// offset from the GGrid center in thousandths of a GGrid, `None` means the center
+    pub offset: Option<Pair<isize>>,
 }
 
 /// Some information about a Blockage.
@@ -125,19 +139,19 @@ pub struct Blockage {
 }
 
 /// Some information about a MasterCell.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct MasterCell {
     /// id of cell
     pub id: usize,
-    /// number of pins
-    pub pins: HashSet<MasterPin>,
-    /// number of blockages
-    pub blkgs: HashSet<Blockage>,
+    /// pins sorted by layer, then id
+    pins: Vec<MasterPin>,
+    /// blockages sorted by layer, then id
+    blkgs: Vec<Blockage>,
 }
 
 /// Some information about a Conflict,
 /// which happens when certain types of MasterCells are too close for confort.
-#[derive(Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Conflict {
     /// adjHGGrid or sameGGrid
     pub kind: ConflictType,
@@ -150,16 +164,20 @@ pub struct Conflict {
 }
 
 /// Some information about a Cell
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Cell {
     /// id of the cell
     pub id: usize,
     /// if the cell can be moved
     pub movable: CellType,
+    /// mastercell id
+    pub mastercell: usize,
     /// whether the cell has moved
     pub moved: bool,
     /// position
     pub position: Pair<usize>,
+    /// position before any movement
+    pub origin: Pair<usize>,
     /// mastercell type
     pub pins: Vec<usize>,
 }
@@ -195,13 +213,50 @@ pub struct PosNode {
 }
 
 /// Some information about a Net.
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct Net {
     /// id of the net
     pub id: usize,
     /// min layer id
     pub min_layer: usize,
-    // TODO: fields that backs the actual implementation
+    /// pins connected by the net
+    pub pins: Vec<usize>,
+    /// route segments
+    pub routes: HashSet<Route<usize>>,
+}
+
+/// Number of entries in each section of the input file.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SectionCounts {
+    /// NumLayer
+    pub layers: usize,
+    /// NumNonDefaultSupplyGGrid
+    pub non_default_supply: usize,
+    /// NumMasterCell
+    pub mastercells: usize,
+    /// NumNeighborCellExtraDemand
+    pub conflicts: usize,
+    /// NumCellInst
+    pub cells: usize,
+    /// NumNets
+    pub nets: usize,
+    /// NumRoutes
+    pub routes: usize,
+}
+
+/// Where the input came from, used to trace results back to a benchmark.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Provenance {
+    /// input file path, `None` if read from a string
+    pub path: Option<String>,
+    /// seconds since the unix epoch when the input was parsed
+    pub parsed_at: u64,
+    /// FNV-1a hash of the input content
+    pub hash: u64,
+    /// size of the input content in bytes
+    pub size: usize,
+    /// entries in every section
+    pub counts: SectionCounts,
 }
 
 impl<T> Pair<T>
@@ -227,6 +282,13 @@ where
     }
 }
 
+impl Pair<usize> {
+    /// Manhattan distance between two pairs.
+    pub fn distance(&self, other: &Self) -> usize {
+        self.x().abs_diff(other.x()) + self.y().abs_diff(other.y())
+    }
+}
+
 impl<T> Point<T>
 where
     T: Copy + Num,
@@ -255,18 +317,34 @@ where
 
 impl<T> Route<T>
 where
-    T: Copy + Num,
+    T: Copy + Num + PartialOrd,
 {
+    /// Creates a normalized route between `a` and `b`.
     pub fn new(a: Point<T>, b: Point<T>) -> Self {
-        Self(a, b)
+        Self(a, b).normalized()
     }
 
     pub fn raw(ax: T, ay: T, az: T, bx: T, by: T, bz: T) -> Self {
         let pa = Point(ax, ay, az);
         let pb = Point(bx, by, bz);
-        Self(pa, pb)
+        Self::new(pa, pb)
     }
 
+    /// The same segment going from its lexicographically lower end to its higher end.
+    pub fn normalized(&self) -> Self {
+        let Route(a, b) = *self;
+        if b < a {
+            Self(b, a)
+        } else {
+            Self(a, b)
+        }
+    }
+}
+
+impl<T> Route<T>
+where
+    T: Copy + Num,
+{
     pub fn source(&self) -> Point<T> {
         self.0
     }
@@ -276,13 +354,146 @@ where
     }
 }
 
+impl MasterCell {
+    /// Creates a MasterCell, sorting pins and blockages by layer.
+    /// Only the first pin or blockage of every id is kept.
+    pub fn new(id: usize, mut pins: Vec<MasterPin>, mut blkgs: Vec<Blockage>) -> Self {
+        let mut seen = HashSet::new();
+        pins.retain(|pin| seen.insert(pin.id));
+        pins.sort_by_key(|pin| (pin.layer, pin.id));
+
+        seen.clear();
+        blkgs.retain(|blkg| seen.insert(blkg.id));
+        blkgs.sort_by_key(|blkg| (blkg.layer, blkg.id));
+
+        Self { id, pins, blkgs }
+    }
+
+    /// All pins, sorted by layer.
+    pub fn pins(&self) -> impl Iterator<Item = &MasterPin> {
+        self.pins.iter()
+    }
+
+    /// All blockages, sorted by layer.
+    pub fn blkgs(&self) -> impl Iterator<Item = &Blockage> {
+        self.blkgs.iter()
+    }
+
+    pub fn num_pins(&self) -> usize {
+        self.pins.len()
+    }
+
+    pub fn num_blkgs(&self) -> usize {
+        self.blkgs.len()
+    }
+
+    /// Pins on the layer with id `layer`.
+    pub fn pins_on(&self, layer: usize) -> &[MasterPin] {
+        let start = self.pins.partition_point(|pin| pin.layer < layer);
+        let end = self.pins.partition_point(|pin| pin.layer <= layer);
+        &self.pins[start..end]
+    }
+
+    /// Blockages on the layer with id `layer`.
+    pub fn blkgs_on(&self, layer: usize) -> &[Blockage] {
+        let start = self.blkgs.partition_point(|blkg| blkg.layer < layer);
+        let end = self.blkgs.partition_point(|blkg| blkg.layer <= layer);
+        &self.blkgs[start..end]
+    }
+
+    /// Demand a single instance imposes on its GGrid, by layer id, on a chip of `layers` layers:
+    /// its blockages, plus its `conflicts` with its own mastercell in the worst case,
+    /// another instance on the same GGrid and one on either side.
+    pub fn demand_footprint(
+        &self,
+        conflicts: Option<&HashSet<Conflict>>,
+        layers: usize,
+    ) -> Vec<usize> {
+        let mut footprint = vec![0; layers];
+        for blkg in self.blkgs.iter() {
+            if let Some(demand) = footprint.get_mut(blkg.layer) {
+                *demand += blkg.demand;
+            }
+        }
+
+        let own = conflicts
+            .into_iter()
+            .flatten()
+            .filter(|conflict| conflict.id == self.id);
+        for conflict in own {
+            let sides = match conflict.kind {
+                ConflictType::SameGGrid => 1,
+                ConflictType::AdjHGGrid => 2,
+            };
+            if let Some(demand) = footprint.get_mut(conflict.layer) {
+                *demand += sides * conflict.demand;
+            }
+        }
+
+        footprint
+    }
+
+    /// The pin with id `id`.
+    pub fn pin(&self, id: usize) -> Option<&MasterPin> {
+        self.pins.iter().find(|pin| pin.id == id)
+    }
+
+    /// Sets the offset of the pin with id `id`.
+    /// Returns `None` if there is no such pin.
+    pub fn set_pin_offset(&mut self, id: usize, offset: Pair<isize>) -> Option<()> {
+        let pin = self.pins.iter_mut().find(|pin| pin.id == id)?;
+        pin.offset = Some(offset);
+        Some(())
+    }
+}
+
 impl Layer {
     pub fn get_capacity(&self, row: usize, col: usize) -> Option<&usize> {
         self.capacity.get(row * self.dim.y() + col)
     }
 
     pub fn get_capacity_mut(&mut self, row: usize, col: usize) -> Option<&mut usize> {
-        self.capacity.get_mut(row * self.dim.y() + col)
+        let idx = row * self.dim.y() + col;
+        Arc::make_mut(&mut self.capacity).get_mut(idx)
+    }
+
+    pub fn get_demand(&self, row: usize, col: usize) -> Option<&usize> {
+        self.demand.get(row * self.dim.y() + col)
+    }
+
+    pub fn get_demand_mut(&mut self, row: usize, col: usize) -> Option<&mut usize> {
+        self.demand.get_mut(row * self.dim.y() + col)
+    }
+
+    pub fn get_base_demand(&self, row: usize, col: usize) -> Option<&usize> {
+        self.base_demand.get(row * self.dim.y() + col)
+    }
+
+    pub fn get_base_demand_mut(&mut self, row: usize, col: usize) -> Option<&mut usize> {
+        self.base_demand.get_mut(row * self.dim.y() + col)
+    }
+}
+
+impl Display for Provenance {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let SectionCounts {
+            layers,
+            non_default_supply,
+            mastercells,
+            conflicts,
+            cells,
+            nets,
+            routes,
+        } = self.counts;
+
+        writeln!(f, "Input {}", self.path.as_deref().unwrap_or("<string>"))?;
+        writeln!(f, "Hash {:016x} ({} bytes)", self.hash, self.size)?;
+        writeln!(f, "ParsedAt {}", self.parsed_at)?;
+        write!(
+            f,
+            "Layers {} NonDefaultSupply {} MasterCells {} Conflicts {} Cells {} Nets {} Routes {}",
+            layers, non_default_supply, mastercells, conflicts, cells, nets, routes
+        )
     }
 }
 
@@ -335,6 +546,38 @@ where
 }
 
 impl Route<usize> {
+    /// All points covered by the route, from `source` to `target`.
+    pub fn points(&self) -> Vec<Point<usize>> {
+        let Route(source, target) = self;
+        let (rlo, rhi) = (
+            source.row().min(target.row()),
+            source.row().max(target.row()),
+        );
+        let (clo, chi) = (
+            source.col().min(target.col()),
+            source.col().max(target.col()),
+        );
+        let (llo, lhi) = (
+            source.lay().min(target.lay()),
+            source.lay().max(target.lay()),
+        );
+
+        (rlo..=rhi)
+            .flat_map(|r| (clo..=chi).flat_map(move |c| (llo..=lhi).map(move |l| Point(r, c, l))))
+            .collect()
+    }
+
+    /// Checks if the route runs along a single axis, or is a single point.
+    pub fn is_straight(&self) -> bool {
+        let Route(source, target) = self;
+        let differs = [
+            source.row() != target.row(),
+            source.col() != target.col(),
+            source.lay() != target.lay(),
+        ];
+        differs.iter().filter(|&&differs| differs).count() <= 1
+    }
+
     /// Calculates the difference between `source` and `target`
     fn vector(&self) -> Point<isize> {
         let Route(source, target) = self;
@@ -346,11 +589,10 @@ impl Route<usize> {
     }
 
     /// Categorizes the result of `vector`.
-    pub fn towards(&self) -> Towards {
-        match self.vector() {
-            // A vector can only be of (a, 0, 0), (0, b, 0), (0, 0, c) with a, b, c != 0
-            // Hence the unreachable arm.
-            Point(0, 0, 0) => unreachable!(),
+    /// Returns `Err(ShapeError)` for a single point or a diagonal route.
+    pub fn towards(&self) -> Result<Towards> {
+        let towards = match self.vector() {
+            Point(0, 0, 0) => return Err(Error::from(ShapeError(*self))),
             Point(row, 0, 0) => {
                 if row > 0 {
                     Towards::Right
@@ -372,8 +614,32 @@ impl Route<usize> {
                     Towards::Bottom
                 }
             }
-            _ => unreachable!(),
-        }
+            _ => return Err(Error::from(ShapeError(*self))),
+        };
+        Ok(towards)
+    }
+}
+
+impl<T> PartialEq for Route<T>
+where
+    T: Copy + Num + PartialOrd,
+{
+    fn eq(&self, other: &Self) -> bool {
+        let (Route(a, b), Route(c, d)) = (self.normalized(), other.normalized());
+        a == c && b == d
+    }
+}
+
+impl<T> Eq for Route<T> where T: Copy + Num + PartialOrd {}
+
+impl<T> Hash for Route<T>
+where
+    T: Copy + Hash + Num + PartialOrd,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let Route(a, b) = self.normalized();
+        a.hash(state);
+        b.hash(state);
     }
 }
 
@@ -431,24 +697,109 @@ impl PosNode {
     }
 
     /// Get the index of a neighboring node.
-    /// As the tree is 2D, `Top` and `Bottom` are not allowed as input.
+    /// As the tree is 2D, there is none towards `Top` or `Bottom`.
     pub fn index(&self, towards: Towards) -> Option<Pointer> {
         match towards {
             Towards::Up => self.up,
             Towards::Down => self.down,
             Towards::Left => self.left,
             Towards::Right => self.right,
-            Towards::Top | Towards::Bottom => unreachable!(),
+            Towards::Top | Towards::Bottom => None,
         }
     }
 }
 
-impl Net {}
+impl Net {
+    /// Checks if a route segment runs planar below the net's min layer.
+    pub fn is_below_min_layer(&self, route: &Route<usize>) -> bool {
+        route.source().lay() == route.target().lay() && route.source().lay() <= self.min_layer
+    }
+
+    /// Number of route segments written to the output.
+    pub fn num_routes(&self) -> usize {
+        self.normalized_routes().len()
+    }
+
+    /// Replaces the routes with their normalized form, see `normalized_routes`.
+    /// The GGrids the net occupies don't change.
+    pub fn canonicalize(&mut self) {
+        self.routes = self.normalized_routes().into_iter().collect();
+    }
+
+    /// Route segments in the form written to the output, see `merge_routes`.
+    pub fn normalized_routes(&self) -> Vec<Route<usize>> {
+        merge_routes(self.routes.iter())
+    }
+}
+
+/// Merges route segments into the form written to the output.
+/// Segments on the same line that overlap or touch are merged,
+/// so split vias `(r, c, 1) (r, c, 2)`, `(r, c, 2) (r, c, 3)` and a merged via
+/// `(r, c, 1) (r, c, 3)` give the same result.
+/// Every segment goes from its lower end to its higher end, and segments are sorted.
+pub fn merge_routes<'a>(routes: impl IntoIterator<Item = &'a Route<usize>>) -> Vec<Route<usize>> {
+    let coord = |point: Point<usize>, axis: usize| match axis {
+        0 => point.row(),
+        1 => point.col(),
+        _ => point.lay(),
+    };
+    let with = |Point(row, col, lay): Point<usize>, axis: usize, value: usize| match axis {
+        0 => Point(value, col, lay),
+        1 => Point(row, value, lay),
+        _ => Point(row, col, value),
+    };
+
+    // unit steps grouped by line, a step from `v` to `v + 1` is stored as `v`
+    let mut lines: HashMap<(usize, Point<usize>), BTreeSet<usize>> = HashMap::new();
+    let mut merged = Vec::new();
+    for &Route(source, target) in routes {
+        let axes: Vec<_> = (0..3)
+            .filter(|&axis| coord(source, axis) != coord(target, axis))
+            .collect();
+        match axes[..] {
+            [axis] => {
+                let (lo, hi) = (coord(source, axis), coord(target, axis));
+                let (lo, hi) = (lo.min(hi), lo.max(hi));
+                lines
+                    .entry((axis, with(source, axis, 0)))
+                    .or_default()
+                    .extend(lo..hi);
+            }
+            // Points are not segments, and diagonals are left for the verifier to report.
+            [] => {}
+            _ => merged.push(Route::new(source, target)),
+        }
+    }
+
+    for ((axis, base), steps) in lines {
+        let mut steps = steps.into_iter();
+        let mut run = match steps.next() {
+            Some(first) => (first, first + 1),
+            None => continue,
+        };
+        for step in steps {
+            if step == run.1 {
+                run.1 += 1;
+            } else {
+                merged.push(Route(with(base, axis, run.0), with(base, axis, run.1)));
+                run = (step, step + 1);
+            }
+        }
+        merged.push(Route(with(base, axis, run.0), with(base, axis, run.1)));
+    }
+
+    merged.sort_unstable_by_key(|route| (route.source(), route.target()));
+    merged
+}
 
 impl Display for Net {
     /// Converts `Net` to `String`
-    fn fmt(&self, _f: &mut Formatter) -> FmtResult {
-        unimplemented!()
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let name = Self::from_num(self.id).map_err(|_| FmtError)?;
+        for route in self.normalized_routes() {
+            writeln!(f, "{} {}", route, name)?;
+        }
+        Ok(())
     }
 }
 
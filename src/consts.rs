@@ -1,3 +1,88 @@
 pub const SECS_PER_MIN: u64 = 60;
 pub const MINS_PER_HR: u64 = 60;
 pub const SECS_PER_HR: u64 = SECS_PER_MIN * MINS_PER_HR;
+
+/// Pin offsets are measured in thousandths of a GGrid.
+pub const OFFSETS_PER_GGRID: isize = 1000;
+
+/// A conservative guess of how fast the output is formatted and written, in bytes per second.
+pub const WRITE_BYTES_PER_SEC: u64 = 20_000_000;
+/// Time always reserved for writing the output, in milliseconds.
+pub const MIN_WRITE_MARGIN_MS: u64 = 500;
+
+/// Most errors and warnings collected before the rest are only counted.
+pub const MAX_DIAGNOSTICS: usize = 20;
+
+/// Restarts perturb an order by swapping every item with one at most this many places later.
+pub const PERTURB_WINDOW: usize = 8;
+
+/// Number of recent iterations a throttle averages gains over.
+pub const THROTTLE_WINDOW: usize = 64;
+
+/// Nets with more pins only get a spanning tree instead of a Steiner tree.
+pub const STEINER_MAX_PINS: usize = 24;
+/// Nets with at least this many pins are clustered, see `Topology::clustered`.
+pub const CLUSTER_MIN_PINS: usize = 128;
+
+/// GGrids around a net's bounding box its search may use at first.
+pub const BOUNDING_BOX_MARGIN: usize = 2;
+
+/// Nets looked ahead for a wave of nets routed in parallel.
+pub const PARALLEL_WINDOW: usize = 64;
+/// Largest side of a tile nets lock to be routed in parallel, in GGrids.
+pub const PARALLEL_MAX_TILE: usize = 64;
+
+/// Positions tried for every branch point when sliding it, see `relocate_steiner_points`.
+pub const STEINER_SLIDE_CANDIDATES: usize = 8;
+
+/// GGrids around a blocked pin an escape path may reach.
+pub const ESCAPE_RADIUS: usize = 4;
+/// Cost of passing a full GGrid on an escape path, in steps.
+pub const ESCAPE_OVERFLOW_COST: usize = 1000;
+
+/// Partially routed nets with at least this many pins are completed by growing their tree.
+pub const TREE_MIN_PINS: usize = 8;
+
+/// Nets with at most this many pins look up their Steiner topology in a table.
+pub const FLUTE_MAX_PINS: usize = 9;
+
+/// Cost added to the history of a GGrid per unit of overflow after every rip-up iteration.
+pub const HISTORY_INCREMENT: usize = 500;
+/// Thousandths of its history a GGrid keeps after every rip-up iteration.
+pub const HISTORY_DECAY: usize = 900;
+/// The present congestion weight is multiplied by this (in thousandths) every iteration.
+pub const PRESENT_GROWTH: usize = 1500;
+/// Negotiation stops after this many iterations without reducing overflow.
+pub const NEGOTIATE_PATIENCE: usize = 16;
+/// Difficulty of a net per pin, in thousandths.
+pub const DIFFICULTY_PIN_WEIGHT: usize = 100;
+/// Most iterations of the force-directed mover.
+pub const FORCE_ITERATIONS: usize = 8;
+/// Push of congestion on a cell in millionths of a GGrid per thousandth of usage difference.
+pub const FORCE_REPULSION: usize = 2000;
+/// Nets two cells must share to be clustered together.
+pub const CLUSTER_MIN_SHARED_NETS: usize = 2;
+/// Nets with more pins don't tie their cells into a cluster.
+pub const CLUSTER_MAX_NET_PINS: usize = 8;
+/// Most cells of a cluster.
+pub const CLUSTER_MAX_CELLS: usize = 16;
+/// Shares the time of `evolve` is split into, one per generation and one to route the result.
+pub const EVOLVE_GENERATIONS: u32 = 8;
+/// Overflowed GGrids the `hotspots` pass repairs by default.
+pub const HOTSPOT_COUNT: usize = 16;
+/// Regions at most this many GGrids along both sides aren't divided further by quadrisection.
+pub const PARTITION_MIN_SIZE: usize = 4;
+/// Thousandths of its share of the weight a quadrant may take on top of it.
+pub const PARTITION_TOLERANCE: usize = 100;
+/// GGrids along each side of a window placed by branch and bound.
+pub const WINDOW_SIZE: usize = 3;
+/// Most cells of a window placed by branch and bound.
+pub const WINDOW_MAX_CELLS: usize = 4;
+/// Margin in GGrids around a tiny net's bounding box the exact router searches.
+#[cfg(feature = "exact")]
+pub const EXACT_MARGIN: usize = 1;
+/// Largest number of positions the exact router searches for a net.
+#[cfg(feature = "exact")]
+pub const EXACT_MAX_AREA: usize = 256;
+/// The present congestion weight grows by this (in thousandths) over a soft-overflow pass.
+pub const SOFT_PRESENT_RAMP: usize = 4000;
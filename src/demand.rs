@@ -0,0 +1,497 @@
+use crate::{
+    chip::Chip,
+    components::{Cell, CellType, ConflictType, Net, Pair, Point},
+    rules::PinDemand,
+};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+
+impl Chip {
+    /// Recalculates the demand of every GGrid from scratch.
+    /// Demand comes from net routing, blockages and conflicting mastercells.
+    /// Only demand caused by movable cells and routing is recalculated,
+    /// the demand of fixed cells is copied from `base_demand`.
+    pub fn update_demand(&mut self) -> Result<()> {
+        for layer in self.layers.iter_mut() {
+            layer.demand.copy_from_slice(&layer.base_demand);
+        }
+
+        let pin_points = self.pin_points();
+
+        let routing: Vec<_> = self
+            .nets
+            .iter()
+            .flat_map(|net| self.net_grids(net, &pin_points))
+            .map(|point| (point, 1))
+            .collect();
+
+        let blockages = self.blockage_demand(|cell| matches!(cell.movable, CellType::Movable));
+
+        // Conflicts among fixed cells only are part of the base demand.
+        let mut conflicts = Self::sum_demand(self.conflict_demand(|_| true));
+        for (point, amount) in self.conflict_demand(|cell| matches!(cell.movable, CellType::Fixed))
+        {
+            if let Some(total) = conflicts.get_mut(&point) {
+                *total = total.saturating_sub(amount);
+            }
+        }
+
+        for (point, amount) in routing.into_iter().chain(blockages).chain(conflicts) {
+            self.add_demand(point, amount)?;
+        }
+
+        Ok(())
+    }
+
+    /// Calculates the demand fixed cells cause with their blockages and conflicts,
+    /// which stays the same however the movable cells move.
+    pub fn bake_base_demand(&mut self) -> Result<()> {
+        for layer in self.layers.iter_mut() {
+            layer.base_demand.iter_mut().for_each(|d| *d = 0);
+        }
+
+        let fixed = |cell: &Cell| matches!(cell.movable, CellType::Fixed);
+        let blockages = self.blockage_demand(fixed);
+        let conflicts = self.conflict_demand(fixed);
+
+        for (point, amount) in blockages.into_iter().chain(conflicts) {
+            self.check_bounds(point)?;
+            let Point(row, col, lay) = point;
+            if let Some(demand) = self
+                .get_layer_mut(lay - 1)
+                .and_then(|layer| layer.get_base_demand_mut(row - 1, col - 1))
+            {
+                *demand = demand.saturating_add(amount);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Demand of the blockages of the cells accepted by `filter`.
+    fn blockage_demand<F>(&self, filter: F) -> Vec<(Point<usize>, usize)>
+    where
+        F: Fn(&Cell) -> bool,
+    {
+        self.cells
+            .iter()
+            .filter(|cell| filter(cell))
+            .flat_map(|cell| {
+                let position = cell.position;
+                self.mastercells[cell.mastercell]
+                    .blkgs()
+                    .map(move |blkg| (position.with(blkg.layer + 1), blkg.demand))
+            })
+            .collect()
+    }
+
+    /// Demand that moves with a movable cell: its blockages, the GGrids of its nets,
+    /// see `own_net_grids`, and the conflict demand of movable cells on the GGrids
+    /// a cell at any of `positions` could conflict on, counted like `update_demand` does.
+    pub(crate) fn cell_demand(
+        &self,
+        idx: usize,
+        positions: &[Pair<usize>],
+    ) -> Vec<(Point<usize>, usize)> {
+        let cell = &self.cells[idx];
+        let mut demand: Vec<_> = self.mastercells[cell.mastercell]
+            .blkgs()
+            .map(|blkg| (cell.position.with(blkg.layer + 1), blkg.demand))
+            .collect();
+
+        for net in self.cell_nets(idx) {
+            let grids = self.own_net_grids(&self.nets[net]);
+            demand.extend(grids.into_iter().map(|point| (point, 1)));
+        }
+
+        // Without conflicts of its own the cell takes no part in any, see `conflict_demand`.
+        if !self.conflicts.contains_key(&cell.mastercell) {
+            return demand;
+        }
+        // Conflicts reach one GGrid left and right, and their demand depends on the cells
+        // one GGrid further.
+        let within = |reach: usize| -> HashSet<Pair<usize>> {
+            positions
+                .iter()
+                .flat_map(|&Pair(row, col)| {
+                    (col.saturating_sub(reach)..=col + reach).map(move |col| Pair(row, col))
+                })
+                .collect()
+        };
+        let (around, near) = (within(1), within(2));
+        let nearby = |cell: &Cell| near.contains(&cell.position);
+        let mut conflicts = Self::sum_demand(self.conflict_demand(nearby));
+        for (point, amount) in
+            self.conflict_demand(|cell| nearby(cell) && matches!(cell.movable, CellType::Fixed))
+        {
+            if let Some(total) = conflicts.get_mut(&point) {
+                *total = total.saturating_sub(amount);
+            }
+        }
+        demand.extend(
+            conflicts
+                .into_iter()
+                .filter(|&(point, amount)| amount > 0 && around.contains(&point.flatten())),
+        );
+
+        demand
+    }
+
+    /// Adds up demand on the same GGrid.
+    fn sum_demand(demand: Vec<(Point<usize>, usize)>) -> HashMap<Point<usize>, usize> {
+        let mut sums = HashMap::new();
+        for (point, amount) in demand {
+            let sum: &mut usize = sums.entry(point).or_default();
+            *sum = sum.saturating_add(amount);
+        }
+        sums
+    }
+
+    /// The points (row, column, layer) of all pins, indexed by pin id.
+    pub(crate) fn pin_points(&self) -> Vec<Point<usize>> {
+        let num_pins = self.cells.iter().map(|cell| cell.pins.len()).sum();
+        let mut points = vec![Point::default(); num_pins];
+
+        for cell in self.cells.iter() {
+            let mc = &self.mastercells[cell.mastercell];
+            for pin in mc.pins() {
+                points[cell.pins[pin.id]] = cell.position.with(pin.layer + 1);
+            }
+        }
+
+        points
+    }
+
+    /// The owning cell and master pin of all pins, indexed by pin id.
+    pub(crate) fn collect_pin_owners(&self) -> Vec<(usize, usize)> {
+        let num_pins = self.cells.iter().map(|cell| cell.pins.len()).sum();
+        let mut owners = vec![(0, 0); num_pins];
+
+        for cell in self.cells.iter() {
+            for (mp, &pin) in cell.pins.iter().enumerate() {
+                owners[pin] = (cell.id, mp);
+            }
+        }
+
+        owners
+    }
+
+    /// The net of all pins, `None` for pins not on any net, indexed by pin id.
+    pub(crate) fn collect_pin_nets(&self) -> Vec<Option<usize>> {
+        let num_pins = self.cells.iter().map(|cell| cell.pins.len()).sum();
+        let mut nets = vec![None; num_pins];
+
+        for net in self.nets.iter() {
+            for &pin in net.pins.iter() {
+                nets[pin] = Some(net.id);
+            }
+        }
+
+        nets
+    }
+
+    /// Ids of the nets connected to a cell, without duplicates.
+    pub fn cell_nets(&self, idx: usize) -> Vec<usize> {
+        let mut nets: Vec<_> = self.cells.get(idx).map_or_else(Vec::new, |cell| {
+            cell.pins
+                .iter()
+                .filter_map(|&pin| self.pin_nets.get(pin).copied().flatten())
+                .collect()
+        });
+        nets.sort_unstable();
+        nets.dedup();
+        nets
+    }
+
+    /// The owning cell and master pin of a pin.
+    pub fn pin_owner(&self, pin: usize) -> Option<(usize, usize)> {
+        self.pin_owners.get(pin).copied()
+    }
+
+    /// The layer id (starts from 0) of a pin.
+    pub fn pin_layer(&self, pin: usize) -> Option<usize> {
+        let (cell, mp) = self.pin_owner(pin)?;
+        self.mastercells[self.cells[cell].mastercell]
+            .pin(mp)
+            .map(|pin| pin.layer)
+    }
+
+    /// The point (row, column, layer) of a pin.
+    pub fn pin_point(&self, pin: usize) -> Option<Point<usize>> {
+        let (cell, _) = self.pin_owner(pin)?;
+        Some(self.cells[cell].position.with(self.pin_layer(pin)? + 1))
+    }
+
+    /// All GGrids a net occupies.
+    /// Pins are included only if the rules say they consume demand.
+    pub fn net_grids(&self, net: &Net, pin_points: &[Point<usize>]) -> HashSet<Point<usize>> {
+        self.grids_with_pins(net, |pin| pin_points.get(pin).copied())
+    }
+
+    /// Like `net_grids`, but looks up only the net's own pins, see `pin_point`.
+    pub fn own_net_grids(&self, net: &Net) -> HashSet<Point<usize>> {
+        self.grids_with_pins(net, |pin| self.pin_point(pin))
+    }
+
+    /// The GGrids of a net's routes, and of its pins at `pin_point` if they consume demand.
+    fn grids_with_pins<F>(&self, net: &Net, pin_point: F) -> HashSet<Point<usize>>
+    where
+        F: Fn(usize) -> Option<Point<usize>>,
+    {
+        let mut grids: HashSet<_> = net.routes.iter().flat_map(|route| route.points()).collect();
+
+        if self.rules.pin_demand() == PinDemand::Consume {
+            grids.extend(net.pins.iter().filter_map(|&pin| pin_point(pin)));
+        }
+
+        grids
+    }
+
+    /// Wirelength of a net: the number of GGrids it occupies.
+    /// GGrids only reached by vias are skipped if the rules don't count vias.
+    pub fn net_length(&self, net: &Net) -> usize {
+        if self.rules.count_vias() {
+            return net
+                .routes
+                .iter()
+                .flat_map(|route| route.points())
+                .collect::<HashSet<_>>()
+                .len();
+        }
+
+        net.routes
+            .iter()
+            .filter(|route| route.source().lay() == route.target().lay())
+            .flat_map(|route| route.points())
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Extra demand caused by conflicting mastercells on the same or adjacent GGrids,
+    /// counting only the cells accepted by `filter`.
+    fn conflict_demand<F>(&self, filter: F) -> Vec<(Point<usize>, usize)>
+    where
+        F: Fn(&Cell) -> bool,
+    {
+        // mastercell counts on every GGrid
+        let mut counts: HashMap<Pair<usize>, HashMap<usize, usize>> = HashMap::new();
+        for cell in self.cells.iter().filter(|cell| filter(cell)) {
+            *counts
+                .entry(cell.position)
+                .or_default()
+                .entry(cell.mastercell)
+                .or_default() += 1;
+        }
+
+        let count = |position: Pair<usize>, mc: usize| -> usize {
+            counts
+                .get(&position)
+                .and_then(|map| map.get(&mc))
+                .copied()
+                .unwrap_or(0)
+        };
+
+        let mut demand = Vec::new();
+        for (&position, mcs) in counts.iter() {
+            for (&mc, &num) in mcs.iter() {
+                let conflicts = match self.conflicts.get(&mc) {
+                    Some(conflicts) => conflicts,
+                    None => continue,
+                };
+
+                for conflict in conflicts.iter() {
+                    let lay = conflict.layer + 1;
+                    match conflict.kind {
+                        ConflictType::SameGGrid => {
+                            // Conflicts are stored in both directions, count each pair once.
+                            if mc > conflict.id {
+                                continue;
+                            }
+                            let other = count(position, conflict.id);
+                            let pairs = self.rules.conflict_pairs(
+                                conflict.kind,
+                                mc == conflict.id,
+                                num,
+                                other,
+                            );
+                            if pairs > 0 {
+                                demand.push((position.with(lay), pairs * conflict.demand));
+                            }
+                        }
+                        ConflictType::AdjHGGrid => {
+                            // Only look to the right, the left side is handled by the other GGrid.
+                            let Pair(row, col) = position;
+                            let right = Pair(row, col + 1);
+                            let other = count(right, conflict.id);
+                            let pairs = self.rules.conflict_pairs(
+                                conflict.kind,
+                                mc == conflict.id,
+                                num,
+                                other,
+                            );
+                            if pairs > 0 {
+                                demand.push((position.with(lay), pairs * conflict.demand));
+                                demand.push((right.with(lay), pairs * conflict.demand));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        demand
+    }
+
+    /// Cells on every GGrid, for `placement_demand` and `placement_fits`.
+    pub fn cells_by_position(&self) -> HashMap<Pair<usize>, Vec<usize>> {
+        let mut cells: HashMap<_, Vec<_>> = HashMap::new();
+        for cell in self.cells.iter() {
+            cells.entry(cell.position).or_default().push(cell.id);
+        }
+        cells
+    }
+
+    /// Demand a cell would add by being placed at `target`: its blockages,
+    /// and the extra demand of its conflicts with the other cells on `target`
+    /// and on the GGrids left and right of it, counted like `conflict_demand` does.
+    /// `occupants` are the cells on every GGrid, see `cells_by_position`.
+    /// The cell itself is not counted where it is now.
+    pub fn placement_demand(
+        &self,
+        idx: usize,
+        target: Pair<usize>,
+        occupants: &HashMap<Pair<usize>, Vec<usize>>,
+    ) -> Vec<(Point<usize>, usize)> {
+        let mc = match self.cells.get(idx) {
+            Some(cell) => cell.mastercell,
+            None => return Vec::new(),
+        };
+        let count = |position: Pair<usize>, of: usize| {
+            occupants.get(&position).map_or(0, |cells| {
+                cells
+                    .iter()
+                    .filter(|&&other| other != idx && self.cells[other].mastercell == of)
+                    .count()
+            })
+        };
+        let added =
+            |kind: ConflictType, other: usize, before: (usize, usize), after: (usize, usize)| {
+                let same = mc == other;
+                self.rules
+                    .conflict_pairs(kind, same, after.0, after.1)
+                    .saturating_sub(self.rules.conflict_pairs(kind, same, before.0, before.1))
+            };
+
+        let mut demand: Vec<_> = self.mastercells[mc]
+            .blkgs()
+            .map(|blkg| (target.with(blkg.layer + 1), blkg.demand))
+            .collect();
+
+        let conflicts = match self.conflicts.get(&mc) {
+            Some(conflicts) => conflicts,
+            None => return demand,
+        };
+        let Pair(row, col) = target;
+        let here = count(target, mc);
+        for conflict in conflicts.iter() {
+            let (lay, other) = (conflict.layer + 1, conflict.id);
+            match conflict.kind {
+                ConflictType::SameGGrid => {
+                    // The lower mastercell of a pair comes first, as in `conflict_demand`.
+                    let there = count(target, other);
+                    let pairs = if mc == other {
+                        added(conflict.kind, other, (here, here), (here + 1, here + 1))
+                    } else if mc < other {
+                        added(conflict.kind, other, (here, there), (here + 1, there))
+                    } else {
+                        added(conflict.kind, other, (there, here), (there, here + 1))
+                    };
+                    demand.push((target.with(lay), pairs * conflict.demand));
+                }
+                ConflictType::AdjHGGrid => {
+                    let right = Pair(row, col + 1);
+                    let there = count(right, other);
+                    let pairs = added(conflict.kind, other, (here, there), (here + 1, there));
+                    demand.push((target.with(lay), pairs * conflict.demand));
+                    demand.push((right.with(lay), pairs * conflict.demand));
+
+                    // Conflicts are stored in both directions, so this one also stands
+                    // for the other mastercell on the left.
+                    if col > 1 {
+                        let left = Pair(row, col - 1);
+                        let there = count(left, other);
+                        let pairs = added(conflict.kind, other, (there, here), (there, here + 1));
+                        demand.push((left.with(lay), pairs * conflict.demand));
+                        demand.push((target.with(lay), pairs * conflict.demand));
+                    }
+                }
+            }
+        }
+
+        let mut demand: Vec<_> = Self::sum_demand(demand)
+            .into_iter()
+            .filter(|&(point, amount)| amount > 0 && self.in_bounds(point))
+            .collect();
+        demand.sort_unstable();
+        demand
+    }
+
+    /// Checks if a cell can be placed at `target` without its `placement_demand`
+    /// exceeding the supply of any layer of the GGrids it touches.
+    /// The cell's demand where it is now is not taken off first.
+    pub fn placement_fits(
+        &self,
+        idx: usize,
+        target: Pair<usize>,
+        occupants: &HashMap<Pair<usize>, Vec<usize>>,
+    ) -> bool {
+        self.placement_demand(idx, target, occupants)
+            .into_iter()
+            .all(|(Point(row, col, lay), amount)| {
+                let layer = &self.layers[lay - 1];
+                match (
+                    layer.get_capacity(row - 1, col - 1),
+                    layer.get_demand(row - 1, col - 1),
+                ) {
+                    (Some(&capacity), Some(&used)) => used.saturating_add(amount) <= capacity,
+                    _ => false,
+                }
+            })
+    }
+
+    /// Adds demand to a GGrid. `point` is indexed the same way as in the input file.
+    /// Returns `Err(BoundsError)` if `point` is not on the chip.
+    pub fn add_demand(&mut self, point: Point<usize>, amount: usize) -> Result<()> {
+        self.check_bounds(point)?;
+
+        let Point(row, col, lay) = point;
+        if let Some(demand) = self
+            .get_layer_mut(lay - 1)
+            .and_then(|layer| layer.get_demand_mut(row - 1, col - 1))
+        {
+            *demand = demand.saturating_add(amount);
+        }
+
+        Ok(())
+    }
+
+    /// Removes demand from a GGrid. `point` is indexed the same way as in the input file.
+    /// Demand never drops below the base demand of fixed cells.
+    /// Returns `Err(BoundsError)` if `point` is not on the chip.
+    pub fn remove_demand(&mut self, point: Point<usize>, amount: usize) -> Result<()> {
+        self.check_bounds(point)?;
+
+        let Point(row, col, lay) = point;
+        if let Some(layer) = self.get_layer_mut(lay - 1) {
+            let base = layer
+                .get_base_demand(row - 1, col - 1)
+                .copied()
+                .unwrap_or(0);
+            if let Some(demand) = layer.get_demand_mut(row - 1, col - 1) {
+                *demand = demand.saturating_sub(amount).max(base);
+            }
+        }
+
+        Ok(())
+    }
+}
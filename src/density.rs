@@ -0,0 +1,101 @@
+use crate::{chip::Chip, components::Pair, consts::OFFSETS_PER_GGRID};
+
+/// How crowded every GGrid is with cells and blockages.
+/// Occupancy is the number of cells plus the blockage demand on a GGrid,
+/// density is occupancy divided by the capacity summed over all layers.
+#[derive(Clone, Debug, Default)]
+pub struct DensityMap {
+    /// dimensions
+    pub dim: Pair<usize>,
+    /// occupancy of every GGrid
+    pub occupancy: Vec<usize>,
+    /// capacity of every GGrid summed over all layers
+    pub capacity: Vec<usize>,
+    /// the highest density allowed before being penalized
+    pub target: Option<f64>,
+}
+
+impl Chip {
+    /// Counts cells and blockages on every GGrid.
+    pub fn density_map(&self) -> DensityMap {
+        let Pair(rows, cols) = self.dim;
+        let mut capacity = vec![0usize; self.dim.size()];
+        for layer in self.layers.iter() {
+            for row in 0..rows {
+                for col in 0..cols {
+                    let supply = layer.get_capacity(row, col).copied().unwrap_or(0);
+                    let total = &mut capacity[row * cols + col];
+                    *total = total.saturating_add(supply);
+                }
+            }
+        }
+
+        let mut map = DensityMap {
+            dim: self.dim,
+            occupancy: vec![0; self.dim.size()],
+            capacity,
+            target: self.target_density,
+        };
+        for cell in self.cells.iter() {
+            if let Some(idx) = Chip::grid_index(map.dim, cell.position) {
+                map.occupancy[idx] = map.occupancy[idx].saturating_add(self.cell_weight(cell.id));
+            }
+        }
+
+        map
+    }
+
+    /// Occupancy a cell adds to its GGrid: itself plus the demand of its blockages.
+    pub fn cell_weight(&self, idx: usize) -> usize {
+        self.cells.get(idx).map_or(0, |cell| {
+            self.mastercells[cell.mastercell]
+                .blkgs()
+                .map(|blkg| blkg.demand)
+                .fold(1, usize::saturating_add)
+        })
+    }
+}
+
+impl DensityMap {
+    /// Density of a GGrid, infinite if an occupied GGrid has no capacity.
+    pub fn density(&self, position: Pair<usize>) -> f64 {
+        let idx = match Chip::grid_index(self.dim, position) {
+            Some(idx) => idx,
+            None => return 0.,
+        };
+        match (self.occupancy[idx], self.capacity[idx]) {
+            (0, _) => 0.,
+            (_, 0) => f64::INFINITY,
+            (occupancy, capacity) => occupancy as f64 / capacity as f64,
+        }
+    }
+
+    /// Occupancy above the target on a GGrid if `extra` more were added.
+    pub fn excess(&self, position: Pair<usize>, extra: usize) -> usize {
+        let (target, idx) = match (self.target, Chip::grid_index(self.dim, position)) {
+            (Some(target), Some(idx)) => (target, idx),
+            _ => return 0,
+        };
+        let allowed = (target * self.capacity[idx] as f64).floor() as usize;
+        self.occupancy[idx]
+            .saturating_add(extra)
+            .saturating_sub(allowed)
+    }
+
+    /// Penalty of putting `weight` more occupancy on a GGrid, in thousandths of a GGrid.
+    pub fn penalty(&self, position: Pair<usize>, weight: usize) -> usize {
+        let before = self.excess(position, 0);
+        let after = self.excess(position, weight);
+        (after - before) * OFFSETS_PER_GGRID as usize
+    }
+
+    /// Moves `weight` occupancy from one GGrid to another.
+    pub fn move_weight(&mut self, from: Pair<usize>, to: Pair<usize>, weight: usize) {
+        if let Some(idx) = Chip::grid_index(self.dim, from) {
+            self.occupancy[idx] = self.occupancy[idx].saturating_sub(weight);
+        }
+        if let Some(idx) = Chip::grid_index(self.dim, to) {
+            self.occupancy[idx] = self.occupancy[idx].saturating_add(weight);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{chip::Chip, components::Point};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// How hard a net is to route, from what is known before routing it again.
+/// Every part is in thousandths, see `Chip::net_difficulty`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Difficulty {
+    /// demand over capacity inside the net's bounding box
+    pub congestion: usize,
+    /// pins of the net, `DIFFICULTY_PIN_WEIGHT` each
+    pub pins: usize,
+    /// share of the layers below the net's min layer
+    pub min_layer: usize,
+    /// wirelength of the net's routes above its lower bound, relative to the bound
+    pub detour: usize,
+}
+
+impl Difficulty {
+    /// The parts summed into one score, the higher the harder.
+    pub fn score(&self) -> usize {
+        self.congestion
+            .saturating_add(self.pins)
+            .saturating_add(self.min_layer)
+            .saturating_add(self.detour)
+    }
+}
+
+/// The hardest nets of a chip, which likely dominate runtime and overflow.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DifficultyReport {
+    /// every net id with its difficulty, hardest first
+    pub nets: Vec<(usize, Difficulty)>,
+    /// most nets listed by `Display`
+    pub limit: usize,
+}
+
+impl Chip {
+    /// Estimates how hard a net is to route, see `Difficulty`.
+    /// The detour is measured against `lower_bound`.
+    pub fn net_difficulty(&self, id: usize, pin_points: &[Point<usize>]) -> Difficulty {
+        use crate::consts::DIFFICULTY_PIN_WEIGHT;
+
+        let net = match self.nets.get(id) {
+            Some(net) => net,
+            None => return Difficulty::default(),
+        };
+        let bound = self.lower_bound(net, pin_points);
+        let length = self.net_length(net);
+
+        Difficulty {
+            congestion: self.bounding_congestion(id),
+            pins: net.pins.len().saturating_mul(DIFFICULTY_PIN_WEIGHT),
+            min_layer: net.min_layer * 1000 / self.layers.len().max(1),
+            detour: length.saturating_sub(bound).saturating_mul(1000) / bound.max(1),
+        }
+    }
+
+    /// The difficulty of every net, hardest first, see `DifficultyReport`.
+    pub fn difficulty_report(&self) -> DifficultyReport {
+        use crate::consts::MAX_DIAGNOSTICS;
+
+        let pin_points = self.pin_points();
+        let mut nets: Vec<_> = (0..self.nets.len())
+            .map(|id| (id, self.net_difficulty(id, &pin_points)))
+            .collect();
+        nets.sort_by_key(|&(id, difficulty)| (std::cmp::Reverse(difficulty.score()), id));
+
+        DifficultyReport {
+            nets,
+            limit: self.max_diagnostics.unwrap_or(MAX_DIAGNOSTICS),
+        }
+    }
+}
+
+impl Display for DifficultyReport {
+    /// The hardest nets with the parts of their difficulty, one per line.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Hardest nets of {}", self.nets.len())?;
+        for &(id, difficulty) in self.nets.iter().take(self.limit) {
+            write!(
+                f,
+                "\nN{}: {} (congestion {}, pins {}, min layer {}, detour {})",
+                id + 1,
+                difficulty.score(),
+                difficulty.congestion,
+                difficulty.pins,
+                difficulty.min_layer,
+                difficulty.detour
+            )?;
+        }
+        if self.nets.len() > self.limit {
+            write!(f, "\n... {} more not shown", self.nets.len() - self.limit)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{args::Args, chip::Chip, force::Mover, utilities};
+use anyhow::Result;
+use std::time::Instant;
+
+impl Chip {
+    /// A copy of the chip's design, current state and fallback, configured by `args`
+    /// except that it never autosaves or checkpoints, so only the chip itself writes the output.
+    /// Learned state such as congestion history starts over.
+    pub fn variant(&self, args: &Args) -> Result<Chip> {
+        let mut chip = Chip {
+            max_move: self.max_move,
+            already_moved: self.already_moved,
+            dim: self.dim,
+            layers: self.layers.clone(),
+            mastercells: self.mastercells.clone(),
+            cells: self.cells.clone(),
+            nets: self.nets.clone(),
+            pin_owners: self.pin_owners.clone(),
+            pin_nets: self.pin_nets.clone(),
+            dirty_nets: self.dirty_nets.clone(),
+            conflicts: self.conflicts.clone(),
+            layer_ids: self.layer_ids.clone(),
+            provenance: self.provenance.clone(),
+            clamped_supply: self.clamped_supply.clone(),
+            fallback: self.fallback.clone(),
+            original: self.original.clone(),
+            ..Chip::default()
+        };
+        chip.configure(&Args {
+            autosave: None,
+            strict_legal: false,
+            ..args.clone()
+        })?;
+        Ok(chip)
+    }
+
+    /// Optimizes a population of `population` placement variants in parallel,
+    /// each a copy of the chip, see `variant`.
+    /// Every generation mutates each variant with a move operator of its own for an even share
+    /// of the time, see `move_and_route`, the operators being the `Mover`s in turn.
+    /// The worse half of the population then takes over the state of the better half,
+    /// so the best variants survive while the operators keep them apart.
+    /// There are at most `EVOLVE_GENERATIONS - 1` generations, leaving the last share of the time
+    /// to route the result; they stop early once one moves nothing.
+    /// The best state seen, see `quality`, is restored at the end.
+    /// Returns the generation that produced it, 0 for the initial state,
+    /// and the mutations that failed.
+    pub fn evolve(&mut self, args: &Args, population: usize) -> Result<(usize, Vec<String>)> {
+        use crate::consts::EVOLVE_GENERATIONS;
+
+        let start = Instant::now();
+        let duration = Self::duration(args).saturating_sub(self.write_margin());
+        let initial = self.snapshot();
+
+        let operators = [
+            Mover::Cost,
+            Mover::Force,
+            Mover::Gain,
+            Mover::Cluster,
+            Mover::Gravity,
+        ];
+        let mut variants = Vec::with_capacity(population);
+        for _ in 0..population.max(1) {
+            variants.push((Mover::default(), Ok(0), self.variant(args)?));
+        }
+
+        let mut best = (self.quality(), 0, initial);
+        let mut failures = Vec::new();
+        for generation in 1..EVOLVE_GENERATIONS as usize {
+            if Self::check_time(start, duration).is_err() {
+                break;
+            }
+            let share =
+                (duration / EVOLVE_GENERATIONS).min(duration.saturating_sub(start.elapsed()));
+
+            for (k, (operator, _, _)) in variants.iter_mut().enumerate() {
+                *operator = operators[(k + generation) % operators.len()];
+            }
+            let phase = Instant::now();
+            self.threads.install(|| {
+                utilities::par_for_each_mut(&mut variants, |(operator, rounds, chip)| {
+                    chip.mover = *operator;
+                    *rounds = chip.move_and_route(phase, share);
+                })
+            });
+            self.counters.record_phase("evolve", phase.elapsed());
+            let mut moved = false;
+            for (operator, rounds, _) in variants.iter() {
+                match rounds {
+                    Ok(rounds) => moved |= *rounds > 0,
+                    Err(err) => {
+                        failures.push(format!("Mutating with {:?} failed: {}", operator, err))
+                    }
+                }
+            }
+            if !moved {
+                break;
+            }
+
+            variants.sort_by_cached_key(|(_, _, chip)| chip.quality());
+            let quality = variants[0].2.quality();
+            if quality < best.0 {
+                best = (quality, generation, variants[0].2.snapshot());
+            }
+
+            let survivors = variants.len().div_ceil(2);
+            let parents: Vec<_> = variants[..survivors]
+                .iter()
+                .map(|(_, _, chip)| chip.snapshot())
+                .collect();
+            for (k, (_, _, chip)) in variants.iter_mut().enumerate().skip(survivors) {
+                chip.restore(&parents[k % survivors])?;
+            }
+        }
+
+        let (_, generation, snapshot) = best;
+        self.restore(&snapshot)?;
+        Ok((generation, failures))
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{
+    args::{Args, Fix},
+    chip::Chip,
+    components::{Cell, FactoryID, Net},
+    consts::MAX_DIAGNOSTICS,
+    raw::RawSolution,
+    report::{Report, Severity},
+    verify::Violation,
+};
+use anyhow::{Error, Result};
+use std::{
+    collections::HashSet,
+    fs,
+    time::{Duration, Instant},
+};
+
+/// The problems found by the `fix` subcommand, see `Chip::run_fix`.
+#[derive(Clone, Debug, Default)]
+pub struct FixOutcome {
+    /// moves and segments of the solution that were dropped, see `Chip::apply_solution`
+    pub dropped: Report,
+    /// problems of the solution before it was repaired
+    pub before: Report,
+    /// problems left after it was repaired
+    pub after: Report,
+    /// number of violations that could not be fixed
+    pub left: usize,
+}
+
+impl Chip {
+    /// Reads a solution file of the loaded input and applies it, see `apply_solution`.
+    pub fn read_solution(&mut self, filename: &str, off_chip: Severity) -> Result<Report> {
+        let content = fs::read_to_string(filename)?;
+        self.apply_solution(&RawSolution::parse(&content)?, off_chip)
+    }
+
+    /// Replaces the placement and routing of the loaded input with those of a solution.
+    /// Moves and segments are applied even if they are illegal, so they can be fixed.
+    /// Those that don't fit on the chip are dropped and reported with the `off_chip` severity,
+    /// unknown cells and nets fail with a `Report` of all of them.
+    /// Returns the dropped moves and segments.
+    pub fn apply_solution(&mut self, raw: &RawSolution, off_chip: Severity) -> Result<Report> {
+        let mut report = Report::new(self.max_diagnostics.unwrap_or(MAX_DIAGNOSTICS));
+        let mut unknown = false;
+
+        let mut moves = Vec::with_capacity(raw.moved.len());
+        for moved in raw.moved.iter() {
+            let idx = Cell::from_str(&moved.name)
+                .ok()
+                .filter(|&idx| idx < self.cells.len());
+            match idx {
+                None => {
+                    unknown = true;
+                    report.error(format!("Unknown cell {}", moved.name))
+                }
+                Some(_) if !self.in_bounds(moved.position.with(1)) => report.push(
+                    off_chip,
+                    format!("Dropped the move of {} off the chip", moved.name),
+                ),
+                Some(idx) => moves.push((idx, moved.position)),
+            }
+        }
+
+        let mut routes = vec![HashSet::new(); self.nets.len()];
+        for route in raw.routes.iter() {
+            let id = Net::from_str(&route.net)
+                .ok()
+                .filter(|&id| id < self.nets.len());
+            match id {
+                None => {
+                    unknown = true;
+                    report.error(format!("Unknown net {}", route.net))
+                }
+                Some(_)
+                    if !self.in_bounds(route.route.source())
+                        || !self.in_bounds(route.route.target()) =>
+                {
+                    report.push(
+                        off_chip,
+                        format!(
+                            "Dropped route {} of {} off the chip",
+                            route.route, route.net
+                        ),
+                    )
+                }
+                Some(id) => {
+                    routes[id].insert(route.route);
+                }
+            }
+        }
+
+        if unknown {
+            return Err(Error::from(report));
+        }
+
+        let return_to_origin = self.rules.return_to_origin();
+        for cell in self.cells.iter_mut() {
+            cell.position = cell.origin;
+            cell.moved = false;
+        }
+        for (idx, position) in moves {
+            let cell = &mut self.cells[idx];
+            cell.position = position;
+            cell.moved = !return_to_origin || position != cell.origin;
+        }
+        self.already_moved = self.cells.iter().filter(|cell| cell.moved).count();
+
+        for (net, routes) in self.nets.iter_mut().zip(routes) {
+            net.routes = routes;
+        }
+        self.canonicalize_routes();
+        self.dirty_nets.clear();
+        self.update_demand()?;
+
+        Ok(report)
+    }
+
+    /// Puts a cell back to its origin, marking its nets dirty.
+    /// Demand is not updated.
+    pub(crate) fn revert_move(&mut self, idx: usize) {
+        let cell = &mut self.cells[idx];
+        cell.position = cell.origin;
+        if cell.moved {
+            cell.moved = false;
+            self.already_moved -= 1;
+        }
+        self.dirty_nets.extend(self.cell_nets(idx));
+    }
+
+    /// Repairs the violations of the current state with as little change as possible:
+    /// illegal moves are reverted, then the moves closest to their origin
+    /// until the budget is met, broken nets and the nets of reverted cells are rerouted,
+    /// and only the nets through overflowed GGrids are negotiated.
+    /// Everything else is left untouched.
+    /// Returns the number of violations left.
+    pub fn fix(&mut self, start: Instant, duration: Duration) -> Result<usize> {
+        for idx in 0..self.cells.len() {
+            if self.is_illegal_move(idx) {
+                self.revert_move(idx);
+            }
+        }
+
+        self.revert_excess_moves();
+        self.update_demand()?;
+
+        for violation in self.verify() {
+            match violation {
+                Violation::OutOfBounds { net, .. }
+                | Violation::BelowMinLayer { net, .. }
+                | Violation::WrongDirection { net, .. }
+                | Violation::Disconnected { net } => {
+                    self.dirty_nets.insert(net);
+                }
+                _ => {}
+            }
+        }
+        self.begin_pass("fix");
+        self.reroute_dirty_nets()?;
+
+        if !self.overflowed().is_empty() {
+            self.negotiate(start, duration)?;
+        }
+
+        Ok(self.verify().len())
+    }
+
+    /// Loads the solution of the `fix` subcommand into the loaded input and repairs it.
+    /// Returns the problems before and after, see `FixOutcome`.
+    pub fn run_fix(&mut self, args: &Args, fix: &Fix) -> Result<FixOutcome> {
+        let start = Instant::now();
+        let duration = Self::duration(args).saturating_sub(self.write_margin());
+
+        self.configure(args)?;
+        let dropped = self.read_solution(&fix.solution, Severity::Warning)?;
+        let before = self.diagnose();
+        let left = self.fix(start, duration)?;
+        Ok(FixOutcome {
+            dropped,
+            before,
+            after: self.diagnose(),
+            left,
+        })
+    }
+}
@@ -0,0 +1,160 @@
+use crate::{
+    chip::Chip,
+    components::{CellType, Pair},
+};
+use anyhow::{anyhow, Error, Result};
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// How `--cell` picks the positions cells move to.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Mover {
+    /// Every cell moves to its cheapest candidate, see `move_cells`.
+    #[default]
+    Cost,
+    /// Cells follow the pull of their nets and the push of congestion, see `move_cells_force`.
+    Force,
+    /// The move with the highest gain is made first, see `move_cells_by_gain`.
+    Gain,
+    /// Clusters of tightly connected cells move as a unit, see `move_clusters`.
+    Cluster,
+    /// Cells move to the center of their nets weighted by routed length, see `move_cells_by_gravity`.
+    Gravity,
+}
+
+impl FromStr for Mover {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "cost" => Ok(Mover::Cost),
+            "force" => Ok(Mover::Force),
+            "gain" => Ok(Mover::Gain),
+            "cluster" => Ok(Mover::Cluster),
+            "gravity" => Ok(Mover::Gravity),
+            _ => Err(anyhow!("Unknown mover: {}", s)),
+        }
+    }
+}
+
+impl Chip {
+    /// Demand over capacity of a position on all layers, in thousandths.
+    pub(crate) fn position_congestion(&self, position: Pair<usize>) -> usize {
+        let Pair(row, col) = position;
+        let (mut demand, mut capacity) = (0usize, 0usize);
+        for layer in self.layers.iter() {
+            demand =
+                demand.saturating_add(layer.get_demand(row - 1, col - 1).copied().unwrap_or(0));
+            capacity =
+                capacity.saturating_add(layer.get_capacity(row - 1, col - 1).copied().unwrap_or(0));
+        }
+        demand.saturating_mul(1000) / capacity.max(1)
+    }
+
+    /// Force on a cell in GGrids, by row and column.
+    /// Every net pulls the cell towards the centroid of the other pins on it,
+    /// weighted by one over the number of those pins, so large nets pull no harder than small ones.
+    /// Every neighboring position less congested than the cell's pushes it that way,
+    /// by `FORCE_REPULSION` thousandths of a GGrid per unit of the difference.
+    /// `None` if the cell shares no net with another cell.
+    pub fn cell_force(&self, idx: usize) -> Option<(f64, f64)> {
+        use crate::consts::FORCE_REPULSION;
+
+        let position = self.cells.get(idx)?.position;
+        let (mut weight, mut row, mut col) = (0.0, 0.0, 0.0);
+        for id in self.cell_nets(idx) {
+            let others: Vec<_> = self.nets[id]
+                .pins
+                .iter()
+                .filter_map(|&pin| {
+                    let (owner, _) = *self.pin_owners.get(pin)?;
+                    (owner != idx).then(|| self.cells[owner].position)
+                })
+                .collect();
+            let share = 1.0 / others.len().max(1) as f64;
+            for Pair(r, c) in others {
+                weight += share;
+                row += share * r as f64;
+                col += share * c as f64;
+            }
+        }
+        if weight == 0.0 {
+            return None;
+        }
+
+        let Pair(r0, c0) = position;
+        let (mut force_row, mut force_col) = (row / weight - r0 as f64, col / weight - c0 as f64);
+
+        let here = self.position_congestion(position);
+        let Pair(rows, cols) = self.dim;
+        let neighbors = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+        for (dr, dc) in neighbors {
+            let (r, c) = (r0 as isize + dr, c0 as isize + dc);
+            if r < 1 || c < 1 || r as usize > rows || c as usize > cols {
+                continue;
+            }
+            let there = self.position_congestion(Pair(r as usize, c as usize));
+            let push = here.saturating_sub(there) as f64 * FORCE_REPULSION as f64 / 1e6;
+            force_row += push * dr as f64;
+            force_col += push * dc as f64;
+        }
+
+        Some((force_row, force_col))
+    }
+
+    /// Moves every movable cell, in input order, by the force on it, see `cell_force`,
+    /// for up to `FORCE_ITERATIONS` iterations or until an iteration moves no cell.
+    /// Targets are kept within `max_displacement`,
+    /// targets without room for the cell's demand footprint are skipped,
+    /// and a move whose nets can't be rerouted is undone.
+    /// Returns the number of moves made.
+    pub fn move_cells_force(&mut self, start: Instant, duration: Duration) -> Result<usize> {
+        use crate::consts::FORCE_ITERATIONS;
+
+        let footprints = self.demand_footprints();
+        let Pair(rows, cols) = self.dim;
+
+        let mut moved = 0;
+        for _ in 0..FORCE_ITERATIONS {
+            let mut iteration = 0;
+            for idx in 0..self.cells.len() {
+                if Self::check_time(start, duration).is_err() {
+                    return Ok(moved + iteration);
+                }
+                if !matches!(self.cells[idx].movable, CellType::Movable) {
+                    continue;
+                }
+
+                let (force_row, force_col) = match self.cell_force(idx) {
+                    Some(force) => force,
+                    None => continue,
+                };
+                let Pair(row, col) = self.cells[idx].position;
+                let step = |from: usize, force: f64, max: usize| {
+                    (from as f64 + force).round().clamp(1.0, max.max(1) as f64) as usize
+                };
+                let target = Pair(step(row, force_row, rows), step(col, force_col, cols));
+                let target = self.within_displacement(idx, target);
+                if target == self.cells[idx].position {
+                    continue;
+                }
+
+                let fits = footprints
+                    .get(self.cells[idx].mastercell)
+                    .is_some_and(|footprint| self.fits_footprint(target, footprint));
+                if fits && self.can_move(idx, target) && self.move_and_reroute(idx, target)? {
+                    iteration += 1;
+                }
+            }
+
+            moved += iteration;
+            if iteration == 0 {
+                break;
+            }
+        }
+
+        Ok(moved)
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{chip::Chip, components::CellType, density::DensityMap};
+use anyhow::Result;
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    time::{Duration, Instant},
+};
+
+impl Chip {
+    /// Other cells sharing a net with a cell, sorted.
+    pub fn connected_cells(&self, idx: usize) -> Vec<usize> {
+        let mut cells: Vec<_> = self
+            .cell_nets(idx)
+            .into_iter()
+            .flat_map(|id| self.nets[id].pins.iter())
+            .filter_map(|&pin| self.pin_owners.get(pin).map(|&(owner, _)| owner))
+            .filter(|&owner| owner != idx)
+            .collect();
+        cells.sort_unstable();
+        cells.dedup();
+        cells
+    }
+
+    /// Moves cells in the order of their gains, in the style of Fiduccia-Mattheyses:
+    /// every movable cell is queued with its best candidate and the gain of moving there,
+    /// see `best_candidate`, and the move with the highest gain is made first.
+    /// A moved cell is locked for the rest of the pass, and the cells sharing a net with it
+    /// are queued again with their new gains; outdated entries are skipped.
+    /// Ties go to the lower cell index. Moves without a positive gain are never made,
+    /// and a move whose nets can't be rerouted is undone.
+    /// Returns the number of cells moved.
+    pub fn move_cells_by_gain(&mut self, start: Instant, duration: Duration) -> Result<usize> {
+        let mut density = self.density_map();
+        let mut versions = vec![0; self.cells.len()];
+        let mut locked = vec![false; self.cells.len()];
+
+        let mut queue = BinaryHeap::new();
+        for idx in 0..self.cells.len() {
+            if Self::check_time(start, duration).is_err() {
+                return Ok(0);
+            }
+            self.queue_gain(idx, 0, &density, &mut queue);
+        }
+
+        let mut moved = 0;
+        while let Some((gain, Reverse(idx), version)) = queue.pop() {
+            if Self::check_time(start, duration).is_err() || self.moves_remaining() == 0 {
+                break;
+            }
+            if locked[idx] || version != versions[idx] {
+                continue;
+            }
+
+            // The queued target may be outdated by moves made since, so look again.
+            let (gain, target) = match self.best_candidate(idx, &density) {
+                Some((now, target)) if now > 0 && now >= gain => (now, target),
+                Some((now, _)) if now > 0 => {
+                    queue.push((now, Reverse(idx), version));
+                    continue;
+                }
+                _ => continue,
+            };
+            debug_assert!(gain > 0);
+
+            let position = self.cells[idx].position;
+            if !self.move_and_reroute(idx, target)? {
+                continue;
+            }
+            density.move_weight(position, target, self.cell_weight(idx));
+            locked[idx] = true;
+            moved += 1;
+
+            for neighbor in self.connected_cells(idx) {
+                if !locked[neighbor] {
+                    versions[neighbor] += 1;
+                    self.queue_gain(neighbor, versions[neighbor], &density, &mut queue);
+                }
+            }
+        }
+
+        Ok(moved)
+    }
+
+    /// Queues a movable cell with the gain of its best candidate, if it is positive.
+    fn queue_gain(
+        &self,
+        idx: usize,
+        version: usize,
+        density: &DensityMap,
+        queue: &mut BinaryHeap<(isize, Reverse<usize>, usize)>,
+    ) {
+        if !matches!(self.cells[idx].movable, CellType::Movable) {
+            return;
+        }
+        if let Some((gain, _)) = self.best_candidate(idx, density) {
+            if gain > 0 {
+                queue.push((gain, Reverse(idx), version));
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{
+    chip::Chip,
+    components::{CellType, Direction, Pair, Point, Route},
+    raw::{RawCell, RawDesign, RawLayer, RawMasterCell, RawNet, RawPin},
+};
+use anyhow::{anyhow, Result};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// A layer of a pure routing problem.
+#[derive(Clone, Debug)]
+pub struct GridLayer {
+    /// routing direction
+    pub direction: Direction,
+    /// capacity of every GGrid, row by row
+    pub capacity: Vec<usize>,
+}
+
+/// A net of a pure routing problem.
+#[derive(Clone, Debug)]
+pub struct GridNet {
+    /// GGrids of the pins, indexed like the input file
+    pub pins: Vec<Point<usize>>,
+    /// lowest layer id the net may run planar on
+    pub min_layer: usize,
+}
+
+/// The router on its own, for routing problems without cells:
+/// a capacity grid and nets given as pins and a min layer.
+/// Every pin is held by a fixed cell of its own behind the scenes,
+/// so the whole routing engine and its options apply, see `chip_mut`.
+#[derive(Debug, Default)]
+pub struct GlobalRouter {
+    chip: Chip,
+}
+
+impl GlobalRouter {
+    /// Sets up the problem on a grid of `dim` GGrids, `layers` from the lowest up.
+    pub fn new(dim: Pair<usize>, layers: Vec<GridLayer>, nets: &[GridNet]) -> Result<Self> {
+        if let Some(layer) = layers
+            .iter()
+            .find(|layer| layer.capacity.len() != dim.size())
+        {
+            return Err(anyhow!(
+                "Capacity of {} GGrids given for a grid of {}",
+                layer.capacity.len(),
+                dim.size()
+            ));
+        }
+
+        // Checked here, so errors name pins rather than the cells behind them.
+        for (id, net) in nets.iter().enumerate() {
+            if net.min_layer >= layers.len() {
+                return Err(anyhow!(
+                    "Net {} has min layer {} of {}",
+                    id,
+                    net.min_layer,
+                    layers.len()
+                ));
+            }
+            if let Some(pin) = net.pins.iter().find(|pin| {
+                !Chip::in_grid(dim, pin.flatten()) || !(1..=layers.len()).contains(&pin.lay())
+            }) {
+                return Err(anyhow!("Net {} has pin {} off the grid", id, pin));
+            }
+        }
+
+        let layer_name = |id: usize| format!("M{}", id + 1);
+        let mastercells = (0..layers.len())
+            .map(|id| RawMasterCell {
+                name: format!("MC{}", id + 1),
+                pins: vec![RawPin {
+                    name: "P1".to_string(),
+                    layer: layer_name(id),
+                }],
+                blkgs: Vec::new(),
+            })
+            .collect();
+
+        let mut cells = Vec::new();
+        let mut raw_nets = Vec::with_capacity(nets.len());
+        for (id, net) in nets.iter().enumerate() {
+            let mut pins = Vec::with_capacity(net.pins.len());
+            for &Point(row, col, lay) in net.pins.iter() {
+                let name = format!("C{}", cells.len() + 1);
+                cells.push(RawCell {
+                    name: name.clone(),
+                    mastercell: format!("MC{}", lay),
+                    position: Pair(row, col),
+                    movable: CellType::Fixed,
+                });
+                pins.push((name, "P1".to_string()));
+            }
+            raw_nets.push(RawNet {
+                name: format!("N{}", id + 1),
+                min_layer: (net.min_layer > 0).then(|| layer_name(net.min_layer)),
+                pins,
+            });
+        }
+
+        let raw = RawDesign {
+            max_move: 0,
+            dim,
+            layers: layers
+                .iter()
+                .enumerate()
+                .map(|(id, layer)| RawLayer {
+                    name: layer_name(id),
+                    direction: layer.direction,
+                    supply: 0,
+                })
+                .collect(),
+            non_default_supply: Vec::new(),
+            mastercells,
+            conflicts: Vec::new(),
+            cells,
+            nets: raw_nets,
+            routes: Vec::new(),
+            pin_offsets: Vec::new(),
+        };
+
+        let mut chip = Chip::default();
+        chip.compile(&raw)?;
+        for (layer, grid) in chip.layers.iter_mut().zip(layers) {
+            layer.capacity = Arc::new(grid.capacity);
+        }
+        chip.update_demand()?;
+
+        Ok(Self { chip })
+    }
+
+    /// Routes every net, negotiates away the overflow and cleans up the routes
+    /// like the routing phases of a full run, within `duration`.
+    pub fn route(&mut self, duration: Duration) -> Result<()> {
+        self.chip.route_phases(Instant::now(), duration)
+    }
+
+    /// Route segments of a net, in output form.
+    pub fn routes(&self, net: usize) -> Vec<Route<usize>> {
+        self.chip
+            .nets
+            .get(net)
+            .map_or_else(Vec::new, |net| net.normalized_routes())
+    }
+
+    /// Sum of demand exceeding capacity over all GGrids.
+    pub fn overflow(&self) -> usize {
+        self.chip.metrics().overflow
+    }
+
+    /// The chip the problem is set up on.
+    pub fn chip(&self) -> &Chip {
+        &self.chip
+    }
+
+    /// The chip the problem is set up on, to configure the router.
+    pub fn chip_mut(&mut self) -> &mut Chip {
+        &mut self.chip
+    }
+}
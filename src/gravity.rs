@@ -0,0 +1,82 @@
+use crate::{
+    chip::Chip,
+    components::{CellType, Pair},
+};
+use anyhow::Result;
+use std::{
+    cmp::Reverse,
+    time::{Duration, Instant},
+};
+
+impl Chip {
+    /// Center of gravity of the pins a cell is connected to, rounded to a GGrid,
+    /// with the cell's pull as its second part.
+    /// Every net pulls the cell towards the other pins on it with the weight of its routed length,
+    /// see `net_length`, so the longest nets pull hardest; unrouted nets weigh 1.
+    /// `None` if the cell shares no net with another cell.
+    pub fn cell_gravity(&self, idx: usize) -> Option<(Pair<usize>, usize)> {
+        self.cells.get(idx)?;
+        let (mut weight, mut row, mut col) = (0, 0, 0);
+        for id in self.cell_nets(idx) {
+            let net = &self.nets[id];
+            let length = self.net_length(net).max(1);
+            for &pin in net.pins.iter() {
+                let owner = match self.pin_owners.get(pin) {
+                    Some(&(owner, _)) if owner != idx => owner,
+                    _ => continue,
+                };
+                let Pair(r, c) = self.cells[owner].position;
+                weight += length;
+                row += length * r;
+                col += length * c;
+            }
+        }
+        if weight == 0 {
+            return None;
+        }
+
+        let round = |sum: usize| (sum + weight / 2) / weight;
+        Some((Pair(round(row), round(col)), weight))
+    }
+
+    /// Moves every movable cell to the center of gravity of its nets, see `cell_gravity`,
+    /// the cells pulled hardest first, so the longest nets get shortened while the budget lasts.
+    /// Targets are kept within `max_displacement`,
+    /// targets without room for the cell's demand footprint are skipped,
+    /// and a move whose nets can't be rerouted is undone.
+    /// Returns the number of cells moved.
+    pub fn move_cells_by_gravity(&mut self, start: Instant, duration: Duration) -> Result<usize> {
+        let footprints = self.demand_footprints();
+
+        let mut order: Vec<_> = (0..self.cells.len())
+            .filter(|&idx| matches!(self.cells[idx].movable, CellType::Movable))
+            .filter_map(|idx| self.cell_gravity(idx).map(|(_, pull)| (idx, pull)))
+            .collect();
+        order.sort_by_key(|&(idx, pull)| (Reverse(pull), idx));
+
+        let mut moved = 0;
+        for (idx, _) in order {
+            if Self::check_time(start, duration).is_err() || self.moves_remaining() == 0 {
+                break;
+            }
+
+            // Earlier moves shift the center, so look again.
+            let target = match self.cell_gravity(idx) {
+                Some((target, _)) => self.within_displacement(idx, target),
+                None => continue,
+            };
+            if target == self.cells[idx].position {
+                continue;
+            }
+
+            let fits = footprints
+                .get(self.cells[idx].mastercell)
+                .is_some_and(|footprint| self.fits_footprint(target, footprint));
+            if fits && self.can_move(idx, target) && self.move_and_reroute(idx, target)? {
+                moved += 1;
+            }
+        }
+
+        Ok(moved)
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{chip::Chip, components::Route};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// The pass and iteration that created a route segment.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SegmentOrigin {
+    /// name of the pass
+    pub pass: &'static str,
+    /// number of passes begun before, 0 for routes of the input
+    pub iteration: usize,
+}
+
+/// Which pass created every route segment, kept beside the routes for debugging.
+/// A segment keeps the origin it was first seen with.
+#[derive(Clone, Debug, Default)]
+pub struct RouteHistory {
+    /// the pass running now
+    pub current: SegmentOrigin,
+    /// origins of the segments of every net, indexed by net id
+    pub origins: HashMap<usize, HashMap<Route<usize>, SegmentOrigin>>,
+}
+
+impl RouteHistory {
+    /// Starts a new pass, segments recorded from now on are attributed to it.
+    pub fn begin(&mut self, pass: &'static str) {
+        self.current = SegmentOrigin {
+            pass,
+            iteration: self.current.iteration + 1,
+        };
+    }
+
+    /// Records the segments of a net that have no origin yet.
+    pub fn record<'a>(&mut self, net: usize, routes: impl IntoIterator<Item = &'a Route<usize>>) {
+        let current = self.current;
+        let origins = self.origins.entry(net).or_default();
+        for &route in routes {
+            origins.entry(route).or_insert(current);
+        }
+    }
+}
+
+impl Chip {
+    /// Starts remembering which pass creates every route segment.
+    /// The current routes are attributed to the input.
+    pub fn trace_routes(&mut self) {
+        let mut history = RouteHistory {
+            current: SegmentOrigin {
+                pass: "input",
+                iteration: 0,
+            },
+            ..RouteHistory::default()
+        };
+        for net in self.nets.iter() {
+            history.record(net.id, net.routes.iter());
+        }
+        self.history = Some(history);
+    }
+
+    /// Starts a new pass if route segments are traced.
+    pub fn begin_pass(&mut self, pass: &'static str) {
+        if let Some(history) = self.history.as_mut() {
+            history.begin(pass);
+        }
+    }
+
+    /// The pass that created a route segment of a net, `None` if routes aren't traced.
+    /// A segment merged from several recorded ones, as in the output,
+    /// gets the latest origin among them.
+    pub fn segment_provenance(&self, net: usize, route: Route<usize>) -> Option<SegmentOrigin> {
+        let origins = self.history.as_ref()?.origins.get(&net)?;
+        if let Some(&origin) = origins.get(&route) {
+            return Some(origin);
+        }
+
+        // Collinear segments that overlap share at least two GGrids, others at most one.
+        let points: HashSet<_> = route.points().into_iter().collect();
+        origins
+            .iter()
+            .filter(|(recorded, _)| {
+                recorded
+                    .points()
+                    .iter()
+                    .filter(|point| points.contains(point))
+                    .count()
+                    >= 2
+            })
+            .map(|(_, &origin)| origin)
+            .max_by_key(|origin| origin.iteration)
+    }
+}
+
+impl Display for SegmentOrigin {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} (pass {})", self.pass, self.iteration)
+    }
+}
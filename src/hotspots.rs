@@ -0,0 +1,109 @@
+use crate::{
+    chip::Chip,
+    components::{CellType, Pair},
+};
+use anyhow::Result;
+use std::{
+    cmp::Reverse,
+    time::{Duration, Instant},
+};
+
+impl Chip {
+    /// Demand over capacity of a GGrid, summed over its layers.
+    pub fn gcell_overflow(&self, position: Pair<usize>) -> usize {
+        let Pair(row, col) = position;
+        self.layers
+            .iter()
+            .map(|layer| {
+                match (
+                    layer.get_capacity(row - 1, col - 1),
+                    layer.get_demand(row - 1, col - 1),
+                ) {
+                    (Some(&capacity), Some(&demand)) => demand.saturating_sub(capacity),
+                    _ => 0,
+                }
+            })
+            .sum()
+    }
+
+    /// The `k` overflowed GGrids with the most overflow, see `gcell_overflow`, most first.
+    /// Ties go to the lower row, then the lower column.
+    pub fn top_overflowed(&self, k: usize) -> Vec<(Pair<usize>, usize)> {
+        let Pair(rows, cols) = self.dim;
+        let mut hotspots: Vec<_> = (1..=rows)
+            .flat_map(|row| (1..=cols).map(move |col| Pair(row, col)))
+            .map(|position| (position, self.gcell_overflow(position)))
+            .filter(|&(_, overflow)| overflow > 0)
+            .collect();
+        hotspots.sort_by_key(|&(Pair(row, col), overflow)| (Reverse(overflow), row, col));
+        hotspots.truncate(k);
+        hotspots
+    }
+
+    /// Repairs the `top` most overflowed GGrids, see `top_overflowed`, most overflowed first,
+    /// by moving away the movable cells on them or next to them.
+    /// The cells of a GGrid are tried cheapest to move first, each to its best candidate,
+    /// see `best_candidate`, until the GGrid no longer overflows or the move budget runs out.
+    /// A move is undone unless its nets can be rerouted and the GGrid overflows less after it.
+    /// Returns the number of cells moved.
+    pub fn relieve_hotspots(
+        &mut self,
+        top: usize,
+        start: Instant,
+        duration: Duration,
+    ) -> Result<usize> {
+        let mut density = self.density_map();
+
+        let mut moved = 0;
+        for (hotspot, _) in self.top_overflowed(top) {
+            let Pair(row, col) = hotspot;
+            let around = [
+                Some(hotspot),
+                Some(Pair(row, col + 1)),
+                col.checked_sub(1).map(|col| Pair(row, col)),
+                Some(Pair(row + 1, col)),
+                row.checked_sub(1).map(|row| Pair(row, col)),
+            ];
+            let occupants = self.cells_by_position();
+            let mut ranked: Vec<_> = around
+                .iter()
+                .flatten()
+                .filter_map(|position| occupants.get(position))
+                .flatten()
+                .copied()
+                .filter(|&idx| matches!(self.cells[idx].movable, CellType::Movable))
+                .filter_map(|idx| {
+                    let (gain, target) = self.best_candidate(idx, &density)?;
+                    let position = self.cells[idx].position;
+                    (target != position && target != hotspot).then_some((gain, idx, target))
+                })
+                .collect();
+            ranked.sort_by_key(|&(gain, idx, _)| (Reverse(gain), idx));
+
+            for (_, idx, target) in ranked {
+                if Self::check_time(start, duration).is_err() || self.moves_remaining() == 0 {
+                    return Ok(moved);
+                }
+                let overflow = self.gcell_overflow(hotspot);
+                if overflow == 0 {
+                    break;
+                }
+                if !self.can_move(idx, target) || !self.admits_blockages(idx, target) {
+                    continue;
+                }
+
+                let position = self.cells[idx].position;
+                let mut txn = self.try_move(idx, target)?;
+                if txn.reroute_dirty_nets()? == 0 && txn.gcell_overflow(hotspot) < overflow {
+                    txn.commit();
+                    density.move_weight(position, target, self.cell_weight(idx));
+                    moved += 1;
+                } else {
+                    txn.rollback()?;
+                }
+            }
+        }
+
+        Ok(moved)
+    }
+}
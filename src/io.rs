@@ -0,0 +1,391 @@
+//! Parsers of the individual sections of an input file.
+//! Every function consumes exactly one section from the tokenizer,
+//! so tools can reuse any of them without parsing a whole chip.
+//! Sections are returned as raw data, see `RawDesign`.
+
+use crate::{
+    components::{CellType, ConflictType, Direction, Pair, Point, Route},
+    raw::{
+        RawBlockage, RawCell, RawConflict, RawLayer, RawMasterCell, RawMovedCell, RawNet, RawPin,
+        RawPinOffset, RawRoute, RawSupply,
+    },
+    utilities::{check_eq, parse_numeric, parse_string, NameError},
+};
+use anyhow::{Error, Result};
+use std::collections::HashMap;
+
+pub use crate::utilities::Tokenizer;
+
+/// Looks up the id of a layer by its name.
+/// Returns `Err(NameError)` if no layer has that name.
+pub fn layer_id(layer_ids: &HashMap<String, usize>, name: &str) -> Result<usize> {
+    layer_ids
+        .get(name)
+        .copied()
+        .ok_or(NameError)
+        .map_err(Error::from)
+}
+
+/// MaxCellMove <maxMoveCount>
+pub fn parse_max_move(content: &mut Tokenizer) -> Result<usize> {
+    let keyword = parse_string(content)?;
+    check_eq(keyword, "MaxCellMove")?;
+    parse_numeric(content)
+}
+
+/// GGridBoundaryIdx <rowBeginIdx> <colBeginIdx> <rowEndIdx> <colEndIdx>
+pub fn parse_dimensions(content: &mut Tokenizer) -> Result<Pair<usize>> {
+    let keyword = parse_string(content)?;
+    check_eq(keyword, "GGridBoundaryIdx")?;
+
+    let row_beg: usize = parse_numeric(content)?;
+    let col_beg: usize = parse_numeric(content)?;
+
+    check_eq(row_beg, 1)?;
+    check_eq(col_beg, 1)?;
+
+    let row_end: usize = parse_numeric(content)?;
+    let col_end: usize = parse_numeric(content)?;
+
+    let num_rows = row_end;
+    let num_cols = col_end;
+
+    Ok(Pair(num_rows, num_cols))
+}
+
+/// NumLayer <LayerCount>
+pub fn parse_layers(content: &mut Tokenizer) -> Result<Vec<RawLayer>> {
+    let keyword = parse_string(content)?;
+    check_eq(keyword, "NumLayer")?;
+
+    let num_layers: usize = parse_numeric(content)?;
+
+    let mut layers = Vec::with_capacity(content.capacity(num_layers));
+
+    // Lay <layerName> <Idx> <RoutingDirection> <defaultSupplyOfOneGGrid>
+    for idx in 0..num_layers {
+        let keyword = parse_string(content)?;
+        check_eq(keyword, "Lay")?;
+
+        let name = parse_string(content)?;
+        let layer_id: usize = parse_numeric(content)?;
+
+        // Layers are identified by their order, the index is redundant.
+        check_eq(layer_id, idx + 1)?;
+
+        let dir_str = parse_string(content)?;
+        let direction = if dir_str == "H" {
+            Direction::Horizontal
+        } else {
+            check_eq(dir_str, "V")?;
+            Direction::Vertical
+        };
+
+        let supply: usize = parse_numeric(content)?;
+
+        layers.push(RawLayer {
+            name: name.to_string(),
+            direction,
+            supply,
+        });
+    }
+
+    Ok(layers)
+}
+
+/// NumNonDefaultSupplyGGrid <nonDefaultSupplyGGridCount>
+pub fn parse_non_default_supply(content: &mut Tokenizer) -> Result<Vec<RawSupply>> {
+    let keyword = parse_string(content)?;
+    check_eq(keyword, "NumNonDefaultSupplyGGrid")?;
+    let num_non_default: usize = parse_numeric(content)?;
+
+    let mut supplies = Vec::with_capacity(content.capacity(num_non_default));
+
+    // <rowIdx> <colIdx> <LayIdx> <incrOrDecrValue>
+    for _ in 0..num_non_default {
+        let r: usize = parse_numeric(content)?;
+        let c: usize = parse_numeric(content)?;
+        let l: usize = parse_numeric(content)?;
+        let delta: isize = parse_numeric(content)?;
+
+        supplies.push(RawSupply {
+            point: Point(r, c, l),
+            delta,
+        });
+    }
+
+    Ok(supplies)
+}
+
+/// NumMasterCell <masterCellCount>
+pub fn parse_master_cells(content: &mut Tokenizer) -> Result<Vec<RawMasterCell>> {
+    let keyword = parse_string(content)?;
+    check_eq(keyword, "NumMasterCell")?;
+    let num_master_cell: usize = parse_numeric(content)?;
+
+    let mut mastercells = Vec::with_capacity(content.capacity(num_master_cell));
+
+    // MasterCell <masterCellName> <pinCount> <blockageCount>
+    for _ in 0..num_master_cell {
+        let keyword = parse_string(content)?;
+        check_eq(keyword, "MasterCell")?;
+
+        let name = parse_string(content)?;
+
+        let num_pins: usize = parse_numeric(content)?;
+        let num_blkgs: usize = parse_numeric(content)?;
+
+        let mut pins = Vec::with_capacity(content.capacity(num_pins));
+        // Pin <pinName> <pinLayer>
+        for _ in 0..num_pins {
+            let keyword = parse_string(content)?;
+            check_eq(keyword, "Pin")?;
+
+            let pin_name = parse_string(content)?;
+            let pin_layer = parse_string(content)?;
+
+            pins.push(RawPin {
+                name: pin_name.to_string(),
+                layer: pin_layer.to_string(),
+            });
+        }
+
+        let mut blkgs = Vec::with_capacity(content.capacity(num_blkgs));
+        // Blkg <blockageName> <blockageLayer> <demand>
+        for _ in 0..num_blkgs {
+            let keyword = parse_string(content)?;
+            check_eq(keyword, "Blkg")?;
+
+            let blkg_name = parse_string(content)?;
+            let blkg_layer = parse_string(content)?;
+            let blkg_demand: usize = parse_numeric(content)?;
+
+            blkgs.push(RawBlockage {
+                name: blkg_name.to_string(),
+                layer: blkg_layer.to_string(),
+                demand: blkg_demand,
+            });
+        }
+
+        mastercells.push(RawMasterCell {
+            name: name.to_string(),
+            pins,
+            blkgs,
+        })
+    }
+
+    Ok(mastercells)
+}
+
+/// NumNeighborCellExtraDemand <count>
+pub fn parse_conflicts(content: &mut Tokenizer) -> Result<Vec<RawConflict>> {
+    let keyword = parse_string(content)?;
+    check_eq(keyword, "NumNeighborCellExtraDemand")?;
+    let extra_count: usize = parse_numeric(content)?;
+
+    let mut conflicts = Vec::with_capacity(content.capacity(extra_count));
+
+    // sameGGrid <masterCellName1> <masterCellName2> <layerName> <demand>
+    // adjHGGrid <masterCellName1> <masterCellName2> <layerName> <demand>
+    for _ in 0..extra_count {
+        let grid_type_str = parse_string(content)?;
+        let kind = if grid_type_str == "adjHGGrid" {
+            ConflictType::AdjHGGrid
+        } else {
+            check_eq(grid_type_str, "sameGGrid")?;
+            ConflictType::SameGGrid
+        };
+
+        let master_cell_1 = parse_string(content)?;
+        let master_cell_2 = parse_string(content)?;
+
+        let layer_name = parse_string(content)?;
+        let layer_demand: usize = parse_numeric(content)?;
+
+        conflicts.push(RawConflict {
+            kind,
+            mastercells: (master_cell_1.to_string(), master_cell_2.to_string()),
+            layer: layer_name.to_string(),
+            demand: layer_demand,
+        });
+    }
+
+    Ok(conflicts)
+}
+
+/// NumCellInst <cellInstCount>
+pub fn parse_cells(content: &mut Tokenizer) -> Result<Vec<RawCell>> {
+    let keyword = parse_string(content)?;
+    check_eq(keyword, "NumCellInst")?;
+    let cell_count: usize = parse_numeric(content)?;
+
+    let mut cells = Vec::with_capacity(content.capacity(cell_count));
+
+    // CellInst <instName> <masterCellName> <gGridRowIdx> <gGridColIdx> <movableCstr>
+    for _ in 0..cell_count {
+        let keyword = parse_string(content)?;
+        check_eq(keyword, "CellInst")?;
+
+        let cell_name = parse_string(content)?;
+        let master_cell_name = parse_string(content)?;
+
+        let row: usize = parse_numeric(content)?;
+        let col: usize = parse_numeric(content)?;
+
+        let move_str = parse_string(content)?;
+        let movable = if move_str == "Movable" {
+            CellType::Movable
+        } else {
+            check_eq(move_str, "Fixed")?;
+            CellType::Fixed
+        };
+
+        cells.push(RawCell {
+            name: cell_name.to_string(),
+            mastercell: master_cell_name.to_string(),
+            position: Pair(row, col),
+            movable,
+        });
+    }
+
+    Ok(cells)
+}
+
+/// NumNets <netCount>
+pub fn parse_nets(content: &mut Tokenizer) -> Result<Vec<RawNet>> {
+    let keyword = parse_string(content)?;
+    check_eq(keyword, "NumNets")?;
+    let net_count: usize = parse_numeric(content)?;
+
+    let mut nets = Vec::with_capacity(content.capacity(net_count));
+
+    // Net <netName> <numPins> <minRoutingLayConstraint>
+    for _ in 0..net_count {
+        let keyword = parse_string(content)?;
+        check_eq(keyword, "Net")?;
+
+        let net_name = parse_string(content)?;
+        let num_pins: usize = parse_numeric(content)?;
+        let layer = parse_string(content)?;
+
+        let min_layer = if layer == "NoCstr" {
+            None
+        } else {
+            Some(layer.to_string())
+        };
+
+        let mut pins = Vec::with_capacity(content.capacity(num_pins));
+        // Pin <instName>/<masterPinName>
+        for _ in 0..num_pins {
+            let keyword = parse_string(content)?;
+            check_eq(keyword, "Pin")?;
+
+            let next = parse_string(content)?;
+            let pin_info = &mut next.split('/');
+            let cell_name = parse_string(pin_info)?;
+            let pin_name = parse_string(pin_info)?;
+            check_eq(pin_info.next(), None)?;
+
+            pins.push((cell_name.to_string(), pin_name.to_string()));
+        }
+
+        nets.push(RawNet {
+            name: net_name.to_string(),
+            min_layer,
+            pins,
+        });
+    }
+
+    Ok(nets)
+}
+
+/// NumRoutes <routeSegmentCount>
+pub fn parse_routes(content: &mut Tokenizer) -> Result<Vec<RawRoute>> {
+    let keyword = parse_string(content)?;
+    check_eq(keyword, "NumRoutes")?;
+    let num_segments: usize = parse_numeric(content)?;
+
+    let mut routes = Vec::with_capacity(content.capacity(num_segments));
+
+    // <sRowIdx> <sColIdx> <sLayIdx> <eRowIdx> <eColIdx> <eLayIdx> <netName>
+    for _ in 0..num_segments {
+        let srow: usize = parse_numeric(content)?;
+        let scol: usize = parse_numeric(content)?;
+        let slay: usize = parse_numeric(content)?;
+        let erow: usize = parse_numeric(content)?;
+        let ecol: usize = parse_numeric(content)?;
+        let elay: usize = parse_numeric(content)?;
+        let net_name = parse_string(content)?;
+
+        routes.push(RawRoute {
+            route: Route::raw(srow, scol, slay, erow, ecol, elay),
+            net: net_name.to_string(),
+        });
+    }
+
+    Ok(routes)
+}
+
+/// NumMovedCellInst <movedCellInstCount>
+///
+/// This section starts a solution file.
+pub fn parse_moved_cells(content: &mut Tokenizer) -> Result<Vec<RawMovedCell>> {
+    let keyword = parse_string(content)?;
+    check_eq(keyword, "NumMovedCellInst")?;
+    let num_moved: usize = parse_numeric(content)?;
+
+    let mut moved = Vec::with_capacity(content.capacity(num_moved));
+
+    // CellInst <instName> <gGridRowIdx> <gGridColIdx>
+    for _ in 0..num_moved {
+        let keyword = parse_string(content)?;
+        check_eq(keyword, "CellInst")?;
+
+        let cell_name = parse_string(content)?;
+        let row: usize = parse_numeric(content)?;
+        let col: usize = parse_numeric(content)?;
+
+        moved.push(RawMovedCell {
+            name: cell_name.to_string(),
+            position: Pair(row, col),
+        });
+    }
+
+    Ok(moved)
+}
+
+/// NumPinOffsets <pinOffsetCount>
+///
+/// This extension section is optional and follows `NumRoutes`.
+/// Offsets are measured from the GGrid center in thousandths of a GGrid.
+/// Returns no offsets if the section is absent.
+pub fn parse_pin_offsets(content: &mut Tokenizer) -> Result<Vec<RawPinOffset>> {
+    if content.clone().next() != Some(b"NumPinOffsets".as_ref()) {
+        return Ok(Vec::new());
+    }
+
+    let keyword = parse_string(content)?;
+    check_eq(keyword, "NumPinOffsets")?;
+    let num_offsets: usize = parse_numeric(content)?;
+
+    let mut offsets = Vec::with_capacity(content.capacity(num_offsets));
+
+    // PinOffset <masterCellName> <pinName> <rowOffset> <colOffset>
+    for _ in 0..num_offsets {
+        let keyword = parse_string(content)?;
+        check_eq(keyword, "PinOffset")?;
+
+        let mc_name = parse_string(content)?;
+        let pin_name = parse_string(content)?;
+        let row: isize = parse_numeric(content)?;
+        let col: isize = parse_numeric(content)?;
+
+        offsets.push(RawPinOffset {
+            mastercell: mc_name.to_string(),
+            pin: pin_name.to_string(),
+            offset: Pair(row, col),
+        });
+    }
+
+    Ok(offsets)
+}
@@ -1,10 +1,99 @@
+#[cfg(feature = "ab")]
+mod ab;
 mod args;
+mod autosave;
+#[cfg(feature = "benchmarks")]
+mod benchmarks;
+mod bounds;
+mod budget;
 mod chip;
+mod clusters;
+mod coarse;
+mod compile;
 mod components;
 mod consts;
+mod demand;
+mod density;
+mod difficulty;
+mod evolve;
+mod fix;
+mod force;
+mod gain;
+mod global_router;
+mod gravity;
+mod history;
+mod hotspots;
+pub mod io;
+mod lookahead;
+#[cfg(feature = "mem-profile")]
+mod memory;
+mod metrics;
+mod mover;
+mod output;
+mod partition;
+mod pipeline;
+mod placement;
+mod raw;
+mod report;
+mod restart;
+mod router;
+mod rudy;
+mod rules;
+mod schedule;
+mod score;
+mod snapshot;
+#[cfg(feature = "stress")]
+mod stress;
+mod throttle;
+mod txn;
 mod utilities;
+mod verify;
+mod view;
+mod window;
 
-pub use args::Args;
-pub use chip::Chip;
+#[cfg(feature = "ab")]
+pub use ab::AbOutcome;
+pub use args::{Args, Command, Eval, Fix};
+pub use autosave::Autosave;
+#[cfg(feature = "benchmarks")]
+pub use benchmarks::BENCHMARKS;
+pub use bounds::{GapReport, NetGap};
+pub use chip::{Chip, RunReport};
+pub use coarse::CoarseChip;
 pub use components::*;
-pub use utilities::UnionFind;
+pub use density::DensityMap;
+pub use difficulty::{Difficulty, DifficultyReport};
+pub use fix::FixOutcome;
+pub use force::Mover;
+pub use global_router::{GlobalRouter, GridLayer, GridNet};
+pub use history::{RouteHistory, SegmentOrigin};
+pub use lookahead::{Candidate, CandidateScore, Routability};
+#[cfg(feature = "mem-profile")]
+pub use memory::{HeapSize, MemoryProfile};
+pub use metrics::{Counters, Metrics};
+pub use output::OutputVersion;
+pub use partition::Region;
+pub use pipeline::{Pass, Pipeline, Stage, Until};
+pub use placement::{PlacementReport, Skip};
+pub use raw::{
+    RawBlockage, RawCell, RawConflict, RawDesign, RawLayer, RawMasterCell, RawMovedCell, RawNet,
+    RawPin, RawPinOffset, RawRoute, RawSolution, RawSupply,
+};
+pub use report::{Diagnostic, Report, Severity};
+pub use router::{
+    decompose, path_to_routes, Congestion, Corridor, CostModel, NetOrder, PathFinder, Search,
+    SteinerTable, Step, TieBreak, TieKey, TieRule, TileStats, Topology, TwoPinTask, WeightedCost,
+    TIE_RULES,
+};
+pub use rudy::{Reservation, RudyMap};
+pub use rules::{Iccad2020, PinDemand, RuleSet};
+pub use schedule::{Exhaustion, Schedule, Strategy};
+pub use score::{LayerWirelength, NetScore, Score};
+pub use snapshot::Snapshot;
+#[cfg(feature = "stress")]
+pub use stress::Stress;
+pub use throttle::Throttle;
+pub use txn::MoveTxn;
+pub use utilities::{BoundsError, Rng, ShapeError, ThreadPool, UnionFind};
+pub use verify::Violation;
+pub use view::{CapacityView, ChipView};
@@ -0,0 +1,97 @@
+use crate::{chip::Chip, components::Pair, density::DensityMap};
+use std::fmt::Debug;
+
+/// What a mover knows about a candidate GGrid of a cell when scoring it.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Candidate {
+    /// the candidate GGrid
+    pub target: Pair<usize>,
+    /// cost of the move by wirelength and density, see `Chip::move_cost`
+    pub cost: usize,
+    /// demand over capacity on all layers of the candidate and its neighbors, in thousandths
+    pub congestion: usize,
+    /// pins of the cell beyond the supply left on their layers at the candidate
+    pub pin_shortage: usize,
+}
+
+/// CandidateScore ranks the candidate GGrids a cell may move to, lower is better,
+/// so routability estimates can be tried without touching the movers.
+/// With `--lookahead k`, the `k` cheapest candidates by `move_cost` are ranked by the score.
+/// Scores are in thousandths of a GGrid like `move_cost`.
+pub trait CandidateScore: Debug + Send + Sync {
+    /// How much the congestion around a candidate counts, in thousandths.
+    fn congestion_weight(&self) -> usize {
+        1000
+    }
+
+    /// Cost of every pin without supply left on its layer.
+    fn pin_penalty(&self) -> usize {
+        1000
+    }
+
+    /// Score of a candidate: its cost, plus the weighted congestion and the pin shortage.
+    fn score(&self, candidate: &Candidate) -> usize {
+        let congestion = candidate
+            .congestion
+            .saturating_mul(self.congestion_weight())
+            / 1000;
+        candidate
+            .cost
+            .saturating_add(congestion)
+            .saturating_add(candidate.pin_shortage.saturating_mul(self.pin_penalty()))
+    }
+}
+
+/// The default lookahead, local congestion and pin-layer supply.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Routability;
+
+impl CandidateScore for Routability {}
+
+impl Default for Box<dyn CandidateScore> {
+    fn default() -> Self {
+        Box::new(Routability)
+    }
+}
+
+impl Chip {
+    /// What is known about moving a cell to `target`, for the chip's `CandidateScore`.
+    /// Congestion is averaged over the candidate and its neighbors on the chip.
+    pub fn candidate(&self, idx: usize, target: Pair<usize>, density: &DensityMap) -> Candidate {
+        let Pair(row, col) = target;
+        let Pair(rows, cols) = self.dim;
+        let around: Vec<_> = [(0, 0), (0, 1), (0, -1), (1, 0), (-1, 0)]
+            .iter()
+            .map(|&(dr, dc)| (row as isize + dr, col as isize + dc))
+            .filter(|&(r, c)| r >= 1 && c >= 1 && r as usize <= rows && c as usize <= cols)
+            .map(|(r, c)| self.position_congestion(Pair(r as usize, c as usize)))
+            .collect();
+        let congestion = around.iter().sum::<usize>() / around.len().max(1);
+
+        let mut pins = vec![0usize; self.layers.len()];
+        if let Some(cell) = self.cells.get(idx) {
+            for pin in self.mastercells[cell.mastercell].pins() {
+                if let Some(count) = pins.get_mut(pin.layer) {
+                    *count += 1;
+                }
+            }
+        }
+        let pin_shortage = self
+            .layers
+            .iter()
+            .zip(pins)
+            .map(|(layer, pins)| {
+                let capacity = layer.get_capacity(row - 1, col - 1).copied().unwrap_or(0);
+                let demand = layer.get_demand(row - 1, col - 1).copied().unwrap_or(0);
+                pins.saturating_sub(capacity.saturating_sub(demand))
+            })
+            .sum();
+
+        Candidate {
+            target,
+            cost: self.move_cost(idx, target, density),
+            congestion,
+            pin_shortage,
+        }
+    }
+}
@@ -1,15 +1,200 @@
-use anyhow::Result;
-use cell_move_router::{Args, Chip};
+use anyhow::{anyhow, Result};
+use cell_move_router::{Args, Chip, Command, RunReport};
 use clap::Clap;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Runs the subcommand given, if any.
+/// Returns whether a subcommand ran, in which case there is nothing to optimize.
+fn run_command(args: &Args) -> Result<bool> {
+    match &args.command {
+        Some(Command::Fix(fix)) => {
+            let mut chip = Chip {
+                max_diagnostics: args.max_diagnostics,
+                ..Chip::default()
+            };
+            chip.read_file(&args.infile)?;
+            let outcome = chip.run_fix(args, fix)?;
+            if !outcome.dropped.is_empty() {
+                eprintln!("{}", outcome.dropped);
+            }
+            eprintln!("Before: {}", outcome.before);
+            eprintln!("After: {}", outcome.after);
+            if outcome.left > 0 {
+                eprintln!("{} violations could not be fixed", outcome.left);
+            }
+            chip.write_file(&args.outfile)?;
+            Ok(true)
+        }
+        Some(Command::Eval(eval)) => {
+            let mut chip = Chip {
+                max_diagnostics: args.max_diagnostics,
+                ..Chip::default()
+            };
+            chip.read_file(&args.infile)?;
+            let (score, report) = chip.evaluate(&eval.solution)?;
+            if !report.is_empty() {
+                println!("{}", report);
+            }
+            println!("{}", score);
+            if let Some(json) = &eval.json {
+                std::fs::write(json, score.to_json())?;
+            }
+            Ok(true)
+        }
+        #[cfg(feature = "stress")]
+        Some(Command::Stress(stress)) => {
+            use cell_move_router::RawDesign;
+            use std::fs;
+
+            let raw = RawDesign::parse(&fs::read_to_string(&args.infile)?)?.stressed(stress);
+            fs::write(&args.outfile, raw.to_string())?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Arguments of every strategy of `--ab`:
+/// the command line without `--ab`, followed by the options of the strategy.
+#[cfg(feature = "ab")]
+fn ab_strategies(ab: &str) -> Result<Vec<(String, Args)>> {
+    let mut base = Vec::new();
+    let mut argv = std::env::args();
+    while let Some(arg) = argv.next() {
+        if arg == "--ab" {
+            argv.next();
+        } else if !arg.starts_with("--ab=") {
+            base.push(arg);
+        }
+    }
+
+    ab.split('|')
+        .map(|strategy| {
+            let strategy = strategy.trim();
+            let argv = base
+                .iter()
+                .map(String::as_str)
+                .chain(strategy.split_whitespace());
+            Ok((strategy.to_string(), Args::try_parse_from(argv)?))
+        })
+        .collect()
+}
+
+/// Runs the optimizer, or compares strategies if `--ab` is given.
+#[cfg(feature = "ab")]
+fn optimize(chip: &mut Chip, args: &Args) -> Result<RunReport> {
+    use cell_move_router::AbOutcome;
+
+    let strategies = match args.ab.as_deref() {
+        Some(ab) => ab_strategies(ab)?,
+        None => return chip.run(args),
+    };
+
+    let outcomes = chip.run_ab(args, &strategies)?;
+    for outcome in outcomes.iter() {
+        eprintln!("{}", outcome);
+    }
+    if let Some(best) = AbOutcome::best(&outcomes) {
+        eprintln!("Writing strategy \"{}\"", best.strategy);
+    }
+    Ok(RunReport::default())
+}
+
+#[cfg(not(feature = "ab"))]
+fn optimize(chip: &mut Chip, args: &Args) -> Result<RunReport> {
+    chip.run(args)
+}
+
+/// The demand footprint of every mastercell, as arrays of demand by layer.
+fn footprints_json(chip: &Chip) -> String {
+    let footprints: Vec<_> = chip
+        .demand_footprints()
+        .iter()
+        .map(|footprint| {
+            let layers: Vec<_> = footprint.iter().map(ToString::to_string).collect();
+            format!("[{}]", layers.join(", "))
+        })
+        .collect();
+    format!("[{}]", footprints.join(", "))
+}
+
+/// The `--stats` output, with the demand footprints
+/// and the bytes of every subsystem if profiled.
+#[cfg(feature = "mem-profile")]
+fn stats_json(chip: &Chip) -> String {
+    let memory = chip.memory_profile();
+    eprintln!("{}", memory);
+    chip.score().to_json_with(&[
+        ("footprints", footprints_json(chip)),
+        ("memory", memory.to_json()),
+    ])
+}
+
+#[cfg(not(feature = "mem-profile"))]
+fn stats_json(chip: &Chip) -> String {
+    chip.score()
+        .to_json_with(&[("footprints", footprints_json(chip))])
+}
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    let mut chip = Chip::default();
+    if run_command(&args)? {
+        return Ok(());
+    }
+
+    let mut chip = Chip {
+        max_diagnostics: args.max_diagnostics,
+        ..Chip::default()
+    };
 
     chip.read_file(&args.infile)?;
-    chip.run(&args)?;
+    eprintln!("{}", chip.provenance);
+    let report = chip.diagnose();
+    if !report.is_empty() {
+        eprintln!("{}", report);
+    }
+
+    if args.difficulty_report {
+        eprintln!("{}", chip.difficulty_report());
+    }
+    let initial = args.gap_report.then(|| chip.net_gaps());
+
+    // If optimization fails, fall back to a solution without any change.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| optimize(&mut chip, &args)))
+        .unwrap_or_else(|_| Err(anyhow!("Optimization panicked")));
+    match result {
+        Ok(run) => {
+            for failure in run.failures.iter() {
+                eprintln!("{}", failure);
+            }
+            if let Some(generation) = run.generation {
+                eprintln!("Keeping the best variant of generation {}", generation);
+            }
+            if let Some(round) = run.restart {
+                eprintln!("Keeping the result of restart {}", round);
+            }
+        }
+        Err(err) => {
+            eprintln!("Error: {}, writing the input unchanged", err);
+            chip.restore_original()?;
+        }
+    }
+
+    if args.strict_legal {
+        chip.ensure_legal()?;
+    }
     chip.write_file(&args.outfile)?;
+    eprintln!("{}", chip.score());
+    if let Some(initial) = initial {
+        eprintln!("{}", chip.gap_report(initial));
+    }
+    if args.placement_report {
+        eprintln!("{}", chip.placement_report());
+    }
+    if let Some(stats) = &args.stats {
+        std::fs::write(stats, stats_json(&chip))?;
+    }
 
     Ok(())
 }
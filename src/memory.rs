@@ -0,0 +1,202 @@
+use crate::{
+    chip::Chip,
+    components::{Blockage, Cell, Conflict, Layer, MasterCell, MasterPin, Net, Pair, Point, Route},
+    history::SegmentOrigin,
+    router::Corridor,
+    snapshot::Snapshot,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter, Result as FmtResult},
+    mem,
+};
+
+/// Heap bytes a value holds through its containers.
+/// Containers count their whole capacity, hash tables one control byte per slot on top.
+pub trait HeapSize {
+    fn heap_size(&self) -> usize;
+}
+
+/// Types without heap allocations of their own.
+macro_rules! flat {
+    ($($ty:ty),*) => {
+        $(impl HeapSize for $ty {
+            fn heap_size(&self) -> usize {
+                0
+            }
+        })*
+    };
+}
+
+flat!(
+    usize,
+    bool,
+    Pair<usize>,
+    Point<usize>,
+    Route<usize>,
+    MasterPin,
+    Blockage,
+    Conflict,
+    SegmentOrigin
+);
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * mem::size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, T::heap_size)
+    }
+}
+
+impl<A: HeapSize, B: HeapSize> HeapSize for (A, B) {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size()
+    }
+}
+
+impl<T: HeapSize> HeapSize for HashSet<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * (mem::size_of::<T>() + 1) + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for HashMap<K, V> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * (mem::size_of::<(K, V)>() + 1)
+            + self
+                .iter()
+                .map(|(key, value)| key.heap_size() + value.heap_size())
+                .sum::<usize>()
+    }
+}
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl HeapSize for Layer {
+    fn heap_size(&self) -> usize {
+        self.name.heap_size()
+            + self.capacity.heap_size()
+            + self.demand.heap_size()
+            + self.base_demand.heap_size()
+    }
+}
+
+impl HeapSize for MasterCell {
+    fn heap_size(&self) -> usize {
+        self.pins().count() * mem::size_of::<MasterPin>()
+            + self.blkgs().count() * mem::size_of::<Blockage>()
+    }
+}
+
+impl HeapSize for Cell {
+    fn heap_size(&self) -> usize {
+        self.pins.heap_size()
+    }
+}
+
+impl HeapSize for Net {
+    fn heap_size(&self) -> usize {
+        self.pins.heap_size() + self.routes.heap_size()
+    }
+}
+
+impl HeapSize for Corridor {
+    fn heap_size(&self) -> usize {
+        self.tiles.heap_size()
+    }
+}
+
+impl HeapSize for Snapshot {
+    fn heap_size(&self) -> usize {
+        self.positions.heap_size() + self.moved.heap_size() + self.routes.heap_size()
+    }
+}
+
+/// Heap bytes held by every subsystem of a chip, see `Chip::memory_profile`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemoryProfile {
+    /// subsystems by name with their bytes, largest first
+    pub subsystems: Vec<(&'static str, usize)>,
+}
+
+impl MemoryProfile {
+    /// Bytes held by all subsystems together.
+    pub fn total(&self) -> usize {
+        self.subsystems.iter().map(|&(_, bytes)| bytes).sum()
+    }
+
+    /// The profile as a JSON object of bytes by subsystem.
+    pub fn to_json(&self) -> String {
+        let subsystems: Vec<_> = self
+            .subsystems
+            .iter()
+            .map(|(name, bytes)| format!("\"{}\": {}", name, bytes))
+            .collect();
+        format!("{{{}}}", subsystems.join(", "))
+    }
+}
+
+impl Display for MemoryProfile {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        for (name, bytes) in self.subsystems.iter() {
+            writeln!(f, "{:>16} {:>12} bytes", name, bytes)?;
+        }
+        write!(f, "{:>16} {:>12} bytes", "total", self.total())
+    }
+}
+
+impl Chip {
+    /// Accounts the heap bytes of the chip's major structures by subsystem,
+    /// to tell which representations are worth compacting first.
+    pub fn memory_profile(&self) -> MemoryProfile {
+        let demand_grids = self.layers.heap_size()
+            + self.congestion.history.heap_size()
+            + self
+                .reservation
+                .as_ref()
+                .map_or(0, |reservation| reservation.rudy.estimates.heap_size());
+        let segment_sets = self
+            .nets
+            .iter()
+            .map(|net| net.routes.heap_size())
+            .sum::<usize>();
+        let snapshots = self.fallback.heap_size() + self.original.heap_size();
+        let history = self
+            .history
+            .as_ref()
+            .map_or(0, |history| history.origins.heap_size());
+        let trees = self.steiner_table.heap_size() + self.corridors.heap_size();
+        let indexes = self.pin_owners.heap_size()
+            + self.pin_nets.heap_size()
+            + self.dirty_nets.heap_size()
+            + self.conflicts.heap_size()
+            + self.layer_ids.heap_size()
+            + self
+                .nets
+                .iter()
+                .map(|net| net.pins.heap_size())
+                .sum::<usize>()
+            + self.nets.capacity() * mem::size_of::<Net>();
+        let cells = self.cells.heap_size() + self.mastercells.heap_size();
+
+        let mut subsystems = vec![
+            ("demand grids", demand_grids),
+            ("segment sets", segment_sets),
+            ("snapshots", snapshots),
+            ("route history", history),
+            ("trees", trees),
+            ("indexes", indexes),
+            ("cells", cells),
+        ];
+        subsystems.sort_by_key(|&(_, bytes)| std::cmp::Reverse(bytes));
+        MemoryProfile { subsystems }
+    }
+}
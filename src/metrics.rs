@@ -0,0 +1,105 @@
+use crate::{chip::Chip, router::TileStats};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// Live counters of a run.
+/// The chip holds them in an `Arc`, so other threads can watch a run in progress.
+#[derive(Debug, Default)]
+pub struct Counters {
+    /// nets given new routes
+    pub nets_rerouted: AtomicUsize,
+    /// nets the router failed to connect
+    pub failed_routes: AtomicUsize,
+    /// cell moves made
+    pub moves_accepted: AtomicUsize,
+    /// cell moves refused
+    pub moves_rejected: AtomicUsize,
+    /// escape paths made for blocked pins
+    pub pin_escapes: AtomicUsize,
+    /// time spent in every finished phase
+    pub phases: Mutex<Vec<(&'static str, Duration)>>,
+    /// contention of the last parallel routing pass
+    pub tiles: Mutex<TileStats>,
+}
+
+/// A snapshot of the counters of a run and the current overflow.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Metrics {
+    /// nets given new routes
+    pub nets_rerouted: usize,
+    /// nets the router failed to connect
+    pub failed_routes: usize,
+    /// cell moves made
+    pub moves_accepted: usize,
+    /// cell moves refused
+    pub moves_rejected: usize,
+    /// escape paths made for blocked pins
+    pub pin_escapes: usize,
+    /// sum of demand exceeding capacity over all GGrids
+    pub overflow: usize,
+    /// time spent in every finished phase
+    pub phases: Vec<(&'static str, Duration)>,
+    /// contention of the last parallel routing pass
+    pub tiles: TileStats,
+}
+
+impl Counters {
+    /// Adds one to a counter.
+    pub fn bump(counter: &AtomicUsize) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records the time spent in a phase.
+    pub fn record_phase(&self, name: &'static str, elapsed: Duration) {
+        if let Ok(mut phases) = self.phases.lock() {
+            phases.push((name, elapsed));
+        }
+    }
+
+    /// Keeps the contention statistics of a parallel routing pass.
+    pub fn record_tiles(&self, stats: TileStats) {
+        if let Ok(mut tiles) = self.tiles.lock() {
+            *tiles = stats;
+        }
+    }
+
+    /// Reads all counters, with `overflow` filled in by the caller.
+    pub fn metrics(&self, overflow: usize) -> Metrics {
+        Metrics {
+            nets_rerouted: self.nets_rerouted.load(Ordering::Relaxed),
+            failed_routes: self.failed_routes.load(Ordering::Relaxed),
+            moves_accepted: self.moves_accepted.load(Ordering::Relaxed),
+            moves_rejected: self.moves_rejected.load(Ordering::Relaxed),
+            pin_escapes: self.pin_escapes.load(Ordering::Relaxed),
+            overflow,
+            phases: self
+                .phases
+                .lock()
+                .map(|phases| phases.clone())
+                .unwrap_or_default(),
+            tiles: self
+                .tiles
+                .lock()
+                .map(|tiles| tiles.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Chip {
+    /// The counters of the run so far and the current overflow.
+    pub fn metrics(&self) -> Metrics {
+        let overflow = self
+            .layers
+            .iter()
+            .flat_map(|layer| layer.capacity.iter().zip(layer.demand.iter()))
+            .map(|(&capacity, &demand)| demand.saturating_sub(capacity))
+            .fold(0, usize::saturating_add);
+        self.counters.metrics(overflow)
+    }
+}
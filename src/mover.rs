@@ -0,0 +1,399 @@
+use crate::{
+    chip::Chip,
+    components::{CellType, ConflictType, Pair},
+    density::DensityMap,
+    metrics::Counters,
+    schedule::{Schedule, Strategy},
+};
+use anyhow::Result;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+/// Keeps the perturbation of the move order apart from the net order's.
+const MOVE_ORDER_SALT: u64 = 0x6365_6c6c;
+
+impl Chip {
+    /// Cost of moving a cell to `target`, in thousandths of a GGrid.
+    /// It is the fine-grained wirelength plus a penalty for exceeding the target density.
+    pub fn move_cost(&self, idx: usize, target: Pair<usize>, density: &DensityMap) -> usize {
+        let wirelength = self.fine_hpwl(idx, target);
+        match self.cells.get(idx) {
+            Some(cell) if cell.position != target => {
+                wirelength + density.penalty(target, self.cell_weight(idx))
+            }
+            _ => wirelength,
+        }
+    }
+
+    /// Moves every movable cell once to its cheapest candidate position
+    /// and reroutes the nets connected to it, until time runs out.
+    /// Cells go in input order, perturbed during a restart.
+    /// The schedule decides how picky to be, and stops the pass once the budget is spent.
+    /// A move whose nets can't be rerouted is undone.
+    /// Returns the number of cells moved.
+    pub fn move_cells(
+        &mut self,
+        start: Instant,
+        duration: Duration,
+        schedule: &mut Schedule,
+    ) -> Result<usize> {
+        self.move_cells_batch(start, duration, schedule, usize::MAX)
+    }
+
+    /// Like `move_cells`, but the pass stops after `batch` moves.
+    pub fn move_cells_batch(
+        &mut self,
+        start: Instant,
+        duration: Duration,
+        schedule: &mut Schedule,
+        batch: usize,
+    ) -> Result<usize> {
+        let mut density = self.density_map();
+        let mut moved = 0;
+
+        let mut order: Vec<_> = (0..self.cells.len()).collect();
+        self.perturb(&mut order, MOVE_ORDER_SALT);
+
+        for idx in order {
+            if moved >= batch || Self::check_time(start, duration).is_err() {
+                break;
+            }
+
+            let remaining = duration.saturating_sub(start.elapsed());
+            let strategy = schedule.strategy(self.moves_remaining(), remaining);
+            if strategy == Strategy::Reroute {
+                break;
+            }
+
+            let attempt = Instant::now();
+            let position = self.cells[idx].position;
+            let (gain, target) = match self.best_candidate(idx, &density) {
+                Some(best) => best,
+                None => {
+                    schedule.record(false, 0, attempt.elapsed());
+                    continue;
+                }
+            };
+            let worth = match strategy {
+                Strategy::Selective => gain > 0 && gain as f64 >= schedule.average_gain(),
+                _ => gain > 0,
+            };
+
+            let accepted = worth && self.move_and_reroute(idx, target)?;
+            if accepted {
+                density.move_weight(position, target, self.cell_weight(idx));
+                moved += 1;
+            }
+            schedule.record(accepted, gain, attempt.elapsed());
+        }
+
+        Ok(moved)
+    }
+
+    /// The cheapest candidate position of a cell, see `move_cost`,
+    /// with the gain of moving there. Ties are broken by the chip's `TieBreak`.
+    /// With a `lookahead` of `k`, the `k` cheapest candidates are ranked
+    /// by the chip's `CandidateScore` instead, see `candidate`.
+    /// `None` if the cell has no candidates.
+    pub fn best_candidate(&self, idx: usize, density: &DensityMap) -> Option<(isize, Pair<usize>)> {
+        let cell = self.cells.get(idx)?;
+        let (position, origin) = (cell.position, cell.origin);
+        let current = self.move_cost(idx, position, density) as isize;
+        let key =
+            |cost: usize, target: Pair<usize>| (cost, self.tie_break.candidate_key(origin, target));
+
+        let mut costs: Vec<_> = self
+            .candidates(idx)
+            .into_iter()
+            .map(|target| (self.move_cost(idx, target, density), target))
+            .collect();
+        let (cost, target) = match self.lookahead {
+            None => costs
+                .into_iter()
+                .min_by_key(|&(cost, target)| key(cost, target))?,
+            Some(k) => {
+                costs.sort_by_cached_key(|&(cost, target)| key(cost, target));
+                costs
+                    .into_iter()
+                    .take(k.max(1))
+                    .map(|(cost, target)| {
+                        let candidate = self.candidate(idx, target, density);
+                        (self.candidate_score.score(&candidate), cost, target)
+                    })
+                    .min_by_key(|&(score, _, target)| key(score, target))
+                    .map(|(_, cost, target)| (cost, target))?
+            }
+        };
+        Some((current - cost as isize, target))
+    }
+
+    /// Median position of the pins of other cells on the nets connected to a cell,
+    /// clamped to the chip and to `max_displacement`, see `within_displacement`.
+    /// `None` if the cell shares no net with another cell.
+    pub fn pin_median(&self, idx: usize) -> Option<Pair<usize>> {
+        let (mut rows, mut cols): (Vec<_>, Vec<_>) = self
+            .cell_nets(idx)
+            .into_iter()
+            .flat_map(|id| self.nets[id].pins.iter())
+            .filter_map(|&pin| {
+                let (owner, _) = *self.pin_owners.get(pin)?;
+                (owner != idx).then(|| self.cells[owner].position)
+            })
+            .map(|Pair(row, col)| (row, col))
+            .unzip();
+        if rows.is_empty() {
+            return None;
+        }
+        rows.sort_unstable();
+        cols.sort_unstable();
+
+        let median = Pair(rows[(rows.len() - 1) / 2], cols[(cols.len() - 1) / 2]);
+        Some(self.within_displacement(idx, median))
+    }
+
+    /// Wirelength-optimal region of a cell: the rectangle, as its lowest and highest GGrid,
+    /// where the half-perimeter wirelength of the connected nets is smallest,
+    /// with the other cells where they are.
+    /// Every net contributes the interval spanned by the pins of other cells on it,
+    /// and the region lies between the two medians of all interval ends, by rows and by columns.
+    /// `None` if the cell shares no net with another cell.
+    pub fn optimal_region(&self, idx: usize) -> Option<(Pair<usize>, Pair<usize>)> {
+        let (mut rows, mut cols) = (Vec::new(), Vec::new());
+        for id in self.cell_nets(idx) {
+            let others: Vec<_> = self.nets[id]
+                .pins
+                .iter()
+                .filter_map(|&pin| {
+                    let (owner, _) = *self.pin_owners.get(pin)?;
+                    (owner != idx).then(|| self.cells[owner].position)
+                })
+                .collect();
+            if others.is_empty() {
+                continue;
+            }
+            rows.push(others.iter().map(Pair::x).min()?);
+            rows.push(others.iter().map(Pair::x).max()?);
+            cols.push(others.iter().map(Pair::y).min()?);
+            cols.push(others.iter().map(Pair::y).max()?);
+        }
+        if rows.is_empty() {
+            return None;
+        }
+        rows.sort_unstable();
+        cols.sort_unstable();
+
+        let half = rows.len() / 2;
+        Some((
+            Pair(rows[half - 1], cols[half - 1]),
+            Pair(rows[half], cols[half]),
+        ))
+    }
+
+    /// Moves every movable cell, in input order, to the GGrid of its optimal region
+    /// closest to where it is, see `optimal_region`, while the move budget and time allow.
+    /// Cells already inside their region stay, targets are kept within `max_displacement`,
+    /// targets without room for the cell's demand footprint are skipped,
+    /// and a move whose nets can't be rerouted is undone.
+    /// Returns the number of cells moved.
+    pub fn move_cells_to_regions(&mut self, start: Instant, duration: Duration) -> Result<usize> {
+        let footprints = self.demand_footprints();
+
+        let mut moved = 0;
+        for idx in 0..self.cells.len() {
+            if Self::check_time(start, duration).is_err() {
+                break;
+            }
+            if !matches!(self.cells[idx].movable, CellType::Movable) {
+                continue;
+            }
+
+            let (Pair(r0, c0), Pair(r1, c1)) = match self.optimal_region(idx) {
+                Some(region) => region,
+                None => continue,
+            };
+            let Pair(row, col) = self.cells[idx].position;
+            let target = self.within_displacement(idx, Pair(row.clamp(r0, r1), col.clamp(c0, c1)));
+            if target == self.cells[idx].position {
+                continue;
+            }
+
+            let fits = footprints
+                .get(self.cells[idx].mastercell)
+                .is_some_and(|footprint| self.fits_footprint(target, footprint));
+            if fits && self.can_move(idx, target) && self.move_and_reroute(idx, target)? {
+                moved += 1;
+            }
+        }
+
+        Ok(moved)
+    }
+
+    /// Clamps `target` to the chip, then pulls it back towards the cell's origin,
+    /// rows first, to stay within `max_displacement`.
+    pub(crate) fn within_displacement(&self, idx: usize, target: Pair<usize>) -> Pair<usize> {
+        let Pair(max_row, max_col) = self.dim;
+        let row = target.x().clamp(1, max_row.max(1));
+        let col = target.y().clamp(1, max_col.max(1));
+
+        let Pair(r0, c0) = self.cells[idx].origin;
+        let limit = match self.max_displacement {
+            Some(limit) => limit,
+            None => return Pair(row, col),
+        };
+        let toward = |from: usize, to: usize, budget: usize| {
+            if to >= from {
+                from + (to - from).min(budget)
+            } else {
+                from - (from - to).min(budget)
+            }
+        };
+        let row = toward(r0, row, limit);
+        let col = toward(c0, col, limit - (row.max(r0) - row.min(r0)));
+        Pair(row, col)
+    }
+
+    /// Greedily moves every movable cell, in input order, to the median of its connected pins,
+    /// see `pin_median`, while the move budget and time allow.
+    /// Targets without room for the cell's demand footprint are skipped,
+    /// and a move whose nets can't be rerouted is undone.
+    /// Returns the number of cells moved.
+    pub fn move_cells_to_medians(&mut self, start: Instant, duration: Duration) -> Result<usize> {
+        let footprints = self.demand_footprints();
+
+        let mut moved = 0;
+        for idx in 0..self.cells.len() {
+            if Self::check_time(start, duration).is_err() {
+                break;
+            }
+
+            let target = match self.pin_median(idx) {
+                Some(target) if target != self.cells[idx].position => target,
+                _ => continue,
+            };
+            let fits = footprints
+                .get(self.cells[idx].mastercell)
+                .is_some_and(|footprint| self.fits_footprint(target, footprint));
+            if fits && self.can_move(idx, target) && self.move_and_reroute(idx, target)? {
+                moved += 1;
+            }
+        }
+
+        Ok(moved)
+    }
+
+    /// Moves a cell and reroutes the dirty nets, which are the ones connected to it.
+    /// Targets without the supply for the cell's blockages are refused, see `admits_blockages`.
+    /// If some net can't be rerouted, the move is rolled back, see `try_move`.
+    /// Returns whether the move was kept.
+    pub(crate) fn move_and_reroute(&mut self, idx: usize, target: Pair<usize>) -> Result<bool> {
+        if !self.admits_blockages(idx, target) {
+            Counters::bump(&self.counters.moves_rejected);
+            return Ok(false);
+        }
+
+        let mut txn = self.try_move(idx, target)?;
+        if txn.reroute_dirty_nets()? == 0 {
+            txn.commit();
+            Ok(true)
+        } else {
+            txn.rollback()?;
+            Ok(false)
+        }
+    }
+
+    /// Spreads co-located cells with sameGGrid conflicts across neighboring GGrids.
+    /// Cells sharing a GGrid form a conflict graph which is colored greedily,
+    /// color 0 keeps a cell in place and every other color is a neighboring GGrid
+    /// with the supply for the cell's blockages, see `admits_blockages`.
+    /// Fixed cells always keep color 0.
+    /// Returns the number of cells moved.
+    pub fn spread_conflicts(&mut self) -> Result<usize> {
+        let mut groups: HashMap<Pair<usize>, Vec<usize>> = HashMap::new();
+        for cell in self.cells.iter() {
+            groups.entry(cell.position).or_default().push(cell.id);
+        }
+
+        let mut moves = Vec::new();
+        for (position, group) in groups.into_iter().filter(|(_, group)| group.len() > 1) {
+            let graph = self.same_grid_conflict_graph(&group);
+
+            let Pair(row, col) = position;
+            let colors = [
+                Some(position),
+                Some(Pair(row, col + 1)),
+                col.checked_sub(1).map(|col| Pair(row, col)),
+                Some(Pair(row + 1, col)),
+                row.checked_sub(1).map(|row| Pair(row, col)),
+            ];
+
+            // Color cells with more conflicts first.
+            let mut order: Vec<_> = (0..group.len()).collect();
+            order.sort_by_key(|&idx| std::cmp::Reverse(graph[idx].len()));
+
+            let mut assigned: Vec<Option<usize>> = vec![None; group.len()];
+            for idx in order {
+                let cell = group[idx];
+                let used: HashSet<_> = graph[idx].iter().filter_map(|&n| assigned[n]).collect();
+
+                let fixed = matches!(self.cells[cell].movable, CellType::Fixed);
+                let color = colors
+                    .iter()
+                    .enumerate()
+                    .filter(|(color, _)| !used.contains(color))
+                    .find(|&(color, target)| match target {
+                        _ if color == 0 => true,
+                        Some(target) => {
+                            !fixed
+                                && self.can_move(cell, *target)
+                                && self.admits_blockages(cell, *target)
+                        }
+                        None => false,
+                    })
+                    .map(|(color, _)| color)
+                    .unwrap_or(0);
+
+                assigned[idx] = Some(color);
+                if let (true, Some(target)) = (color != 0, colors[color]) {
+                    moves.push((cell, target));
+                }
+            }
+        }
+
+        let mut moved = 0;
+        for (cell, target) in moves {
+            // The budget and the supply may run out while moving.
+            if self.can_move(cell, target) && self.admits_blockages(cell, target) {
+                self.move_cell(cell, target)?;
+                moved += 1;
+            }
+        }
+
+        Ok(moved)
+    }
+
+    /// Adjacency lists of sameGGrid conflicts among `cells`, indexed by position in `cells`.
+    fn same_grid_conflict_graph(&self, cells: &[usize]) -> Vec<Vec<usize>> {
+        let mastercells: Vec<_> = cells
+            .iter()
+            .map(|&cell| self.cells[cell].mastercell)
+            .collect();
+
+        let conflicting = |a: usize, b: usize| {
+            self.conflicts.get(&a).is_some_and(|conflicts| {
+                conflicts
+                    .iter()
+                    .any(|conflict| conflict.kind == ConflictType::SameGGrid && conflict.id == b)
+            })
+        };
+
+        (0..cells.len())
+            .map(|i| {
+                (0..cells.len())
+                    .filter(|&j| i != j && conflicting(mastercells[i], mastercells[j]))
+                    .collect()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{
+    chip::Chip,
+    components::{CellType, Pair},
+};
+use anyhow::Result;
+use std::{
+    cmp::Reverse,
+    time::{Duration, Instant},
+};
+
+/// A rectangle of GGrids, bounds inclusive, divided up by `Chip::partition_cells`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Region {
+    /// first and last row
+    pub rows: (usize, usize),
+    /// first and last column
+    pub cols: (usize, usize),
+}
+
+impl Region {
+    /// Checks if a position is inside the region.
+    pub fn contains(&self, Pair(row, col): Pair<usize>) -> bool {
+        (self.rows.0..=self.rows.1).contains(&row) && (self.cols.0..=self.cols.1).contains(&col)
+    }
+
+    /// The position inside the region closest to `position`.
+    pub fn clamp(&self, Pair(row, col): Pair<usize>) -> Pair<usize> {
+        Pair(
+            row.clamp(self.rows.0, self.rows.1),
+            col.clamp(self.cols.0, self.cols.1),
+        )
+    }
+
+    /// The region split in half both ways, top left first;
+    /// a side of a single GGrid isn't split, so there may be fewer than four.
+    pub fn quadrants(&self) -> Vec<Region> {
+        let halves = |(first, last): (usize, usize)| {
+            let middle = first + (last - first) / 2;
+            if first == last {
+                vec![(first, last)]
+            } else {
+                vec![(first, middle), (middle + 1, last)]
+            }
+        };
+        let cols = halves(self.cols);
+        halves(self.rows)
+            .into_iter()
+            .flat_map(|rows| cols.iter().map(move |&cols| Region { rows, cols }))
+            .collect()
+    }
+
+    /// Number of rows and columns.
+    pub fn size(&self) -> Pair<usize> {
+        Pair(self.rows.1 - self.rows.0 + 1, self.cols.1 - self.cols.0 + 1)
+    }
+}
+
+impl Chip {
+    /// Capacity of all layers of the GGrids in a region.
+    pub fn region_capacity(&self, region: Region) -> usize {
+        let (rows, cols) = (region.rows, region.cols);
+        self.layers
+            .iter()
+            .flat_map(|layer| {
+                (rows.0..=rows.1).flat_map(move |row| {
+                    (cols.0..=cols.1)
+                        .filter_map(move |col| layer.get_capacity(row - 1, col - 1).copied())
+                })
+            })
+            .sum()
+    }
+
+    /// Divides `region` into quadrants and assigns each of `cells` to one, recursively,
+    /// until regions are at most `PARTITION_MIN_SIZE` GGrids along both sides or hold one cell.
+    /// A cell goes to the quadrant closest to its `desired` position, ties to the first,
+    /// unless that quadrant would get more than its share of the cells' weight, see `cell_weight`:
+    /// shares follow the capacity of the quadrants, with `PARTITION_TOLERANCE` thousandths to spare.
+    /// The leaf region of every cell is pushed onto `leaves`.
+    pub fn assign_quadrants(
+        &self,
+        region: Region,
+        cells: Vec<usize>,
+        desired: &[Pair<usize>],
+        leaves: &mut Vec<(usize, Region)>,
+    ) {
+        use crate::consts::{PARTITION_MIN_SIZE, PARTITION_TOLERANCE};
+
+        let Pair(rows, cols) = region.size();
+        let quadrants = region.quadrants();
+        if cells.len() <= 1 || (rows <= PARTITION_MIN_SIZE && cols <= PARTITION_MIN_SIZE) {
+            leaves.extend(cells.into_iter().map(|idx| (idx, region)));
+            return;
+        }
+
+        let capacities: Vec<_> = quadrants
+            .iter()
+            .map(|&quadrant| self.region_capacity(quadrant))
+            .collect();
+        let capacity = capacities.iter().sum::<usize>().max(1);
+        let weight: usize = cells.iter().map(|&idx| self.cell_weight(idx)).sum();
+        let limits: Vec<_> = capacities
+            .iter()
+            .map(|&share| weight * share / capacity * (1000 + PARTITION_TOLERANCE) / 1000)
+            .collect();
+
+        let mut loads = vec![0; quadrants.len()];
+        let mut assigned = vec![Vec::new(); quadrants.len()];
+        for idx in cells {
+            let want = desired[idx];
+            let mut ranked: Vec<_> = (0..quadrants.len()).collect();
+            ranked.sort_by_key(|&q| (want.distance(&quadrants[q].clamp(want)), q));
+
+            let cell_weight = self.cell_weight(idx);
+            let quadrant = ranked
+                .iter()
+                .copied()
+                .find(|&q| loads[q] == 0 || loads[q] + cell_weight <= limits[q])
+                .unwrap_or_else(|| {
+                    // Every quadrant is full, so overfill the emptiest relative to its capacity.
+                    (0..quadrants.len())
+                        .min_by_key(|&q| loads[q] * 1000 / capacities[q].max(1))
+                        .unwrap_or(0)
+                });
+            loads[quadrant] += cell_weight;
+            assigned[quadrant].push(idx);
+        }
+
+        for (quadrant, cells) in quadrants.into_iter().zip(assigned) {
+            self.assign_quadrants(quadrant, cells, desired, leaves);
+        }
+    }
+
+    /// Places the movable cells globally by quadrisection, see `assign_quadrants`,
+    /// each cell wanting to be at the median of its connected pins, see `pin_median`.
+    /// A cell outside its leaf region moves to the position in it closest to the median,
+    /// kept within `max_displacement`, if that shortens its nets, see `fine_hpwl`;
+    /// the moves shortening them most are made first while the move budget and time allow.
+    /// Targets without room for the cell's demand footprint are skipped,
+    /// and a move whose nets can't be rerouted is undone.
+    /// Returns the number of cells moved.
+    pub fn partition_cells(&mut self, start: Instant, duration: Duration) -> Result<usize> {
+        let Pair(rows, cols) = self.dim;
+        if rows == 0 || cols == 0 {
+            return Ok(0);
+        }
+
+        let desired: Vec<_> = (0..self.cells.len())
+            .map(|idx| self.pin_median(idx).unwrap_or(self.cells[idx].position))
+            .collect();
+        let movable: Vec<_> = (0..self.cells.len())
+            .filter(|&idx| matches!(self.cells[idx].movable, CellType::Movable))
+            .collect();
+        let chip = Region {
+            rows: (1, rows),
+            cols: (1, cols),
+        };
+        let mut leaves = Vec::with_capacity(movable.len());
+        self.assign_quadrants(chip, movable, &desired, &mut leaves);
+
+        let mut moves: Vec<_> = leaves
+            .into_iter()
+            .filter_map(|(idx, leaf)| {
+                let position = self.cells[idx].position;
+                if leaf.contains(position) {
+                    return None;
+                }
+                let target = self.within_displacement(idx, leaf.clamp(desired[idx]));
+                let before = self.fine_hpwl(idx, position);
+                let after = self.fine_hpwl(idx, target);
+                (target != position && after < before).then_some((before - after, idx, target))
+            })
+            .collect();
+        moves.sort_by_key(|&(gain, idx, _)| (Reverse(gain), idx));
+
+        let footprints = self.demand_footprints();
+        let mut moved = 0;
+        for (_, idx, target) in moves {
+            if Self::check_time(start, duration).is_err() || self.moves_remaining() == 0 {
+                break;
+            }
+            let fits = footprints
+                .get(self.cells[idx].mastercell)
+                .is_some_and(|footprint| self.fits_footprint(target, footprint));
+            if fits && self.can_move(idx, target) && self.move_and_reroute(idx, target)? {
+                moved += 1;
+            }
+        }
+
+        Ok(moved)
+    }
+}
@@ -0,0 +1,475 @@
+use crate::{chip::Chip, schedule::Schedule};
+use anyhow::{anyhow, Error, Result};
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// A pass of a pipeline, see `Pipeline`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Pass {
+    /// `spread`: spreads co-located conflicting cells, see `spread_conflicts`.
+    Spread,
+    /// `partition`: places cells globally by quadrisection, see `partition_cells`.
+    Partition,
+    /// `median`: moves cells to the median of their pins, see `move_cells_to_medians`.
+    Median,
+    /// `region`: moves cells into their wirelength-optimal regions, see `move_cells_to_regions`.
+    Region,
+    /// `move` or `move(batch=N)`: one pass of the cost-driven mover,
+    /// making at most `batch` moves, see `move_cells_batch`.
+    Move { batch: Option<usize> },
+    /// `force`: the force-directed mover, see `move_cells_force`.
+    Force,
+    /// `gain`: one pass of the gain-ordered mover, see `move_cells_by_gain`.
+    Gain,
+    /// `cluster`: moves clusters of tightly connected cells as units, see `move_clusters`.
+    Cluster,
+    /// `gravity`: moves cells to their length-weighted centers of gravity, see `move_cells_by_gravity`.
+    Gravity,
+    /// `windows`: places the cells of small windows optimally, see `place_windows`.
+    Windows,
+    /// `hotspots` or `hotspots(top=N)`: moves cells away from the `top` most overflowed GGrids,
+    /// `HOTSPOT_COUNT` by default, see `relieve_hotspots`.
+    Hotspots { top: Option<usize> },
+    /// `tiles(factor=N)`: moves cells into the super-GGrids planned on a coarsened chip,
+    /// see `move_cells_to_tiles`.
+    Tiles { factor: usize },
+    /// `coarse(factor=N)`: plans corridors on a coarsened chip, see `plan_coarse_routes`.
+    Coarse { factor: usize },
+    /// `reroute`, `reroute(all)` or `reroute(dirty)`: reroutes every net, or only the dirty ones.
+    Reroute { dirty: bool },
+    /// `soft(bound=N)`: routes with bounded overflow, see `soft_route_nets`.
+    Soft { bound: usize },
+    /// `negotiate`: negotiates the overflow away, see `negotiate`.
+    Negotiate,
+    /// `relocate`: slides Steiner points, see `relocate_all_steiner_points`.
+    Relocate,
+    /// `exact`: routes tiny nets exactly, see `exact_route_nets`.
+    #[cfg(feature = "exact")]
+    Exact,
+    /// `prune`: cleans up the routes, see `clean_up_all_routes`.
+    Prune,
+    /// `vias`: removes needless vias, see `minimize_all_vias`.
+    Vias,
+    /// `repair`: repairs the violations, see `fix`.
+    Repair,
+    /// `legalize`: rolls back to the last legal state if the current one isn't, see `ensure_legal`.
+    Legalize,
+    /// `checkpoint`: remembers the current state if it is legal, see `checkpoint`.
+    Checkpoint,
+}
+
+/// When a loop of a pipeline stops, besides after an iteration doing nothing.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Until {
+    /// `time(P%)`: once P percent of the time is spent.
+    Time(usize),
+}
+
+/// A stage of a pipeline, see `Pipeline`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Stage {
+    Pass(Pass),
+    /// `loop(...)` or `loop(...) until ...`: repeats its stages
+    /// until an iteration does nothing, the condition holds, or time runs out.
+    Loop(Vec<Stage>, Option<Until>),
+}
+
+/// The optimization passes to run, in order, parsed from a small language:
+/// passes separated by `;`, with arguments in parentheses, and loops around them, e.g.
+/// `repair; loop(move(batch=100); reroute(dirty); prune) until time(90%); legalize`.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Pipeline {
+    pub stages: Vec<Stage>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Word(String),
+    Number(usize),
+    Symbol(char),
+}
+
+/// Splits a pipeline into words, numbers and the symbols `;(),=%`.
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if ";(),=%".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else if c.is_ascii_digit() {
+            let mut digits = String::new();
+            while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                digits.push(d);
+                chars.next();
+            }
+            tokens.push(Token::Number(digits.parse()?));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some(&w) = chars
+                .peek()
+                .filter(|w| w.is_ascii_alphanumeric() || **w == '_')
+            {
+                word.push(w);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        } else {
+            return Err(anyhow!("Unexpected character in pipeline: {}", c));
+        }
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser over the tokens of a pipeline.
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.at).cloned();
+        self.at += 1;
+        token
+    }
+
+    /// Consumes `symbol` if it comes next.
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.at += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<()> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(anyhow!("Expected '{}' in pipeline", symbol))
+        }
+    }
+
+    fn word(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            _ => Err(anyhow!("Expected a name in pipeline")),
+        }
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(number),
+            _ => Err(anyhow!("Expected a number in pipeline")),
+        }
+    }
+
+    /// Stages separated by `;`, up to a `)` or the end.
+    fn stages(&mut self) -> Result<Vec<Stage>> {
+        let mut stages = Vec::new();
+        loop {
+            while self.eat(';') {}
+            match self.peek() {
+                None | Some(Token::Symbol(')')) => return Ok(stages),
+                _ => stages.push(self.stage()?),
+            }
+            match self.peek() {
+                None | Some(Token::Symbol(')')) | Some(Token::Symbol(';')) => {}
+                _ => return Err(anyhow!("Expected ';' between passes in pipeline")),
+            }
+        }
+    }
+
+    fn stage(&mut self) -> Result<Stage> {
+        let name = self.word()?;
+        if name == "loop" {
+            self.expect('(')?;
+            let body = self.stages()?;
+            self.expect(')')?;
+            let until = if self.peek() == Some(&Token::Word("until".to_string())) {
+                self.at += 1;
+                Some(self.until()?)
+            } else {
+                None
+            };
+            return Ok(Stage::Loop(body, until));
+        }
+
+        let args = self.args()?;
+        self.pass(&name, args).map(Stage::Pass)
+    }
+
+    fn until(&mut self) -> Result<Until> {
+        match self.word()?.as_str() {
+            "time" => {
+                self.expect('(')?;
+                let percent = self.number()?;
+                self.expect('%')?;
+                self.expect(')')?;
+                Ok(Until::Time(percent.min(100)))
+            }
+            other => Err(anyhow!("Unknown loop condition: {}", other)),
+        }
+    }
+
+    /// Arguments in parentheses, each a word optionally followed by `=` and a number.
+    fn args(&mut self) -> Result<Vec<(String, Option<usize>)>> {
+        let mut args = Vec::new();
+        if !self.eat('(') {
+            return Ok(args);
+        }
+        if self.eat(')') {
+            return Ok(args);
+        }
+        loop {
+            let name = self.word()?;
+            let value = if self.eat('=') {
+                Some(self.number()?)
+            } else {
+                None
+            };
+            args.push((name, value));
+            if self.eat(')') {
+                return Ok(args);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    /// The pass named `name`, checking its arguments.
+    fn pass(&self, name: &str, args: Vec<(String, Option<usize>)>) -> Result<Pass> {
+        let value = |key: &str| -> Result<Option<usize>> {
+            let mut value = None;
+            for (arg, given) in args.iter() {
+                if arg != key {
+                    return Err(anyhow!("Unknown argument of {}: {}", name, arg));
+                }
+                value = Some(given.ok_or_else(|| anyhow!("Missing value of {}", arg))?);
+            }
+            Ok(value)
+        };
+        let none = || -> Result<()> {
+            match args.first() {
+                Some((arg, _)) => Err(anyhow!("Unknown argument of {}: {}", name, arg)),
+                None => Ok(()),
+            }
+        };
+        let required = |key: &str| -> Result<usize> {
+            value(key)?.ok_or_else(|| anyhow!("Missing argument of {}: {}", name, key))
+        };
+
+        let pass = match name {
+            "spread" => none().map(|_| Pass::Spread)?,
+            "partition" => none().map(|_| Pass::Partition)?,
+            "median" => none().map(|_| Pass::Median)?,
+            "region" => none().map(|_| Pass::Region)?,
+            "move" => Pass::Move {
+                batch: value("batch")?,
+            },
+            "force" => none().map(|_| Pass::Force)?,
+            "gain" => none().map(|_| Pass::Gain)?,
+            "cluster" => none().map(|_| Pass::Cluster)?,
+            "gravity" => none().map(|_| Pass::Gravity)?,
+            "windows" => none().map(|_| Pass::Windows)?,
+            "hotspots" => Pass::Hotspots { top: value("top")? },
+            "tiles" => Pass::Tiles {
+                factor: required("factor")?,
+            },
+            "coarse" => Pass::Coarse {
+                factor: required("factor")?,
+            },
+            "reroute" => match args.as_slice() {
+                [] => Pass::Reroute { dirty: false },
+                [(arg, None)] if arg == "all" => Pass::Reroute { dirty: false },
+                [(arg, None)] if arg == "dirty" => Pass::Reroute { dirty: true },
+                _ => return Err(anyhow!("reroute takes all or dirty")),
+            },
+            "soft" => Pass::Soft {
+                bound: required("bound")?,
+            },
+            "negotiate" => none().map(|_| Pass::Negotiate)?,
+            "relocate" => none().map(|_| Pass::Relocate)?,
+            #[cfg(feature = "exact")]
+            "exact" => none().map(|_| Pass::Exact)?,
+            "prune" => none().map(|_| Pass::Prune)?,
+            "vias" => none().map(|_| Pass::Vias)?,
+            "repair" => none().map(|_| Pass::Repair)?,
+            "legalize" => none().map(|_| Pass::Legalize)?,
+            "checkpoint" => none().map(|_| Pass::Checkpoint)?,
+            _ => return Err(anyhow!("Unknown pass: {}", name)),
+        };
+        Ok(pass)
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            at: 0,
+        };
+        let stages = parser.stages()?;
+        if parser.peek().is_some() {
+            return Err(anyhow!("Unmatched ')' in pipeline"));
+        }
+        Ok(Pipeline { stages })
+    }
+}
+
+impl Pass {
+    /// Name of the pass in the language, also its name in traces and phase timings.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::Spread => "spread",
+            Pass::Partition => "partition",
+            Pass::Median => "median",
+            Pass::Region => "region",
+            Pass::Move { .. } => "move",
+            Pass::Force => "force",
+            Pass::Gain => "gain",
+            Pass::Cluster => "cluster",
+            Pass::Gravity => "gravity",
+            Pass::Windows => "windows",
+            Pass::Hotspots { .. } => "hotspots",
+            Pass::Tiles { .. } => "tiles",
+            Pass::Coarse { .. } => "coarse",
+            Pass::Reroute { .. } => "reroute",
+            Pass::Soft { .. } => "soft",
+            Pass::Negotiate => "negotiate",
+            Pass::Relocate => "relocate",
+            #[cfg(feature = "exact")]
+            Pass::Exact => "exact",
+            Pass::Prune => "prune",
+            Pass::Vias => "vias",
+            Pass::Repair => "repair",
+            Pass::Legalize => "legalize",
+            Pass::Checkpoint => "checkpoint",
+        }
+    }
+}
+
+impl Chip {
+    /// Runs the stages of a pipeline until `duration` after `start`.
+    /// Returns the work done, see `run_pass`.
+    pub fn run_pipeline(
+        &mut self,
+        pipeline: &Pipeline,
+        start: Instant,
+        duration: Duration,
+    ) -> Result<usize> {
+        self.run_stages(&pipeline.stages, start, duration)
+    }
+
+    fn run_stages(
+        &mut self,
+        stages: &[Stage],
+        start: Instant,
+        duration: Duration,
+    ) -> Result<usize> {
+        let mut work = 0;
+        for stage in stages {
+            if Self::check_time(start, duration).is_err() {
+                break;
+            }
+            work += match stage {
+                Stage::Pass(pass) => self.run_pass(*pass, start, duration)?,
+                Stage::Loop(body, until) => {
+                    // The stages of the loop stop at its deadline too.
+                    let duration = match until {
+                        Some(Until::Time(percent)) => duration * *percent as u32 / 100,
+                        None => duration,
+                    };
+                    let mut total = 0;
+                    loop {
+                        let done = self.run_stages(body, start, duration)?;
+                        total += done;
+                        if done == 0 || Self::check_time(start, duration).is_err() {
+                            break;
+                        }
+                    }
+                    total
+                }
+            };
+        }
+        Ok(work)
+    }
+
+    /// Runs a single pass, recording its time as a phase.
+    /// Returns the work it did: the cells moved, nets rerouted,
+    /// GGrids saved or violations repaired, so a loop knows when to stop.
+    pub fn run_pass(&mut self, pass: Pass, start: Instant, duration: Duration) -> Result<usize> {
+        let phase = Instant::now();
+        self.begin_pass(pass.name());
+        let work = match pass {
+            Pass::Spread => self.spread_conflicts()?,
+            Pass::Partition => self.partition_cells(start, duration)?,
+            Pass::Median => self.move_cells_to_medians(start, duration)?,
+            Pass::Region => self.move_cells_to_regions(start, duration)?,
+            Pass::Move { batch } => {
+                let mut schedule = Schedule::default();
+                let batch = batch.unwrap_or(usize::MAX);
+                self.move_cells_batch(start, duration, &mut schedule, batch)?
+            }
+            Pass::Force => self.move_cells_force(start, duration)?,
+            Pass::Gain => self.move_cells_by_gain(start, duration)?,
+            Pass::Cluster => self.move_clusters(start, duration)?,
+            Pass::Gravity => self.move_cells_by_gravity(start, duration)?,
+            Pass::Windows => self.place_windows(start, duration)?,
+            Pass::Hotspots { top } => {
+                use crate::consts::HOTSPOT_COUNT;
+
+                let top = top.unwrap_or(HOTSPOT_COUNT);
+                self.relieve_hotspots(top, start, duration)?
+            }
+            Pass::Tiles { factor } => self.move_cells_to_tiles(factor, start, duration)?,
+            Pass::Coarse { factor } => {
+                self.plan_coarse_routes(factor);
+                0
+            }
+            Pass::Reroute { dirty: true } => {
+                let before = self.dirty_nets.len();
+                before.saturating_sub(self.reroute_dirty_nets()?)
+            }
+            Pass::Reroute { dirty: false } => {
+                if self.parallel_routing {
+                    self.reroute_nets_parallel(start, duration)?
+                } else {
+                    self.reroute_nets(start, duration)?
+                }
+            }
+            Pass::Soft { bound } => self.soft_route_nets(bound, start, duration)?,
+            Pass::Negotiate => self.negotiate(start, duration)?,
+            Pass::Relocate => self.relocate_all_steiner_points()?,
+            #[cfg(feature = "exact")]
+            Pass::Exact => self.exact_route_nets(start, duration)?,
+            Pass::Prune => self.clean_up_all_routes()?,
+            Pass::Vias => self.minimize_all_vias()?,
+            Pass::Repair => {
+                let before = self.verify().len();
+                before.saturating_sub(self.fix(start, duration)?)
+            }
+            Pass::Legalize => {
+                self.ensure_legal()?;
+                0
+            }
+            Pass::Checkpoint => {
+                self.checkpoint();
+                0
+            }
+        };
+        self.counters.record_phase(pass.name(), phase.elapsed());
+        Ok(work)
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{
+    chip::Chip,
+    components::{CellType, Pair},
+};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// Why a cell can't move to a position, see `Chip::move_rejection` and `Chip::candidate_checks`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Skip {
+    /// the position is not on the chip
+    OffChip,
+    /// the cell is fixed
+    Fixed,
+    /// the move budget is spent
+    Budget,
+    /// the position is farther from the cell's origin than `max_displacement`
+    Displacement,
+    /// some layer lacks the supply for the cell's demand footprint
+    Supply,
+    /// some layer lacks the supply for the cell's conflicts with the cells around
+    Conflicts,
+}
+
+impl Skip {
+    /// Every reason, in the order they are checked.
+    pub const ALL: [Skip; 6] = [
+        Skip::OffChip,
+        Skip::Fixed,
+        Skip::Budget,
+        Skip::Displacement,
+        Skip::Supply,
+        Skip::Conflicts,
+    ];
+
+    /// Name of the reason in reports and errors.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Skip::OffChip => "off chip",
+            Skip::Fixed => "fixed",
+            Skip::Budget => "budget",
+            Skip::Displacement => "displacement",
+            Skip::Supply => "supply",
+            Skip::Conflicts => "conflicts",
+        }
+    }
+}
+
+/// How many cells may move and did, and why candidate positions were skipped.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PlacementReport {
+    /// movable cells
+    pub movable: usize,
+    /// fixed cells
+    pub fixed: usize,
+    /// cells counted as moved, see `Chip::already_moved`
+    pub moved: usize,
+    /// fixed cells away from their origin, only ever nonzero in an illegal state
+    pub fixed_moved: usize,
+    /// candidate positions of the movable cells skipped, by reason, see `Chip::candidate_checks`
+    pub skipped: Vec<(Skip, usize)>,
+}
+
+impl Chip {
+    /// Every position searched for candidates of a cell, with the reason it is skipped,
+    /// `None` for the candidates, see `candidates`.
+    /// The search is restricted to the window `max_displacement` allows around the origin,
+    /// and the cell's current position is left out.
+    pub fn candidate_checks(
+        &self,
+        idx: usize,
+        occupants: &HashMap<Pair<usize>, Vec<usize>>,
+    ) -> Vec<(Pair<usize>, Option<Skip>)> {
+        let cell = match self.cells.get(idx) {
+            Some(cell) => cell,
+            None => return Vec::new(),
+        };
+        let footprint = match self.mastercells.get(cell.mastercell) {
+            Some(mc) => mc.demand_footprint(self.conflicts.get(&mc.id), self.layers.len()),
+            None => Vec::new(),
+        };
+
+        let Pair(rows, cols) = self.dim;
+        let Pair(row, col) = cell.origin;
+
+        // Restrict the search window when the displacement is bounded.
+        let (row_range, col_range) = match self.max_displacement {
+            Some(limit) => (
+                row.saturating_sub(limit).max(1)..=(row + limit).min(rows),
+                col.saturating_sub(limit).max(1)..=(col + limit).min(cols),
+            ),
+            None => (1..=rows, 1..=cols),
+        };
+
+        row_range
+            .flat_map(|r| col_range.clone().map(move |c| Pair(r, c)))
+            .filter(|&target| target != cell.position)
+            .map(|target| {
+                let skip = self.move_rejection(idx, target).or_else(|| {
+                    if !self.fits_footprint(target, &footprint) {
+                        Some(Skip::Supply)
+                    } else if !self.placement_fits(idx, target, occupants) {
+                        Some(Skip::Conflicts)
+                    } else {
+                        None
+                    }
+                });
+                (target, skip)
+            })
+            .collect()
+    }
+
+    /// Counts the movable, fixed and moved cells,
+    /// and why the candidate positions of the movable cells are skipped right now.
+    pub fn placement_report(&self) -> PlacementReport {
+        let mut report = PlacementReport {
+            moved: self.already_moved,
+            ..PlacementReport::default()
+        };
+
+        let occupants = self.cells_by_position();
+        let mut skipped: HashMap<Skip, usize> = HashMap::new();
+        for (idx, cell) in self.cells.iter().enumerate() {
+            if !matches!(cell.movable, CellType::Movable) {
+                report.fixed += 1;
+                if cell.position != cell.origin {
+                    report.fixed_moved += 1;
+                }
+                continue;
+            }
+
+            report.movable += 1;
+            for (_, skip) in self.candidate_checks(idx, &occupants) {
+                if let Some(skip) = skip {
+                    *skipped.entry(skip).or_default() += 1;
+                }
+            }
+        }
+
+        report.skipped = Skip::ALL
+            .iter()
+            .map(|skip| (*skip, skipped.get(skip).copied().unwrap_or(0)))
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        report
+    }
+}
+
+impl Display for PlacementReport {
+    /// The cell counts on one line, then the skipped candidates by reason.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "Cells: {} movable, {} fixed, {} moved",
+            self.movable, self.fixed, self.moved
+        )?;
+        if self.fixed_moved > 0 {
+            write!(f, ", {} fixed away from their origin", self.fixed_moved)?;
+        }
+
+        let total: usize = self.skipped.iter().map(|&(_, count)| count).sum();
+        write!(f, "\nCandidates skipped: {}", total)?;
+        for &(skip, count) in self.skipped.iter() {
+            write!(f, "\n{}: {}", skip.name(), count)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{
+    components::{CellType, ConflictType, Direction, Pair, Point, Route, SectionCounts},
+    io::{self, Tokenizer},
+    utilities,
+};
+use anyhow::{Context, Result};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// The content of an input file, mirrored section by section.
+/// Names are kept as they are written and nothing is cross-checked,
+/// that happens when a `RawDesign` is compiled into a `Chip`.
+#[derive(Clone, Debug, Default)]
+pub struct RawDesign {
+    /// MaxCellMove
+    pub max_move: usize,
+    /// GGridBoundaryIdx, the last row and column
+    pub dim: Pair<usize>,
+    /// NumLayer
+    pub layers: Vec<RawLayer>,
+    /// NumNonDefaultSupplyGGrid
+    pub non_default_supply: Vec<RawSupply>,
+    /// NumMasterCell
+    pub mastercells: Vec<RawMasterCell>,
+    /// NumNeighborCellExtraDemand
+    pub conflicts: Vec<RawConflict>,
+    /// NumCellInst
+    pub cells: Vec<RawCell>,
+    /// NumNets
+    pub nets: Vec<RawNet>,
+    /// NumRoutes
+    pub routes: Vec<RawRoute>,
+    /// NumPinOffsets, empty if the section is absent
+    pub pin_offsets: Vec<RawPinOffset>,
+}
+
+/// The content of a solution file, mirrored section by section.
+/// Like `RawDesign`, names are resolved against a chip later, see `Chip::apply_solution`.
+#[derive(Clone, Debug, Default)]
+pub struct RawSolution {
+    /// NumMovedCellInst
+    pub moved: Vec<RawMovedCell>,
+    /// NumRoutes
+    pub routes: Vec<RawRoute>,
+}
+
+/// Lay <layerName> <Idx> <RoutingDirection> <defaultSupplyOfOneGGrid>
+#[derive(Clone, Debug)]
+pub struct RawLayer {
+    pub name: String,
+    pub direction: Direction,
+    pub supply: usize,
+}
+
+/// <rowIdx> <colIdx> <LayIdx> <incrOrDecrValue>
+#[derive(Clone, Debug)]
+pub struct RawSupply {
+    pub point: Point<usize>,
+    pub delta: isize,
+}
+
+/// MasterCell <masterCellName> <pinCount> <blockageCount>
+#[derive(Clone, Debug)]
+pub struct RawMasterCell {
+    pub name: String,
+    pub pins: Vec<RawPin>,
+    pub blkgs: Vec<RawBlockage>,
+}
+
+/// Pin <pinName> <pinLayer>
+#[derive(Clone, Debug)]
+pub struct RawPin {
+    pub name: String,
+    pub layer: String,
+}
+
+/// Blkg <blockageName> <blockageLayer> <demand>
+#[derive(Clone, Debug)]
+pub struct RawBlockage {
+    pub name: String,
+    pub layer: String,
+    pub demand: usize,
+}
+
+/// <sameGGrid|adjHGGrid> <masterCellName1> <masterCellName2> <layerName> <demand>
+#[derive(Clone, Debug)]
+pub struct RawConflict {
+    pub kind: ConflictType,
+    pub mastercells: (String, String),
+    pub layer: String,
+    pub demand: usize,
+}
+
+/// CellInst <instName> <masterCellName> <gGridRowIdx> <gGridColIdx> <movableCstr>
+#[derive(Clone, Debug)]
+pub struct RawCell {
+    pub name: String,
+    pub mastercell: String,
+    pub position: Pair<usize>,
+    pub movable: CellType,
+}
+
+/// Net <netName> <numPins> <minRoutingLayConstraint>
+#[derive(Clone, Debug)]
+pub struct RawNet {
+    pub name: String,
+    /// `None` means NoCstr
+    pub min_layer: Option<String>,
+    /// Pin <instName>/<masterPinName>
+    pub pins: Vec<(String, String)>,
+}
+
+/// <sRowIdx> <sColIdx> <sLayIdx> <eRowIdx> <eColIdx> <eLayIdx> <netName>
+#[derive(Clone, Debug)]
+pub struct RawRoute {
+    pub route: Route<usize>,
+    pub net: String,
+}
+
+/// CellInst <instName> <gGridRowIdx> <gGridColIdx>
+#[derive(Clone, Debug)]
+pub struct RawMovedCell {
+    pub name: String,
+    pub position: Pair<usize>,
+}
+
+/// PinOffset <masterCellName> <pinName> <rowOffset> <colOffset>
+#[derive(Clone, Debug)]
+pub struct RawPinOffset {
+    pub mastercell: String,
+    pub pin: String,
+    pub offset: Pair<isize>,
+}
+
+impl RawDesign {
+    /// Parses a whole input file.
+    /// Only the syntax is checked, names are resolved by `Chip::compile`.
+    pub fn parse(content: &str) -> Result<Self> {
+        let content = &mut Tokenizer::new(content);
+
+        // The syntax leaves no way to resynchronize, so parsing stops at the first error.
+        let raw = Self {
+            max_move: io::parse_max_move(content).context("Invalid MaxCellMove")?,
+            dim: io::parse_dimensions(content).context("Invalid GGridBoundaryIdx")?,
+            layers: io::parse_layers(content).context("Invalid NumLayer section")?,
+            non_default_supply: io::parse_non_default_supply(content)
+                .context("Invalid NumNonDefaultSupplyGGrid section")?,
+            mastercells: io::parse_master_cells(content)
+                .context("Invalid NumMasterCell section")?,
+            conflicts: io::parse_conflicts(content)
+                .context("Invalid NumNeighborCellExtraDemand section")?,
+            cells: io::parse_cells(content).context("Invalid NumCellInst section")?,
+            nets: io::parse_nets(content).context("Invalid NumNets section")?,
+            routes: io::parse_routes(content).context("Invalid NumRoutes section")?,
+            pin_offsets: io::parse_pin_offsets(content).context("Invalid NumPinOffsets section")?,
+        };
+
+        // parsing ends here
+        utilities::check_eq(content.next(), None)
+            .context("Unexpected content after the last section")?;
+
+        Ok(raw)
+    }
+
+    /// Number of entries in every section.
+    pub fn counts(&self) -> SectionCounts {
+        SectionCounts {
+            layers: self.layers.len(),
+            non_default_supply: self.non_default_supply.len(),
+            mastercells: self.mastercells.len(),
+            conflicts: self.conflicts.len(),
+            cells: self.cells.len(),
+            nets: self.nets.len(),
+            routes: self.routes.len(),
+        }
+    }
+}
+
+impl RawSolution {
+    /// Parses a whole solution file.
+    pub fn parse(content: &str) -> Result<Self> {
+        let content = &mut Tokenizer::new(content);
+
+        let raw = Self {
+            moved: io::parse_moved_cells(content).context("Invalid NumMovedCellInst section")?,
+            routes: io::parse_routes(content).context("Invalid NumRoutes section")?,
+        };
+
+        // parsing ends here
+        utilities::check_eq(content.next(), None)
+            .context("Unexpected content after the last section")?;
+
+        Ok(raw)
+    }
+}
+
+impl Display for RawDesign {
+    /// Writes the design back in the input file format.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        writeln!(f, "MaxCellMove {}", self.max_move)?;
+        writeln!(f, "GGridBoundaryIdx 1 1 {}", self.dim)?;
+
+        writeln!(f, "NumLayer {}", self.layers.len())?;
+        for (idx, layer) in self.layers.iter().enumerate() {
+            let direction = match layer.direction {
+                Direction::Horizontal => "H",
+                Direction::Vertical => "V",
+            };
+            writeln!(
+                f,
+                "Lay {} {} {} {}",
+                layer.name,
+                idx + 1,
+                direction,
+                layer.supply
+            )?;
+        }
+
+        writeln!(
+            f,
+            "NumNonDefaultSupplyGGrid {}",
+            self.non_default_supply.len()
+        )?;
+        for supply in self.non_default_supply.iter() {
+            writeln!(f, "{} {}", supply.point, supply.delta)?;
+        }
+
+        writeln!(f, "NumMasterCell {}", self.mastercells.len())?;
+        for mc in self.mastercells.iter() {
+            writeln!(
+                f,
+                "MasterCell {} {} {}",
+                mc.name,
+                mc.pins.len(),
+                mc.blkgs.len()
+            )?;
+            for pin in mc.pins.iter() {
+                writeln!(f, "Pin {} {}", pin.name, pin.layer)?;
+            }
+            for blkg in mc.blkgs.iter() {
+                writeln!(f, "Blkg {} {} {}", blkg.name, blkg.layer, blkg.demand)?;
+            }
+        }
+
+        writeln!(f, "NumNeighborCellExtraDemand {}", self.conflicts.len())?;
+        for conflict in self.conflicts.iter() {
+            let kind = match conflict.kind {
+                ConflictType::SameGGrid => "sameGGrid",
+                ConflictType::AdjHGGrid => "adjHGGrid",
+            };
+            let (a, b) = &conflict.mastercells;
+            writeln!(
+                f,
+                "{} {} {} {} {}",
+                kind, a, b, conflict.layer, conflict.demand
+            )?;
+        }
+
+        writeln!(f, "NumCellInst {}", self.cells.len())?;
+        for cell in self.cells.iter() {
+            let movable = match cell.movable {
+                CellType::Movable => "Movable",
+                CellType::Fixed => "Fixed",
+            };
+            writeln!(
+                f,
+                "CellInst {} {} {} {}",
+                cell.name, cell.mastercell, cell.position, movable
+            )?;
+        }
+
+        writeln!(f, "NumNets {}", self.nets.len())?;
+        for net in self.nets.iter() {
+            let min_layer = net.min_layer.as_deref().unwrap_or("NoCstr");
+            writeln!(f, "Net {} {} {}", net.name, net.pins.len(), min_layer)?;
+            for (cell, pin) in net.pins.iter() {
+                writeln!(f, "Pin {}/{}", cell, pin)?;
+            }
+        }
+
+        writeln!(f, "NumRoutes {}", self.routes.len())?;
+        for route in self.routes.iter() {
+            writeln!(f, "{} {}", route.route, route.net)?;
+        }
+
+        if !self.pin_offsets.is_empty() {
+            writeln!(f, "NumPinOffsets {}", self.pin_offsets.len())?;
+            for offset in self.pin_offsets.iter() {
+                writeln!(
+                    f,
+                    "PinOffset {} {} {}",
+                    offset.mastercell, offset.pin, offset.offset
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
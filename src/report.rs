@@ -0,0 +1,140 @@
+use crate::{chip::Chip, consts::MAX_DIAGNOSTICS, verify::Violation};
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// How bad a diagnostic is.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    /// the input or solution is usable, but likely not what was intended
+    Warning,
+    /// the input or solution cannot be used
+    Error,
+}
+
+/// A problem found in an input or a solution.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Problems collected in one run, so all of them can be shown together.
+/// Only the first `limit` are kept, the rest are only counted.
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    /// most diagnostics kept
+    pub limit: usize,
+    /// diagnostics kept, in the order they were found
+    pub diagnostics: Vec<Diagnostic>,
+    /// number of errors found, including those not kept
+    pub errors: usize,
+    /// number of warnings found, including those not kept
+    pub warnings: usize,
+}
+
+impl Report {
+    /// An empty report keeping at most `limit` diagnostics.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            ..Self::default()
+        }
+    }
+
+    /// Adds a diagnostic, dropped if the report is full.
+    pub fn push(&mut self, severity: Severity, message: impl Display) {
+        match severity {
+            Severity::Warning => self.warnings += 1,
+            Severity::Error => self.errors += 1,
+        }
+        if self.diagnostics.len() < self.limit {
+            self.diagnostics.push(Diagnostic {
+                severity,
+                message: message.to_string(),
+            });
+        }
+    }
+
+    /// Adds an error.
+    pub fn error(&mut self, message: impl Display) {
+        self.push(Severity::Error, message)
+    }
+
+    /// Adds a warning.
+    pub fn warning(&mut self, message: impl Display) {
+        self.push(Severity::Warning, message)
+    }
+
+    /// Adds the diagnostics of another report after those of this one,
+    /// counting the ones it dropped too.
+    pub fn append(&mut self, other: Report) {
+        let (errors, warnings) = (self.errors + other.errors, self.warnings + other.warnings);
+        for diagnostic in other.diagnostics {
+            self.push(diagnostic.severity, diagnostic.message);
+        }
+        self.errors = errors;
+        self.warnings = warnings;
+    }
+
+    /// Checks if nothing was found.
+    pub fn is_empty(&self) -> bool {
+        self.errors + self.warnings == 0
+    }
+
+    /// Number of diagnostics found but not kept.
+    pub fn dropped(&self) -> usize {
+        self.errors + self.warnings - self.diagnostics.len()
+    }
+}
+
+impl Chip {
+    /// Every problem of the current state: violations are errors,
+    /// unreachable pins of the input are warnings.
+    /// At most `max_diagnostics` are kept.
+    pub fn diagnose(&self) -> Report {
+        let mut report = Report::new(self.max_diagnostics.unwrap_or(MAX_DIAGNOSTICS));
+        for violation in self.sanity_check() {
+            report.warning(violation);
+        }
+        for violation in self.verify() {
+            match violation {
+                Violation::UnreachablePin { .. } => report.warning(violation),
+                _ => report.error(violation),
+            }
+        }
+        report
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Severity::Warning => write!(f, "Warning"),
+            Severity::Error => write!(f, "Error"),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+impl Display for Report {
+    /// The totals, followed by one diagnostic per line.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} errors, {} warnings", self.errors, self.warnings)?;
+        for diagnostic in self.diagnostics.iter() {
+            write!(f, "\n{}", diagnostic)?;
+        }
+        if self.dropped() > 0 {
+            write!(f, "\n... {} more not shown", self.dropped())?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for Report {}
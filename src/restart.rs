@@ -0,0 +1,75 @@
+use crate::{args::Args, chip::Chip, utilities::Rng};
+use anyhow::{anyhow, Result};
+use std::time::Instant;
+
+impl Chip {
+    /// Quality of the current state, smaller is better:
+    /// a legal state beats an illegal one, then shorter wirelength wins.
+    pub fn quality(&self) -> (bool, usize) {
+        let wirelength = self.nets.iter().map(|net| self.net_length(net)).sum();
+        (!self.is_legal(), wirelength)
+    }
+
+    /// Perturbs an order with the configured perturbation seed, a no-op without one.
+    /// Every item swaps with one at most `PERTURB_WINDOW` places later,
+    /// so the order is shaken up but stays roughly the same.
+    /// `salt` keeps the perturbations of different orders apart.
+    pub fn perturb(&self, order: &mut [usize], salt: u64) {
+        use crate::consts::PERTURB_WINDOW;
+
+        let seed = match self.perturbation {
+            Some(seed) => seed,
+            None => return,
+        };
+        let mut rng = Rng::new(seed ^ salt);
+        for i in 0..order.len() {
+            let window = PERTURB_WINDOW.min(order.len() - i);
+            order.swap(i, i + rng.below(window));
+        }
+    }
+
+    /// Optimizes from the current state once as configured,
+    /// then `restarts` more times from the same state with the net and move order
+    /// perturbed by seeds drawn from `seed`. Every round gets an even share of the time left.
+    /// The best state seen, see `quality`, is restored at the end.
+    /// Returns the round that produced it, 0 for the unperturbed one, and the rounds that failed.
+    pub fn run_restarts(
+        &mut self,
+        args: &Args,
+        restarts: usize,
+        seed: u64,
+    ) -> Result<(usize, Vec<String>)> {
+        let start = Instant::now();
+        let duration = Self::duration(args).saturating_sub(self.write_margin());
+        let initial = self.snapshot();
+        let mut rng = Rng::new(seed);
+
+        let mut best = None;
+        let mut failures = Vec::new();
+        for round in 0..=restarts {
+            let remaining = duration.saturating_sub(start.elapsed());
+            if Self::check_time(start, duration).is_err() {
+                break;
+            }
+            let share = remaining / (restarts + 1 - round) as u32;
+
+            self.restore(&initial)?;
+            self.perturbation = Some(rng.next_u64()).filter(|_| round > 0);
+            let result = self.optimize(args, Instant::now(), share);
+            self.perturbation = None;
+            if let Err(err) = result {
+                failures.push(format!("Restart {} failed: {}", round, err));
+                continue;
+            }
+
+            let quality = self.quality();
+            if best.as_ref().is_none_or(|(best, _, _)| quality < *best) {
+                best = Some((quality, round, self.snapshot()));
+            }
+        }
+
+        let (_, round, snapshot) = best.ok_or_else(|| anyhow!("Every restart failed"))?;
+        self.restore(&snapshot)?;
+        Ok((round, failures))
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{
+    chip::Chip,
+    components::{Direction, Point},
+    router::{turns, TieKey},
+};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+impl Chip {
+    /// A lower bound of the steps from `point` to `target`.
+    /// Besides the Manhattan distance, a via is needed to leave a layer
+    /// whose direction doesn't lead towards the target.
+    fn heuristic(&self, point: Point<usize>, target: Point<usize>) -> usize {
+        let Point(row, col, lay) = point;
+        let Point(trow, tcol, tlay) = target;
+        let (drow, dcol, dlay) = (row.abs_diff(trow), col.abs_diff(tcol), lay.abs_diff(tlay));
+
+        let leave = match lay
+            .checked_sub(1)
+            .and_then(|id| self.get_layer(id))
+            .map(|layer| layer.direction)
+        {
+            Some(Direction::Horizontal) => drow > 0,
+            Some(Direction::Vertical) => dcol > 0,
+            None => false,
+        };
+
+        drow + dcol + dlay.max(leave as usize)
+    }
+
+    /// A* search from `source` to `target`,
+    /// only visiting GGrids for which `allowed` returns true
+    /// and never running planar below `min_layer`.
+    /// Finds a path as short as `bfs` does while visiting fewer GGrids.
+    /// Returns the path including both ends.
+    pub fn astar<F>(
+        &self,
+        source: Point<usize>,
+        target: Point<usize>,
+        min_layer: usize,
+        allowed: F,
+    ) -> Option<Vec<Point<usize>>>
+    where
+        F: Fn(Point<usize>) -> bool,
+    {
+        self.cheapest_path(source, target, 1, min_layer, |_, _, point| {
+            if allowed(point) {
+                Some(1)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// A* search from `source` to `target` where a step from a GGrid, entered from `parent`,
+    /// to the next one costs `cost(parent, from, to)`, and `None` means it can't be taken.
+    /// The target can always be entered.
+    /// Every GGrid keeps only its cheapest path, so costs depending on `parent`,
+    /// like bends, are only minimized approximately.
+    /// `unit` must not exceed the cost of any step for the path to be the cheapest,
+    /// a `unit` of 0 makes it Dijkstra's algorithm.
+    /// Planar steps are never taken below `min_layer`.
+    /// Paths of the same cost are chosen by the chip's `TieBreak`.
+    /// Returns the path including both ends.
+    pub fn cheapest_path<F>(
+        &self,
+        source: Point<usize>,
+        target: Point<usize>,
+        unit: usize,
+        min_layer: usize,
+        cost: F,
+    ) -> Option<Vec<Point<usize>>>
+    where
+        F: Fn(Point<usize>, Point<usize>, Point<usize>) -> Option<usize>,
+    {
+        let key = |Point(row, col, lay): Point<usize>| (row, col, lay);
+
+        let mut parents = HashMap::new();
+        let mut turned = HashMap::new();
+        // cost of the best path to every GGrid, and its tie key
+        let mut costs = HashMap::new();
+        let mut heap = BinaryHeap::new();
+
+        parents.insert(source, source);
+        turned.insert(source, 0);
+        costs.insert(source, (0, TieKey::default()));
+        heap.push(Reverse((
+            unit * self.heuristic(source, target),
+            0,
+            TieKey::default(),
+            key(source),
+        )));
+
+        while let Some(Reverse((_, total, tie, (row, col, lay)))) = heap.pop() {
+            let point = Point(row, col, lay);
+            if costs.get(&point).is_some_and(|&best| (total, tie) > best) {
+                continue;
+            }
+
+            if point == target {
+                let mut path = vec![point];
+                let mut current = point;
+                while current != source {
+                    current = parents[&current];
+                    path.push(current);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let (parent, bends) = (parents[&point], turned[&point]);
+            for next in self.neighbors_above(point, min_layer) {
+                let step = match cost(parent, point, next) {
+                    Some(step) => step,
+                    None if next == target => unit,
+                    None => continue,
+                };
+                let next_total = total + step;
+                let next_tie = self.tie_break.step_key(parent, point, next, bends);
+                if costs
+                    .get(&next)
+                    .is_some_and(|&best| (next_total, next_tie) >= best)
+                {
+                    continue;
+                }
+                costs.insert(next, (next_total, next_tie));
+                parents.insert(next, point);
+                turned.insert(next, bends + turns(parent, point, next));
+                heap.push(Reverse((
+                    next_total + unit * self.heuristic(next, target),
+                    next_total,
+                    next_tie,
+                    key(next),
+                )));
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{
+    chip::Chip,
+    components::{Pair, Point, Route},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+};
+
+impl Chip {
+    /// Groups of nets whose pins form the same pattern up to a planar shift,
+    /// on the same layers and with the same min layer, in input order.
+    /// Only groups of at least two nets are returned.
+    pub fn buses(&self) -> Vec<Vec<usize>> {
+        let pin_points = self.pin_points();
+
+        let mut groups: HashMap<_, Vec<usize>> = HashMap::new();
+        let mut keys = Vec::new();
+        for net in self.nets.iter().filter(|net| net.pins.len() >= 2) {
+            let mut points: Vec<_> = net
+                .pins
+                .iter()
+                .map(|&pin| {
+                    let Point(row, col, lay) = pin_points[pin];
+                    (row, col, lay)
+                })
+                .collect();
+            points.sort_unstable();
+
+            let (row0, col0, _) = points[0];
+            let pattern: Vec<_> = points
+                .iter()
+                .map(|&(row, col, lay)| {
+                    (
+                        row as isize - row0 as isize,
+                        col as isize - col0 as isize,
+                        lay,
+                    )
+                })
+                .collect();
+            let key = (net.min_layer, pattern);
+
+            let group = groups.entry(key.clone()).or_default();
+            if group.is_empty() {
+                keys.push(key);
+            }
+            group.push(net.id);
+        }
+
+        keys.into_iter()
+            .filter_map(|key| groups.remove(&key))
+            .filter(|group| group.len() >= 2)
+            .collect()
+    }
+
+    /// For every net of a bus except the first one in `order`,
+    /// the first one and the shift from its pins to the net's pins.
+    pub fn bus_followers(&self, order: &[usize]) -> HashMap<usize, (usize, Pair<isize>)> {
+        let rank: HashMap<_, _> = order.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+        let corner = |id: usize| self.bounding_box(id).map(|(low, _)| low);
+
+        let mut followers = HashMap::new();
+        for bus in self.buses() {
+            let leader = match bus.iter().copied().min_by_key(|id| rank.get(id)) {
+                Some(leader) => leader,
+                None => continue,
+            };
+            let Pair(row0, col0) = match corner(leader) {
+                Some(corner) => corner,
+                None => continue,
+            };
+
+            for &id in bus.iter().filter(|&&id| id != leader) {
+                if let Some(Pair(row, col)) = corner(id) {
+                    let shift = Pair(row as isize - row0 as isize, col as isize - col0 as isize);
+                    followers.insert(id, (leader, shift));
+                }
+            }
+        }
+
+        followers
+    }
+
+    /// The routes of `leader` shifted by `shift`, on a parallel track for net `id`.
+    /// Returns `None` if a shifted GGrid leaves the chip or has no room left.
+    pub fn shifted_routes(
+        &self,
+        leader: usize,
+        id: usize,
+        shift: Pair<isize>,
+    ) -> Option<HashSet<Route<usize>>> {
+        let Pair(drow, dcol) = shift;
+        let move_point = |Point(row, col, lay): Point<usize>| {
+            let row = usize::try_from(row as isize + drow).ok()?;
+            let col = usize::try_from(col as isize + dcol).ok()?;
+            Some(Point(row, col, lay)).filter(|&point| self.in_bounds(point))
+        };
+
+        let pin_grids = self.pin_access_grids(id);
+        let routes = self
+            .nets
+            .get(leader)?
+            .routes
+            .iter()
+            .map(|route| {
+                Some(Route::new(
+                    move_point(route.source())?,
+                    move_point(route.target())?,
+                ))
+            })
+            .collect::<Option<HashSet<_>>>()?;
+
+        let fits = routes
+            .iter()
+            .flat_map(Route::points)
+            .all(|point| pin_grids.contains(&point.flatten()) || self.has_room(point));
+        if !fits {
+            return None;
+        }
+
+        Some(routes)
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{chip::Chip, components::Point, router::path_to_routes};
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+
+/// Unit steps of a net's routes, by GGrid.
+type Graph = BTreeMap<Point<usize>, BTreeSet<Point<usize>>>;
+
+impl Chip {
+    /// Removes the parts of a net's routes that connect nothing:
+    /// loops are broken by keeping a breadth-first tree grown from the pins,
+    /// then branches not leading to pins are cut back and pieces without pins dropped.
+    /// Pins stay connected as before, and the demand of the GGrids left is reclaimed.
+    /// Nets with diagonal segments are left for the verifier to report.
+    /// Returns the number of GGrids freed.
+    pub fn clean_up_routes(&mut self, id: usize) -> Result<usize> {
+        let pin_points = self.pin_points();
+        let net = match self.nets.get(id) {
+            Some(net) => net,
+            None => return Ok(0),
+        };
+        if net.routes.iter().any(|route| !route.is_straight()) {
+            return Ok(0);
+        }
+
+        let pins: Vec<_> = net.pins.iter().map(|&pin| pin_points[pin]).collect();
+        let mut graph = Graph::new();
+        for route in net.routes.iter() {
+            for pair in route.points().windows(2) {
+                graph.entry(pair[0]).or_default().insert(pair[1]);
+                graph.entry(pair[1]).or_default().insert(pair[0]);
+            }
+        }
+
+        let mut tree = Self::spanning_forest(&graph, &pins);
+        let pins: HashSet<_> = pins.into_iter().collect();
+        Self::cut_stubs(&mut tree, &pins);
+
+        let steps = |graph: &Graph| graph.values().map(BTreeSet::len).sum::<usize>();
+        if tree.len() == graph.len() && steps(&tree) == steps(&graph) {
+            return Ok(0);
+        }
+
+        let before = self.net_length(net);
+        let mut routes = HashSet::new();
+        for (&point, nexts) in tree.iter() {
+            for &next in nexts.iter().filter(|&&next| point < next) {
+                routes.extend(path_to_routes(&[point, next]));
+            }
+        }
+
+        self.rip_up(id)?;
+        self.commit_routes(id, routes)?;
+        Ok(before.saturating_sub(self.net_length(&self.nets[id])))
+    }
+
+    /// Runs `clean_up_routes` on every net.
+    /// Returns the number of GGrids freed.
+    pub fn clean_up_all_routes(&mut self) -> Result<usize> {
+        let mut freed = 0;
+        for id in 0..self.nets.len() {
+            freed += self.clean_up_routes(id)?;
+        }
+        Ok(freed)
+    }
+
+    /// A breadth-first spanning tree of every piece of `graph`, grown from the pins first.
+    fn spanning_forest(graph: &Graph, pins: &[Point<usize>]) -> Graph {
+        let mut tree = Graph::new();
+        let mut seen = HashSet::new();
+        let roots = pins.iter().chain(graph.keys());
+        for &root in roots {
+            if !graph.contains_key(&root) || !seen.insert(root) {
+                continue;
+            }
+
+            let mut queue = VecDeque::from(vec![root]);
+            while let Some(point) = queue.pop_front() {
+                tree.entry(point).or_default();
+                for &next in graph[&point].iter() {
+                    if seen.insert(next) {
+                        tree.entry(point).or_default().insert(next);
+                        tree.entry(next).or_default().insert(point);
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        tree
+    }
+
+    /// Removes GGrids other than pins with at most one neighbor in `tree` until none is left.
+    fn cut_stubs(tree: &mut Graph, pins: &HashSet<Point<usize>>) {
+        let mut leaves: Vec<_> = tree
+            .iter()
+            .filter(|(point, nexts)| nexts.len() <= 1 && !pins.contains(point))
+            .map(|(&point, _)| point)
+            .collect();
+
+        while let Some(leaf) = leaves.pop() {
+            let nexts = match tree.remove(&leaf) {
+                Some(nexts) => nexts,
+                None => continue,
+            };
+            for next in nexts {
+                if let Some(others) = tree.get_mut(&next) {
+                    others.remove(&leaf);
+                    if others.len() <= 1 && !pins.contains(&next) {
+                        leaves.push(next);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{
+    chip::Chip,
+    coarse::CoarseChip,
+    components::{Pair, Point},
+};
+use std::collections::HashSet;
+
+/// A set of super-GGrids a net's search is restricted to.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Corridor {
+    /// number of GGrids along each side of a super-GGrid
+    pub factor: usize,
+    /// dimensions in super-GGrids
+    pub dim: Pair<usize>,
+    /// allowed super-GGrids
+    pub tiles: HashSet<Pair<usize>>,
+}
+
+impl Corridor {
+    /// Creates a corridor from tiles planned on `coarse`.
+    pub fn new(coarse: &CoarseChip, tiles: HashSet<Pair<usize>>) -> Self {
+        Self {
+            factor: coarse.factor,
+            dim: coarse.dim,
+            tiles,
+        }
+    }
+
+    /// Creates a corridor of single GGrids covering the rectangle from `low` to `high`,
+    /// clipped to a chip of dimensions `dim`.
+    pub fn rectangle(dim: Pair<usize>, low: Pair<usize>, high: Pair<usize>) -> Self {
+        let Pair(rows, cols) = dim;
+        let (Pair(r0, c0), Pair(r1, c1)) = (low, high);
+        let tiles = (r0.max(1)..=r1.min(rows))
+            .flat_map(|row| (c0.max(1)..=c1.min(cols)).map(move |col| Pair(row, col)))
+            .collect();
+
+        Self {
+            factor: 1,
+            dim,
+            tiles,
+        }
+    }
+
+    /// Checks if a GGrid lies inside the corridor.
+    pub fn contains(&self, point: Point<usize>) -> bool {
+        let Point(row, col, _) = point;
+        // The GGrids the super-GGrids cover, which may reach past the chip.
+        let covered = Pair(self.dim.x() * self.factor, self.dim.y() * self.factor);
+        if !Chip::in_grid(covered, point.flatten()) {
+            return false;
+        }
+        let tile = Pair((row - 1) / self.factor + 1, (col - 1) / self.factor + 1);
+        self.tiles.contains(&tile)
+    }
+
+    /// Checks if the corridor already covers the whole chip.
+    pub fn is_full(&self) -> bool {
+        self.tiles.len() >= self.dim.size()
+    }
+
+    /// Widens the corridor by one super-GGrid in every planar direction.
+    pub fn widen(&mut self) {
+        let Pair(rows, cols) = self.dim;
+        let widened: Vec<_> = self
+            .tiles
+            .iter()
+            .flat_map(|&Pair(row, col)| {
+                let up = (row < rows).then(|| Pair(row + 1, col));
+                let down = (row > 1).then(|| Pair(row - 1, col));
+                let right = (col < cols).then(|| Pair(row, col + 1));
+                let left = (col > 1).then(|| Pair(row, col - 1));
+                [up, down, right, left]
+            })
+            .flatten()
+            .collect();
+        self.tiles.extend(widened);
+    }
+}
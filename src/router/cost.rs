@@ -0,0 +1,162 @@
+use crate::{
+    chip::Chip,
+    components::Point,
+    router::{turns, Congestion},
+};
+use std::fmt::Debug;
+
+/// What the router knows about a step when pricing it.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Step {
+    /// whether the step changes layers
+    pub via: bool,
+    /// whether the step changes the direction of the step before it
+    pub bend: bool,
+    /// capacity of the GGrid entered
+    pub capacity: usize,
+    /// demand of the GGrid entered, before the step
+    pub demand: usize,
+    /// accumulated congestion cost of the GGrid entered
+    pub history: usize,
+    /// weight of the overflow the step would cause, 0 outside negotiation
+    pub present: usize,
+}
+
+/// CostModel prices every step of the router's cost-driven searches,
+/// so cost functions can be tried without touching the searches.
+/// Costs are in thousandths like those of `Congestion`.
+/// The default methods price steps in the style of PathFinder.
+pub trait CostModel: Debug + Send + Sync {
+    /// Cost of a planar step.
+    fn wire_cost(&self) -> usize {
+        Congestion::UNIT
+    }
+
+    /// Cost of a step to an adjacent layer.
+    fn via_cost(&self) -> usize {
+        Congestion::UNIT
+    }
+
+    /// Cost added to a step that changes direction, nothing by default.
+    fn bend_cost(&self) -> usize {
+        0
+    }
+
+    /// Penalty in thousandths of the step's cost for the overflow entering a GGrid causes.
+    fn overflow_penalty(&self, overflow: usize, present: usize) -> usize {
+        present.saturating_mul(overflow)
+    }
+
+    /// How much the congestion history counts, in thousandths.
+    fn history_weight(&self) -> usize {
+        Congestion::UNIT
+    }
+
+    /// Cost of a step: its wire or via cost plus its bend cost and the weighted history,
+    /// raised by the overflow penalty.
+    fn step_cost(&self, step: &Step) -> usize {
+        let base = if step.via {
+            self.via_cost()
+        } else {
+            self.wire_cost()
+        };
+        let base = if step.bend {
+            base + self.bend_cost()
+        } else {
+            base
+        };
+        let history = step.history.saturating_mul(self.history_weight()) / Congestion::UNIT;
+        let overflow = (step.demand + 1).saturating_sub(step.capacity);
+        let penalty = self.overflow_penalty(overflow, step.present);
+        (base + history).saturating_mul(Congestion::UNIT.saturating_add(penalty)) / Congestion::UNIT
+    }
+
+    /// A lower bound of the cost of any step, which scales the A* heuristic.
+    /// `None` if there is no such bound, the search is then plain Dijkstra's algorithm.
+    fn min_step_cost(&self) -> Option<usize> {
+        Some(self.wire_cost().min(self.via_cost()))
+    }
+}
+
+/// The default costs, every step costs the same before congestion.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PathFinder;
+
+impl CostModel for PathFinder {}
+
+/// PathFinder costs with custom weights, in thousandths.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct WeightedCost {
+    /// cost of a planar step
+    pub wire: usize,
+    /// cost of a via
+    pub via: usize,
+    /// cost added to a change of direction
+    pub bend: usize,
+    /// weight of the congestion history
+    pub history: usize,
+}
+
+impl CostModel for WeightedCost {
+    fn wire_cost(&self) -> usize {
+        self.wire
+    }
+
+    fn via_cost(&self) -> usize {
+        self.via
+    }
+
+    fn bend_cost(&self) -> usize {
+        self.bend
+    }
+
+    fn history_weight(&self) -> usize {
+        self.history
+    }
+}
+
+impl Default for WeightedCost {
+    fn default() -> Self {
+        Self {
+            wire: Congestion::UNIT,
+            via: Congestion::UNIT,
+            bend: 0,
+            history: Congestion::UNIT,
+        }
+    }
+}
+
+impl Default for Box<dyn CostModel> {
+    fn default() -> Self {
+        Box::new(PathFinder)
+    }
+}
+
+impl Chip {
+    /// Prices a step from `from`, entered from `parent`, to the adjacent `to`
+    /// with the chip's cost model, `present` is the weight of overflow during negotiation.
+    /// The first step of a path, where `parent == from`, never bends.
+    /// Returns `None` if `to` is off the chip.
+    pub fn step_cost(
+        &self,
+        parent: Point<usize>,
+        from: Point<usize>,
+        to: Point<usize>,
+        present: usize,
+    ) -> Option<usize> {
+        if !self.in_bounds(to) {
+            return None;
+        }
+        let Point(row, col, lay) = to;
+        let layer = self.get_layer(lay - 1)?;
+        let step = Step {
+            via: from.lay() != lay,
+            bend: turns(parent, from, to) > 0,
+            capacity: *layer.get_capacity(row - 1, col - 1)?,
+            demand: *layer.get_demand(row - 1, col - 1)?,
+            history: self.history_cost(to),
+            present,
+        };
+        Some(self.cost_model.step_cost(&step))
+    }
+}
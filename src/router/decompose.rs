@@ -0,0 +1,83 @@
+use crate::{
+    chip::Chip,
+    components::{Pair, Point},
+    utilities::UnionFind,
+};
+use std::collections::VecDeque;
+
+/// A 2-pin connection of a net for the point-to-point router.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TwoPinTask {
+    /// pin index within the net, already connected unless this is the first task
+    pub source: usize,
+    /// pin index within the net
+    pub target: usize,
+    /// GGrid of the source pin
+    pub from: Point<usize>,
+    /// GGrid of the target pin
+    pub to: Point<usize>,
+}
+
+/// Edges of a minimum spanning tree (Manhattan distance) over `points`, using Kruskal's algorithm.
+/// Edges of the same length are taken in index order, so the tree is deterministic.
+/// Edges are ordered breadth-first from the first point,
+/// and the first end of every edge is already in the tree.
+pub fn decompose(points: &[Pair<usize>]) -> Vec<(usize, usize)> {
+    let mut candidates: Vec<_> = (0..points.len())
+        .flat_map(|a| (a + 1..points.len()).map(move |b| (a, b)))
+        .map(|(a, b)| (points[a].distance(&points[b]), a, b))
+        .collect();
+    candidates.sort_unstable();
+
+    let mut union_find = UnionFind::new(points.len());
+    let mut adjacent = vec![Vec::new(); points.len()];
+    for (_, a, b) in candidates {
+        if union_find.union(a, b) {
+            adjacent[a].push(b);
+            adjacent[b].push(a);
+        }
+    }
+
+    let mut edges = Vec::with_capacity(points.len().saturating_sub(1));
+    let mut seen = vec![false; points.len()];
+    let mut queue = VecDeque::new();
+    if !points.is_empty() {
+        seen[0] = true;
+        queue.push_back(0);
+    }
+    while let Some(current) = queue.pop_front() {
+        for &next in adjacent[current].iter() {
+            if !seen[next] {
+                seen[next] = true;
+                edges.push((current, next));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    edges
+}
+
+impl Chip {
+    /// Splits a net into 2-pin tasks along a minimum spanning tree of its pins' GGrids,
+    /// see `decompose`. Routing the tasks in order connects every pin.
+    pub fn two_pin_tasks(&self, id: usize) -> Vec<TwoPinTask> {
+        let net = match self.nets.get(id) {
+            Some(net) => net,
+            None => return Vec::new(),
+        };
+        let pin_points = self.pin_points();
+        let points: Vec<_> = net.pins.iter().map(|&pin| pin_points[pin]).collect();
+        let flat: Vec<_> = points.iter().map(Point::flatten).collect();
+
+        decompose(&flat)
+            .into_iter()
+            .map(|(source, target)| TwoPinTask {
+                source,
+                target,
+                from: points[source],
+                to: points[target],
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{chip::Chip, components::Point, metrics::Counters, router::Corridor};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+impl Chip {
+    /// Checks if a route can't leave `point` without overflow:
+    /// no GGrid one step away, never running planar below `min_layer`, has room.
+    pub fn is_blocked(&self, point: Point<usize>, min_layer: usize) -> bool {
+        self.neighbors_above(point, min_layer)
+            .into_iter()
+            .all(|next| !self.has_room(next))
+    }
+
+    /// Pins of a net the router can't leave without overflow, see `is_blocked`.
+    /// Their GGrids are usually taken by blockages or conflicting cells.
+    pub fn blocked_pins(&self, id: usize) -> Vec<usize> {
+        let net = match self.nets.get(id) {
+            Some(net) => net,
+            None => return Vec::new(),
+        };
+        let pin_points = self.pin_points();
+        net.pins
+            .iter()
+            .copied()
+            .filter(|&pin| self.is_blocked(pin_points[pin], net.min_layer))
+            .collect()
+    }
+
+    /// The way out of a blocked GGrid through the fewest full GGrids,
+    /// then the fewest steps: a detour to a neighboring GGrid or up to a higher layer,
+    /// ending at the nearest GGrid with room that isn't blocked itself.
+    /// The search stays within `ESCAPE_RADIUS` GGrids and inside `corridor` if given.
+    /// Returns the path including both ends, `None` if there is no way out.
+    pub fn escape_path(
+        &self,
+        point: Point<usize>,
+        min_layer: usize,
+        corridor: Option<&Corridor>,
+    ) -> Option<Vec<Point<usize>>> {
+        use crate::consts::{ESCAPE_OVERFLOW_COST, ESCAPE_RADIUS};
+
+        let key = |Point(row, col, lay): Point<usize>| (row, col, lay);
+        let within = |next: Point<usize>| {
+            next.flatten().distance(&point.flatten()) <= ESCAPE_RADIUS
+                && corridor.is_none_or(|corr| corr.contains(next))
+        };
+
+        let mut parents = HashMap::new();
+        let mut costs = HashMap::new();
+        let mut heap = BinaryHeap::new();
+
+        parents.insert(point, point);
+        costs.insert(point, 0);
+        heap.push(Reverse((0, key(point))));
+
+        while let Some(Reverse((total, (row, col, lay)))) = heap.pop() {
+            let current = Point(row, col, lay);
+            if costs.get(&current).is_some_and(|&best| total > best) {
+                continue;
+            }
+
+            if current != point && self.has_room(current) && !self.is_blocked(current, min_layer) {
+                let mut path = vec![current];
+                let mut back = current;
+                while back != point {
+                    back = parents[&back];
+                    path.push(back);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for next in self.neighbors_above(current, min_layer) {
+                if !within(next) {
+                    continue;
+                }
+                let step = if self.has_room(next) {
+                    1
+                } else {
+                    ESCAPE_OVERFLOW_COST
+                };
+                let next_total = total + step;
+                if costs.get(&next).is_some_and(|&best| next_total >= best) {
+                    continue;
+                }
+                costs.insert(next, next_total);
+                parents.insert(next, current);
+                heap.push(Reverse((next_total, key(next))));
+            }
+        }
+
+        None
+    }
+
+    /// Escape paths of the blocked pins of a net, see `escape_path`.
+    /// Pins without a way out are left to the router.
+    pub fn escape_paths(&self, id: usize, corridor: Option<&Corridor>) -> Vec<Vec<Point<usize>>> {
+        let min_layer = match self.nets.get(id) {
+            Some(net) => net.min_layer,
+            None => return Vec::new(),
+        };
+        let pin_points = self.pin_points();
+        self.blocked_pins(id)
+            .into_iter()
+            .filter_map(|pin| {
+                let path = self.escape_path(pin_points[pin], min_layer, corridor)?;
+                Counters::bump(&self.counters.pin_escapes);
+                Some(path)
+            })
+            .collect()
+    }
+}
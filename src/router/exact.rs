@@ -0,0 +1,177 @@
+use crate::{
+    chip::Chip,
+    components::{Pair, Point, Route},
+    metrics::Counters,
+    router::path_to_routes,
+};
+use anyhow::Result;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+impl Chip {
+    /// Steps from `source` to every GGrid of `window` reachable through `allowed` GGrids,
+    /// with the GGrid every one was reached from.
+    fn window_distances<F>(
+        &self,
+        source: Point<usize>,
+        window: (Pair<usize>, Pair<usize>),
+        min_layer: usize,
+        allowed: F,
+    ) -> HashMap<Point<usize>, (usize, Point<usize>)>
+    where
+        F: Fn(Point<usize>) -> bool,
+    {
+        let (Pair(r0, c0), Pair(r1, c1)) = window;
+        let mut distances = HashMap::new();
+        let mut queue = VecDeque::new();
+        distances.insert(source, (0, source));
+        queue.push_back(source);
+
+        while let Some(point) = queue.pop_front() {
+            let steps = distances[&point].0 + 1;
+            for next in self.neighbors_above(point, min_layer) {
+                let Point(row, col, _) = next;
+                if !(r0..=r1).contains(&row) || !(c0..=c1).contains(&col) {
+                    continue;
+                }
+                if distances.contains_key(&next) || !allowed(next) {
+                    continue;
+                }
+                distances.insert(next, (steps, point));
+                queue.push_back(next);
+            }
+        }
+
+        distances
+    }
+
+    /// Paths of a tree connecting the 2 or 3 `terminals` through the fewest GGrids
+    /// inside `window`. Such a tree is the shortest paths from some meeting GGrid to every terminal,
+    /// so trying every meeting GGrid finds the best tree exactly.
+    fn exact_tree<F>(
+        &self,
+        terminals: &[Point<usize>],
+        window: (Pair<usize>, Pair<usize>),
+        min_layer: usize,
+        allowed: F,
+    ) -> Option<Vec<Vec<Point<usize>>>>
+    where
+        F: Fn(Point<usize>) -> bool,
+    {
+        let searches: Vec<_> = terminals
+            .iter()
+            .map(|&terminal| self.window_distances(terminal, window, min_layer, &allowed))
+            .collect();
+
+        let (_, meeting) = searches
+            .first()?
+            .keys()
+            .filter_map(|&point| {
+                searches
+                    .iter()
+                    .map(|search| search.get(&point).map(|&(steps, _)| steps))
+                    .sum::<Option<usize>>()
+                    .map(|steps| (steps, point))
+            })
+            .min_by_key(|&(steps, Point(row, col, lay))| (steps, row, col, lay))?;
+
+        let paths = searches
+            .iter()
+            .map(|search| {
+                let mut path = vec![meeting];
+                let mut current = meeting;
+                while let Some(&(steps, parent)) = search.get(&current) {
+                    if steps == 0 {
+                        break;
+                    }
+                    current = parent;
+                    path.push(current);
+                }
+                path
+            })
+            .collect();
+        Some(paths)
+    }
+
+    /// Routes a tiny net exactly: a net of 2 or 3 pin GGrids whose bounding box,
+    /// widened by `EXACT_MARGIN`, spans at most `EXACT_MAX_AREA` positions
+    /// is given the tree with the fewest GGrids inside that box, see `exact_tree`.
+    /// The net's own demand should be ripped up first.
+    /// Returns `None` for other nets or if the pins cannot be connected inside the box.
+    pub fn route_net_exact(&self, id: usize) -> Option<HashSet<Route<usize>>> {
+        use crate::consts::{EXACT_MARGIN, EXACT_MAX_AREA};
+
+        let net = self.nets.get(id)?;
+        let pin_points = self.pin_points();
+        let mut terminals: Vec<_> = net.pins.iter().map(|&pin| pin_points[pin]).collect();
+        terminals.sort_unstable_by_key(|&Point(row, col, lay)| (row, col, lay));
+        terminals.dedup();
+        if !(2..=3).contains(&terminals.len()) {
+            return None;
+        }
+
+        let (Pair(r0, c0), Pair(r1, c1)) = self.bounding_box(id)?;
+        let Pair(rows, cols) = self.dim;
+        let low = Pair(
+            r0.saturating_sub(EXACT_MARGIN).max(1),
+            c0.saturating_sub(EXACT_MARGIN).max(1),
+        );
+        let high = Pair((r1 + EXACT_MARGIN).min(rows), (c1 + EXACT_MARGIN).min(cols));
+        if (high.x() + 1 - low.x()) * (high.y() + 1 - low.y()) > EXACT_MAX_AREA {
+            return None;
+        }
+
+        let pin_grids = self.pin_access_grids(id);
+        let allowed = |point: Point<usize>| {
+            terminals.contains(&point)
+                || pin_grids.contains(&point.flatten())
+                || self.has_room(point)
+        };
+        let paths = self.exact_tree(&terminals, (low, high), net.min_layer, allowed)?;
+        Some(paths.iter().flat_map(|path| path_to_routes(path)).collect())
+    }
+
+    /// Gives tiny nets their exact routes while time remains, see `route_net_exact`.
+    /// A new route is only kept if it is connected and shorter, or the old one is disconnected.
+    /// Returns the number of GGrids saved.
+    pub fn exact_route_nets(&mut self, start: Instant, duration: Duration) -> Result<usize> {
+        let mut saved = 0;
+        for id in 0..self.nets.len() {
+            if Self::check_time(start, duration).is_err() {
+                break;
+            }
+
+            let pin_points = self.pin_points();
+            let connected = self.is_connected(&self.nets[id], &pin_points)
+                && Self::below_min_layer(&self.nets[id]).is_empty()
+                && self.wrong_direction(&self.nets[id]).is_empty();
+            let length = self.net_length(&self.nets[id]);
+
+            let old = self.rip_up(id)?;
+            let routes = match self.route_net_exact(id) {
+                Some(routes) => routes,
+                None => {
+                    self.commit_routes(id, old)?;
+                    continue;
+                }
+            };
+
+            self.commit_routes(id, routes)?;
+            let new_length = self.net_length(&self.nets[id]);
+            let kept = self.is_connected(&self.nets[id], &pin_points)
+                && Self::below_min_layer(&self.nets[id]).is_empty()
+                && (!connected || new_length < length);
+            if kept {
+                saved += length.saturating_sub(new_length);
+                Counters::bump(&self.counters.nets_rerouted);
+            } else {
+                self.rip_up(id)?;
+                self.commit_routes(id, old)?;
+            }
+        }
+
+        Ok(saved)
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{
+    components::Pair,
+    router::{spanning_tree, Topology},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+/// Points in ranks instead of rows and columns.
+type Pattern = Vec<Pair<usize>>;
+
+/// A lookup table of Steiner topologies for small nets, in the spirit of FLUTE.
+/// Which trees can be optimal only depends on the relative order of the pins,
+/// not on the actual distances between them.
+/// So pins are reduced to their ranks among all rows and columns,
+/// and every rank pattern keeps a few candidate sets of Steiner points (in ranks).
+/// A net then only evaluates the candidates of its pattern with the actual distances.
+/// Patterns are filled in the first time they are seen.
+#[derive(Debug, Default)]
+pub struct SteinerTable {
+    /// candidate Steiner points of every rank pattern
+    entries: Mutex<HashMap<Pattern, Vec<Pattern>>>,
+}
+
+#[cfg(feature = "mem-profile")]
+impl crate::memory::HeapSize for SteinerTable {
+    fn heap_size(&self) -> usize {
+        self.entries
+            .lock()
+            .map(|entries| entries.heap_size())
+            .unwrap_or(0)
+    }
+}
+
+impl SteinerTable {
+    /// Number of rank patterns in the table.
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .map(|entries| entries.len())
+            .unwrap_or(0)
+    }
+
+    /// Checks if the table has no patterns yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Builds the topology of `pins` from the candidates of its rank pattern.
+    pub fn topology(&self, pins: &[Pair<usize>]) -> Topology {
+        let mut rows: Vec<_> = pins.iter().map(Pair::x).collect();
+        let mut cols: Vec<_> = pins.iter().map(Pair::y).collect();
+        rows.sort_unstable();
+        rows.dedup();
+        cols.sort_unstable();
+        cols.dedup();
+
+        let rank = |Pair(row, col): Pair<usize>| {
+            Pair(
+                rows.binary_search(&row).unwrap_or_default(),
+                cols.binary_search(&col).unwrap_or_default(),
+            )
+        };
+        let unrank = |Pair(row, col): Pair<usize>| Pair(rows[row], cols[col]);
+
+        let mut pattern: Vec<_> = pins.iter().map(|&pin| rank(pin)).collect();
+        pattern.sort_unstable_by_key(|&Pair(row, col)| (row, col));
+        pattern.dedup();
+
+        let candidates = match self.entries.lock() {
+            Ok(mut entries) => entries
+                .entry(pattern.clone())
+                .or_insert_with(|| {
+                    // Unit distances and the first net's distances give the candidates.
+                    let mut candidates: HashSet<Pattern> = HashSet::new();
+                    candidates.insert(Vec::new());
+                    for (realization, ranked) in [(pattern.clone(), true), (pins.to_vec(), false)] {
+                        let topology = Topology::new(&realization);
+                        let mut steiner: Vec<_> = topology.points[topology.num_pins..]
+                            .iter()
+                            .map(|&point| if ranked { point } else { rank(point) })
+                            .collect();
+                        steiner.sort_unstable_by_key(|&Pair(row, col)| (row, col));
+                        candidates.insert(steiner);
+                    }
+                    candidates.into_iter().collect()
+                })
+                .clone(),
+            Err(_) => return Topology::new(pins),
+        };
+
+        let best = candidates
+            .into_iter()
+            .map(|steiner| {
+                let mut points = pins.to_vec();
+                points.extend(steiner.into_iter().map(unrank));
+                let edges = spanning_tree(&points);
+                Topology {
+                    points,
+                    num_pins: pins.len(),
+                    edges,
+                }
+            })
+            .min_by_key(Topology::length);
+
+        best.unwrap_or_else(|| Topology::new(pins))
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{
+    chip::Chip,
+    components::{Direction, Point},
+    router::turns,
+};
+use std::collections::{HashMap, VecDeque};
+
+impl Chip {
+    /// Neighboring GGrids reachable from `point` in one step.
+    /// Planar steps follow the layer's routing direction, vias go up or down one layer.
+    pub fn neighbors(&self, point: Point<usize>) -> Vec<Point<usize>> {
+        self.neighbors_above(point, 0)
+    }
+
+    /// Like `neighbors`, but planar steps are only taken on layer ids of at least `min_layer`,
+    /// below that a net may only stack vias.
+    pub fn neighbors_above(&self, point: Point<usize>, min_layer: usize) -> Vec<Point<usize>> {
+        if !self.in_bounds(point) {
+            return Vec::new();
+        }
+        let Point(row, col, lay) = point;
+        let mut neighbors = Vec::with_capacity(4);
+
+        let planar = self
+            .get_layer(lay - 1)
+            .filter(|layer| layer.id >= min_layer)
+            .map(|layer| layer.direction);
+        match planar {
+            Some(Direction::Horizontal) => {
+                neighbors.push(Point(row, col.wrapping_sub(1), lay));
+                neighbors.push(Point(row, col + 1, lay));
+            }
+            Some(Direction::Vertical) => {
+                neighbors.push(Point(row.wrapping_sub(1), col, lay));
+                neighbors.push(Point(row + 1, col, lay));
+            }
+            None => {}
+        }
+        neighbors.push(Point(row, col, lay.wrapping_sub(1)));
+        neighbors.push(Point(row, col, lay + 1));
+
+        neighbors.retain(|&point| self.in_bounds(point));
+        neighbors
+    }
+
+    /// Checks if a GGrid can take one more unit of demand,
+    /// leaving the supply reserved for unrouted nets alone, see `Reservation`.
+    /// `point` is indexed the same way as in the input file.
+    pub fn has_room(&self, point: Point<usize>) -> bool {
+        if !self.in_bounds(point) {
+            return false;
+        }
+        let Point(row, col, lay) = point;
+        self.get_layer(lay - 1).is_some_and(|layer| {
+            match (
+                layer.get_capacity(row - 1, col - 1),
+                layer.get_demand(row - 1, col - 1),
+            ) {
+                (Some(&capacity), Some(&demand)) if demand < capacity => {
+                    let remaining = capacity - demand;
+                    remaining > self.reserved(point.flatten(), remaining)
+                }
+                _ => false,
+            }
+        })
+    }
+
+    /// Breadth-first search from `source` to `target`,
+    /// only visiting GGrids for which `allowed` returns true
+    /// and never running planar below `min_layer`.
+    /// Neighbors are visited in the order of the chip's `TieBreak`.
+    /// Returns the path including both ends.
+    pub fn bfs<F>(
+        &self,
+        source: Point<usize>,
+        target: Point<usize>,
+        min_layer: usize,
+        allowed: F,
+    ) -> Option<Vec<Point<usize>>>
+    where
+        F: Fn(Point<usize>) -> bool,
+    {
+        let mut parents = HashMap::new();
+        let mut turned = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        parents.insert(source, source);
+        turned.insert(source, 0);
+        queue.push_back(source);
+
+        while let Some(point) = queue.pop_front() {
+            if point == target {
+                let mut path = vec![point];
+                let mut current = point;
+                while current != source {
+                    current = parents[&current];
+                    path.push(current);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let (parent, bends) = (parents[&point], turned[&point]);
+            let mut neighbors = self.neighbors_above(point, min_layer);
+            if !self.tie_break.is_empty() {
+                neighbors.sort_by_key(|&next| self.tie_break.step_key(parent, point, next, bends));
+            }
+
+            for next in neighbors {
+                if parents.contains_key(&next) || !(next == target || allowed(next)) {
+                    continue;
+                }
+                parents.insert(next, point);
+                turned.insert(next, bends + turns(parent, point, next));
+                queue.push_back(next);
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,483 @@
+mod astar;
+mod bus;
+mod cleanup;
+mod corridor;
+mod cost;
+mod decompose;
+mod escape;
+#[cfg(feature = "exact")]
+mod exact;
+mod flute;
+mod maze;
+mod negotiate;
+mod order;
+mod parallel;
+mod pattern;
+mod relocate;
+mod soft;
+mod stack;
+mod steiner;
+mod tie;
+mod tree;
+mod two_phase;
+mod vias;
+
+pub use corridor::Corridor;
+pub use cost::{CostModel, PathFinder, Step, WeightedCost};
+pub use decompose::{decompose, TwoPinTask};
+pub use flute::SteinerTable;
+pub use negotiate::Congestion;
+pub use order::NetOrder;
+pub use parallel::TileStats;
+pub use steiner::Topology;
+pub use tie::{TieBreak, TieKey, TieRule, TIE_RULES};
+
+pub(crate) use pattern::walk;
+pub(crate) use steiner::spanning_tree;
+pub(crate) use tie::{axis, turns};
+
+use crate::{
+    chip::Chip,
+    components::{Pair, Point, Route},
+    metrics::Counters,
+};
+use anyhow::{anyhow, Error, Result};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// The search used to connect two points.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Search {
+    /// Breadth-first search, a Lee wavefront.
+    #[default]
+    Bfs,
+    /// A* search guided by the Manhattan distance and the vias needed.
+    AStar,
+    /// The cheapest path under the chip's `CostModel`.
+    Cost,
+}
+
+/// Converts a path of adjacent GGrids into route segments.
+/// Consecutive steps along the same axis are merged into one segment.
+pub fn path_to_routes(path: &[Point<usize>]) -> Vec<Route<usize>> {
+    let mut routes = Vec::new();
+    let mut start = match path.first() {
+        Some(&start) => start,
+        None => return routes,
+    };
+
+    for window in path.windows(3) {
+        if let [a, b, c] = *window {
+            if axis(a, b) != axis(b, c) {
+                routes.push(Route::new(start, b));
+                start = b;
+            }
+        }
+    }
+
+    if let Some(&end) = path.last() {
+        if end != start {
+            routes.push(Route::new(start, end));
+        }
+    }
+
+    routes
+}
+
+impl FromStr for Search {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "bfs" => Ok(Search::Bfs),
+            "astar" => Ok(Search::AStar),
+            "cost" => Ok(Search::Cost),
+            _ => Err(anyhow!("Unknown search: {}", s)),
+        }
+    }
+}
+
+impl Chip {
+    /// Searches a path from `source` to `target` with the configured search,
+    /// never running planar below `min_layer`.
+    /// Once congestion history is recorded, the cheapest path around it is searched instead.
+    pub fn search<F>(
+        &self,
+        source: Point<usize>,
+        target: Point<usize>,
+        min_layer: usize,
+        allowed: F,
+    ) -> Option<Vec<Point<usize>>>
+    where
+        F: Fn(Point<usize>) -> bool,
+    {
+        if self.search == Search::Cost || !self.congestion.history.is_empty() {
+            let unit = self.cost_model.min_step_cost().unwrap_or(0);
+            return self.cheapest_path(source, target, unit, min_layer, |parent, from, to| {
+                if allowed(to) {
+                    self.step_cost(parent, from, to, 0)
+                } else {
+                    None
+                }
+            });
+        }
+
+        match self.search {
+            Search::Bfs => self.bfs(source, target, min_layer, allowed),
+            Search::AStar | Search::Cost => self.astar(source, target, min_layer, allowed),
+        }
+    }
+
+    /// Routes a net along the edges of its Steiner topology.
+    /// Every edge tries L- and Z-shaped patterns before falling back to the maze search.
+    /// GGrids without remaining capacity are avoided,
+    /// so the net's own demand should be ripped up first.
+    /// Blocked pins are first given an escape path, see `escape_path`.
+    /// When `corridor` is given, the search never leaves it.
+    /// Returns `None` if some pins cannot be connected.
+    pub fn route_net(
+        &self,
+        id: usize,
+        corridor: Option<&Corridor>,
+    ) -> Option<HashSet<Route<usize>>> {
+        let pin_grids = self.pin_access_grids(id);
+        let min_layer = self.nets.get(id)?.min_layer;
+        let escapes = self.escape_paths(id, corridor);
+        self.route_topology_with(id, escapes, |source, target, used| {
+            let allowed = |point: Point<usize>| {
+                corridor.is_none_or(|corr| corr.contains(point))
+                    && (used.contains(&point)
+                        || pin_grids.contains(&point.flatten())
+                        || self.has_room(point))
+            };
+            self.pattern_path(source, target, min_layer, allowed)
+                .or_else(|| self.search(source, target, min_layer, allowed))
+        })
+    }
+
+    /// GGrids of a net's pins that routes may pass regardless of capacity,
+    /// empty unless pin access is exempt.
+    pub fn pin_access_grids(&self, id: usize) -> HashSet<Pair<usize>> {
+        if !self.pin_access_exempt {
+            return HashSet::new();
+        }
+
+        self.nets.get(id).map_or_else(HashSet::new, |net| {
+            net.pins
+                .iter()
+                .filter_map(|&pin| self.pin_point(pin))
+                .map(|point| point.flatten())
+                .collect()
+        })
+    }
+
+    /// Routes a net by connecting the two ends of every edge of its Steiner topology
+    /// with `connect`, which is given the GGrids the net already occupies.
+    /// A Steiner point is reached on the layer of the point it is connected from,
+    /// raised to the net's min layer if needed.
+    /// Pins below the min layer are connected from the top of their via stack, see `via_stack`.
+    /// Returns `None` if some edge cannot be connected.
+    pub fn route_topology<F>(&self, id: usize, connect: F) -> Option<HashSet<Route<usize>>>
+    where
+        F: FnMut(Point<usize>, Point<usize>, &HashSet<Point<usize>>) -> Option<Vec<Point<usize>>>,
+    {
+        self.route_topology_with(id, Vec::new(), connect)
+    }
+
+    /// Like `route_topology`, but the net starts out with the paths of `escapes`,
+    /// which later edges may reuse.
+    pub fn route_topology_with<F>(
+        &self,
+        id: usize,
+        escapes: Vec<Vec<Point<usize>>>,
+        mut connect: F,
+    ) -> Option<HashSet<Route<usize>>>
+    where
+        F: FnMut(Point<usize>, Point<usize>, &HashSet<Point<usize>>) -> Option<Vec<Point<usize>>>,
+    {
+        let net = self.nets.get(id)?;
+        let topology = self.steiner_topology(id);
+
+        let mut points: Vec<Option<Point<usize>>> = vec![None; topology.points.len()];
+        let mut stacks = Vec::new();
+        for (point, &pin) in points.iter_mut().zip(net.pins.iter()) {
+            let pin_point = self.pin_point(pin)?;
+            *point = Some(pin_point);
+            if let Some(stack) = self.via_stack(pin_point, net.min_layer) {
+                *point = stack.last().copied();
+                stacks.push(stack);
+            }
+        }
+
+        // GGrids the net already occupies cost nothing more.
+        let mut used: HashSet<_> = points.iter().flatten().copied().collect();
+
+        let mut routes = HashSet::new();
+        for path in stacks.into_iter().chain(escapes) {
+            used.extend(path.iter().copied());
+            routes.extend(path_to_routes(&path));
+        }
+        for &(from, to) in topology.edges.iter() {
+            let source = points[from]?;
+            let target = *points[to].get_or_insert_with(|| {
+                topology.points[to]
+                    .with(source.lay().max((net.min_layer + 1).min(self.layers.len())))
+            });
+
+            let path = connect(source, target, &used)?;
+            used.extend(path.iter().copied());
+            routes.extend(path_to_routes(&path));
+        }
+
+        Some(routes)
+    }
+
+    /// Routes a net inside a corridor planned by the coarse planner.
+    /// The corridor is widened every time the search fails,
+    /// until it covers the whole chip.
+    pub fn route_net_in_corridor(
+        &self,
+        id: usize,
+        mut corridor: Corridor,
+    ) -> Option<HashSet<Route<usize>>> {
+        loop {
+            if let Some(routes) = self.route_net(id, Some(&corridor)) {
+                return Some(routes);
+            }
+
+            if corridor.is_full() {
+                return None;
+            }
+
+            corridor.widen();
+        }
+    }
+
+    /// Removes the routes of a net and the demand they cause.
+    /// Returns the removed routes.
+    pub fn rip_up(&mut self, id: usize) -> Result<HashSet<Route<usize>>> {
+        let grids = match self.nets.get(id) {
+            Some(net) => self.own_net_grids(net),
+            None => return Ok(HashSet::new()),
+        };
+
+        for point in grids {
+            self.remove_demand(point, 1)?;
+        }
+
+        Ok(std::mem::take(&mut self.nets[id].routes))
+    }
+
+    /// Gives a net new routes and adds the demand they cause.
+    pub fn commit_routes(&mut self, id: usize, routes: HashSet<Route<usize>>) -> Result<()> {
+        let net = match self.nets.get_mut(id) {
+            Some(net) => net,
+            None => return Ok(()),
+        };
+        net.routes = routes;
+        if let Some(history) = self.history.as_mut() {
+            history.record(id, self.nets[id].routes.iter());
+        }
+
+        for point in self.own_net_grids(&self.nets[id]) {
+            self.add_demand(point, 1)?;
+        }
+
+        Ok(())
+    }
+
+    /// Routes a net from scratch with the configured router.
+    /// A net with a planned corridor is refined inside it.
+    fn route_net_configured(&self, id: usize) -> Option<HashSet<Route<usize>>> {
+        if self.two_phase {
+            self.route_net_two_phase(id)
+        } else if let Some(corridor) = self.corridors.get(id) {
+            self.route_net_in_corridor(id, corridor.clone())
+        } else {
+            self.route_net_bounded(id)
+        }
+    }
+
+    /// Plans a corridor for every net on a chip coarsened by `factor`,
+    /// which the configured router refines.
+    pub fn plan_coarse_routes(&mut self, factor: usize) {
+        let coarse = self.coarsen(factor);
+        self.corridors = coarse
+            .plan_corridors()
+            .into_iter()
+            .map(|tiles| Corridor::new(&coarse, tiles))
+            .collect();
+    }
+
+    /// Routes a net inside the bounding box of its pins plus a margin.
+    /// The margin doubles every time the search fails,
+    /// until the box covers the whole chip.
+    pub fn route_net_bounded(&self, id: usize) -> Option<HashSet<Route<usize>>> {
+        use crate::consts::BOUNDING_BOX_MARGIN;
+
+        let (Pair(r0, c0), Pair(r1, c1)) = match self.bounding_box(id) {
+            Some(bbox) => bbox,
+            None => return self.route_net(id, None),
+        };
+        let mut margin = BOUNDING_BOX_MARGIN;
+        loop {
+            let low = Pair(r0.saturating_sub(margin), c0.saturating_sub(margin));
+            let high = Pair(r1 + margin, c1 + margin);
+            let corridor = Corridor::rectangle(self.dim, low, high);
+
+            if let Some(routes) = self.route_net(id, Some(&corridor)) {
+                return Some(routes);
+            }
+
+            if corridor.is_full() {
+                return None;
+            }
+
+            margin = (margin * 2).max(1);
+        }
+    }
+
+    /// Reroutes only the dirty nets, whose pins moved since they were last routed,
+    /// in the configured order. Nets that can't be routed keep their old routes and stay dirty.
+    /// Returns the number of nets still dirty.
+    pub fn reroute_dirty_nets(&mut self) -> Result<usize> {
+        let mut dirty: Vec<_> = self.dirty_nets.iter().copied().collect();
+        dirty.sort_unstable();
+        let dirty = self.order_nets(dirty);
+
+        for id in dirty {
+            let old = self.rip_up(id)?;
+            match self.route_net_configured(id) {
+                Some(routes) => {
+                    self.commit_routes(id, routes)?;
+                    self.dirty_nets.remove(&id);
+                    Counters::bump(&self.counters.nets_rerouted);
+                }
+                None => {
+                    Counters::bump(&self.counters.failed_routes);
+                    self.commit_routes(id, old)?;
+                }
+            }
+        }
+
+        Ok(self.dirty_nets.len())
+    }
+
+    /// Reroutes every net in the configured order with the maze router until time runs out.
+    /// Once the throttle finds the gains too small, only dirty and broken nets are rerouted.
+    /// Nets with unreachable pins are skipped, see `sanity_check`.
+    /// Partially routed nets with many pins grow their existing tree instead of starting over.
+    /// With bus routing, nets of a bus first try the routes of its first net on a parallel track.
+    /// A new route is only kept if the old one is disconnected, runs below the min layer,
+    /// or is longer, otherwise the old route is restored.
+    /// With `reserve` set, supply is reserved for the nets not routed yet, see `Reservation`.
+    /// Overflow left afterwards is recorded in the congestion history.
+    /// Returns the number of nets rerouted.
+    pub fn reroute_nets(&mut self, start: Instant, duration: Duration) -> Result<usize> {
+        self.reroute_nets_in((0..self.nets.len()).collect(), start, duration)
+    }
+
+    /// Reroutes the nets `ids` in the configured order like `reroute_nets` does.
+    /// Returns the number of nets rerouted.
+    pub fn reroute_nets_in(
+        &mut self,
+        ids: Vec<usize>,
+        start: Instant,
+        duration: Duration,
+    ) -> Result<usize> {
+        use crate::consts::TREE_MIN_PINS;
+
+        let order = self.order_nets(ids);
+        let followers = if self.bus_routing {
+            self.bus_followers(&order)
+        } else {
+            HashMap::new()
+        };
+
+        if let Some(fraction) = self.reserve {
+            self.reserve_for(fraction, order.iter().copied());
+        }
+        if let Some(throttle) = self.throttle.as_mut() {
+            throttle.reset();
+        }
+
+        // Pins don't move while nets are rerouted.
+        let pin_points = self.pin_points();
+        let mut rerouted = 0;
+        for id in order {
+            if Self::check_time(start, duration).is_err() {
+                break;
+            }
+            self.release_reservation(id);
+
+            if !self.pin_access_exempt
+                && !self
+                    .unreachable_pins(&self.nets[id], &pin_points)
+                    .is_empty()
+            {
+                continue;
+            }
+
+            let connected = self.is_connected(&self.nets[id], &pin_points)
+                && Self::below_min_layer(&self.nets[id]).is_empty()
+                && self.wrong_direction(&self.nets[id]).is_empty();
+            let length = self.net_length(&self.nets[id]);
+            let stalled = self
+                .throttle
+                .as_ref()
+                .is_some_and(|throttle| throttle.is_stalled(0));
+            if stalled && connected && !self.dirty_nets.contains(&id) {
+                continue;
+            }
+
+            let old = self.rip_up(id)?;
+            let shifted = followers
+                .get(&id)
+                .and_then(|&(leader, shift)| self.shifted_routes(leader, id, shift));
+            let routed = if shifted.is_some() {
+                shifted
+            } else if !connected && self.nets[id].pins.len() >= TREE_MIN_PINS {
+                self.route_net_tree(id, &old)
+                    .or_else(|| self.route_net(id, None))
+            } else {
+                self.route_net_configured(id)
+            };
+            let routes = match routed {
+                Some(routes) => routes,
+                None => {
+                    Counters::bump(&self.counters.failed_routes);
+                    self.commit_routes(id, old)?;
+                    continue;
+                }
+            };
+
+            self.commit_routes(id, routes)?;
+            self.dirty_nets.remove(&id);
+            let new_length = self.net_length(&self.nets[id]);
+            if connected && new_length > length {
+                self.rip_up(id)?;
+                self.commit_routes(id, old)?;
+            } else {
+                rerouted += 1;
+                Counters::bump(&self.counters.nets_rerouted);
+            }
+
+            // Connecting a net is worth at least as much as its whole length.
+            let gain = if connected {
+                length.saturating_sub(new_length)
+            } else {
+                new_length.max(1)
+            } as isize;
+            if let Some(throttle) = self.throttle.as_mut() {
+                throttle.record(0, gain);
+            }
+        }
+        self.reservation = None;
+        self.age_congestion();
+
+        Ok(rerouted)
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{
+    chip::Chip,
+    components::{Pair, Point, Route},
+    metrics::Counters,
+};
+use anyhow::Result;
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+/// Congestion costs of negotiated routing, in the style of PathFinder.
+/// Costs are in thousandths, so entering a free GGrid without history costs `UNIT`.
+/// The history outlives a negotiation, so later passes keep avoiding chronic congestion.
+#[derive(Clone, Debug, Default)]
+pub struct Congestion {
+    /// accumulated overflow cost of every GGrid, per layer, empty until overflow is recorded
+    pub history: Vec<Vec<usize>>,
+    /// weight of the overflow a step would cause right now
+    pub present: usize,
+}
+
+impl Congestion {
+    pub const UNIT: usize = 1000;
+}
+
+impl Chip {
+    /// Ages the congestion history after a rip-up iteration:
+    /// every entry decays to `HISTORY_DECAY` thousandths of itself,
+    /// then every GGrid adds `HISTORY_INCREMENT` per unit it overflows by.
+    /// The history is dropped once it has decayed completely.
+    pub fn age_congestion(&mut self) {
+        use crate::consts::{HISTORY_DECAY, HISTORY_INCREMENT};
+
+        let size = self.dim.size();
+        let history = &mut self.congestion.history;
+        history.resize_with(self.layers.len(), || vec![0; size]);
+
+        let mut recorded = false;
+        for (layer, costs) in self.layers.iter().zip(history.iter_mut()) {
+            for ((cost, &demand), &capacity) in costs
+                .iter_mut()
+                .zip(layer.demand.iter())
+                .zip(layer.capacity.iter())
+            {
+                *cost = (cost.saturating_mul(HISTORY_DECAY) / Congestion::UNIT).saturating_add(
+                    demand
+                        .saturating_sub(capacity)
+                        .saturating_mul(HISTORY_INCREMENT),
+                );
+                recorded |= *cost > 0;
+            }
+        }
+        if !recorded {
+            history.clear();
+        }
+    }
+
+    /// Accumulated congestion cost of a GGrid, 0 if none was recorded.
+    /// `point` is indexed the same way as in the input file.
+    pub fn history_cost(&self, point: Point<usize>) -> usize {
+        if !self.in_bounds(point) {
+            return 0;
+        }
+        let Point(row, col, lay) = point;
+        self.congestion
+            .history
+            .get(lay - 1)
+            .and_then(|costs| costs.get((row - 1) * self.dim.y() + col - 1))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// GGrids whose demand exceeds their capacity.
+    pub fn overflowed(&self) -> HashSet<Point<usize>> {
+        let Pair(rows, cols) = self.dim;
+        self.layers
+            .iter()
+            .flat_map(|layer| {
+                (0..rows).flat_map(move |row| {
+                    (0..cols).filter_map(move |col| {
+                        match (layer.get_capacity(row, col), layer.get_demand(row, col)) {
+                            (Some(capacity), Some(demand)) if demand > capacity => {
+                                Some(Point(row + 1, col + 1, layer.id + 1))
+                            }
+                            _ => None,
+                        }
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// Routes a net letting it pass through congested GGrids at a price set by the cost model.
+    /// The net's own demand should be ripped up first.
+    pub fn route_net_negotiated(&self, id: usize) -> Option<HashSet<Route<usize>>> {
+        let present = self.congestion.present;
+        let min_layer = self.nets.get(id)?.min_layer;
+        self.route_topology(id, |source, target, used| {
+            // Steps along the net itself are free, so no heuristic is admissible.
+            self.cheapest_path(source, target, 0, min_layer, |parent, from, to| {
+                if used.contains(&to) {
+                    return Some(0);
+                }
+                self.step_cost(parent, from, to, present)
+            })
+        })
+    }
+
+    /// Rips up and reroutes the nets passing through overflowed GGrids,
+    /// raising the price of congestion after every iteration,
+    /// until no GGrid overflows, the overflow stops improving, or time runs out.
+    /// The history of earlier passes is kept, the present weight starts over.
+    /// Returns the number of iterations.
+    pub fn negotiate(&mut self, start: Instant, duration: Duration) -> Result<usize> {
+        use crate::consts::{NEGOTIATE_PATIENCE, PRESENT_GROWTH};
+
+        self.congestion.present = Congestion::UNIT;
+
+        let mut iterations = 0;
+        let mut best = self.metrics().overflow;
+        let mut stalled = 0;
+        loop {
+            let overflowed = self.overflowed();
+            if overflowed.is_empty() || Self::check_time(start, duration).is_err() {
+                return Ok(iterations);
+            }
+
+            let pin_points = self.pin_points();
+            let offending: Vec<_> = self
+                .nets
+                .iter()
+                .filter(|net| {
+                    self.net_grids(net, &pin_points)
+                        .iter()
+                        .any(|point| overflowed.contains(point))
+                })
+                .filter(|net| {
+                    self.pin_access_exempt || self.unreachable_pins(net, &pin_points).is_empty()
+                })
+                .map(|net| net.id)
+                .collect();
+
+            // Overflow caused by blockages and pins alone can't be fixed by routing.
+            if offending.is_empty() {
+                return Ok(iterations);
+            }
+            self.begin_pass("negotiate");
+
+            for id in offending {
+                if Self::check_time(start, duration).is_err() {
+                    break;
+                }
+
+                let old = self.rip_up(id)?;
+                match self.route_net_negotiated(id) {
+                    Some(routes) => {
+                        self.commit_routes(id, routes)?;
+                        Counters::bump(&self.counters.nets_rerouted);
+                    }
+                    None => {
+                        self.commit_routes(id, old)?;
+                        Counters::bump(&self.counters.failed_routes);
+                    }
+                }
+            }
+
+            self.age_congestion();
+            self.congestion.present =
+                self.congestion.present.saturating_mul(PRESENT_GROWTH) / Congestion::UNIT;
+            iterations += 1;
+
+            let overflow = self.metrics().overflow;
+            if overflow < best {
+                best = overflow;
+                stalled = 0;
+            } else {
+                stalled += 1;
+            }
+            if stalled >= NEGOTIATE_PATIENCE {
+                return Ok(iterations);
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{chip::Chip, components::Pair};
+use anyhow::{anyhow, Error, Result};
+use std::{cmp::Reverse, str::FromStr};
+
+/// The order nets are rerouted in.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum NetOrder {
+    /// The order of the input file.
+    #[default]
+    Input,
+    /// Nets with smaller bounding boxes first.
+    HalfPerimeter,
+    /// Nets with fewer pins first.
+    PinCount,
+    /// Nets whose bounding boxes are more congested first.
+    Congestion,
+    /// Harder nets first, see `Difficulty`.
+    Difficulty,
+}
+
+impl FromStr for NetOrder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "input" => Ok(NetOrder::Input),
+            "hpwl" => Ok(NetOrder::HalfPerimeter),
+            "pins" => Ok(NetOrder::PinCount),
+            "congestion" => Ok(NetOrder::Congestion),
+            "difficulty" => Ok(NetOrder::Difficulty),
+            _ => Err(anyhow!("Unknown net order: {}", s)),
+        }
+    }
+}
+
+/// Keeps the perturbation of the net order apart from the move order's.
+const NET_ORDER_SALT: u64 = 0x6e65_7473;
+
+impl Chip {
+    /// Ids of all nets in the order the configured `NetOrder` routes them.
+    /// Ties keep the input order.
+    pub fn routing_order(&self) -> Vec<usize> {
+        self.order_nets((0..self.nets.len()).collect())
+    }
+
+    /// Sorts the nets `ids` in the order the configured `NetOrder` routes them,
+    /// perturbed during a restart. Ties keep the order of `ids`.
+    pub fn order_nets(&self, mut ids: Vec<usize>) -> Vec<usize> {
+        match self.net_order {
+            NetOrder::Input => {}
+            NetOrder::HalfPerimeter => ids.sort_by_cached_key(|&id| self.half_perimeter(id)),
+            NetOrder::PinCount => ids.sort_by_key(|&id| self.nets[id].pins.len()),
+            NetOrder::Congestion => {
+                ids.sort_by_cached_key(|&id| Reverse(self.bounding_congestion(id)))
+            }
+            NetOrder::Difficulty => {
+                let pin_points = self.pin_points();
+                ids.sort_by_cached_key(|&id| Reverse(self.net_difficulty(id, &pin_points).score()))
+            }
+        }
+        self.perturb(&mut ids, NET_ORDER_SALT);
+        ids
+    }
+
+    /// Corners of the GGrids a net's pins span, `None` for a net without pins.
+    pub(crate) fn bounding_box(&self, id: usize) -> Option<(Pair<usize>, Pair<usize>)> {
+        let net = self.nets.get(id)?;
+        let positions = net
+            .pins
+            .iter()
+            .map(|&pin| self.cells[self.pin_owners[pin].0].position);
+
+        positions.fold(None, |bbox, Pair(row, col)| match bbox {
+            None => Some((Pair(row, col), Pair(row, col))),
+            Some((Pair(r0, c0), Pair(r1, c1))) => Some((
+                Pair(r0.min(row), c0.min(col)),
+                Pair(r1.max(row), c1.max(col)),
+            )),
+        })
+    }
+
+    /// Half-perimeter of a net's bounding box in GGrids.
+    pub fn half_perimeter(&self, id: usize) -> usize {
+        self.bounding_box(id)
+            .map_or(0, |(Pair(r0, c0), Pair(r1, c1))| r1 - r0 + c1 - c0)
+    }
+
+    /// Demand over capacity inside a net's bounding box on all layers, in thousandths.
+    pub fn bounding_congestion(&self, id: usize) -> usize {
+        let (Pair(r0, c0), Pair(r1, c1)) = match self.bounding_box(id) {
+            Some(bbox) => bbox,
+            None => return 0,
+        };
+
+        let (mut demand, mut capacity) = (0, 0);
+        for layer in self.layers.iter() {
+            for row in r0 - 1..r1 {
+                for col in c0 - 1..c1 {
+                    demand += layer.get_demand(row, col).copied().unwrap_or(0);
+                    capacity += layer.get_capacity(row, col).copied().unwrap_or(0);
+                }
+            }
+        }
+
+        demand * 1000 / capacity.max(1)
+    }
+}
@@ -0,0 +1,316 @@
+use crate::{
+    chip::Chip,
+    components::{Pair, Route},
+    metrics::Counters,
+    router::Corridor,
+    utilities,
+};
+use anyhow::Result;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// GGrids a net reserves while it is routed in parallel, on all layers.
+type Region = (Pair<usize>, Pair<usize>);
+
+/// A net of a wave, its region and the tiles it locks.
+type Member = (usize, Region, Vec<Pair<usize>>);
+
+/// Contention of the parallel router on the tiles nets lock, see `reroute_nets_parallel`.
+/// Per-tile counts are for tiles of the current size, and start over when it changes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TileStats {
+    /// side of a tile in GGrids
+    pub size: usize,
+    /// sizes every wave was built with, in order
+    pub sizes: Vec<usize>,
+    /// nets kept out of a wave because a tile they needed was locked, by tile
+    pub waits: HashMap<Pair<usize>, usize>,
+    /// nets that could not be routed inside their region and were deferred, by tile
+    pub conflicts: HashMap<Pair<usize>, usize>,
+    /// nets kept out of a wave in total
+    pub total_waits: usize,
+    /// nets deferred in total
+    pub total_conflicts: usize,
+}
+
+/// Tiles of `size` by `size` GGrids a region touches.
+fn region_tiles((Pair(r0, c0), Pair(r1, c1)): Region, size: usize) -> Vec<Pair<usize>> {
+    let tile = |n: usize| (n - 1) / size + 1;
+    (tile(r0)..=tile(r1))
+        .flat_map(|row| (tile(c0)..=tile(c1)).map(move |col| Pair(row, col)))
+        .collect()
+}
+
+impl TileStats {
+    /// Counts a net kept out of a wave by the locked tiles it needed.
+    fn wait(&mut self, tiles: &[Pair<usize>]) {
+        self.total_waits += 1;
+        for &tile in tiles {
+            *self.waits.entry(tile).or_default() += 1;
+        }
+    }
+
+    /// Counts a net deferred to be routed alone, on every tile it locked.
+    fn conflict(&mut self, tiles: &[Pair<usize>]) {
+        self.total_conflicts += 1;
+        for &tile in tiles {
+            *self.conflicts.entry(tile).or_default() += 1;
+        }
+    }
+
+    /// Adapts the tile size after a wave where `waited` of the `looked` nets had to wait
+    /// and `conflicted` of the nets routed were deferred:
+    /// tiles shrink when many nets wait for each other, so more fit in a wave,
+    /// and grow when few do, so a net locks fewer of them.
+    fn adapt(&mut self, looked: usize, waited: usize, conflicted: usize) {
+        use crate::consts::PARALLEL_MAX_TILE;
+
+        let size = if 2 * (waited + conflicted) > looked {
+            (self.size / 2).max(1)
+        } else if 8 * (waited + conflicted) < looked {
+            (self.size * 2).min(PARALLEL_MAX_TILE)
+        } else {
+            self.size
+        };
+        if size != self.size {
+            self.size = size;
+            self.waits.clear();
+            self.conflicts.clear();
+        }
+    }
+}
+
+impl Chip {
+    /// The region a net reserves: the bounding box of its pins and routes plus a margin.
+    /// Everything the net's rip-up and new routes change lies inside it.
+    fn routing_region(&self, id: usize) -> Option<Region> {
+        use crate::consts::BOUNDING_BOX_MARGIN;
+
+        let (mut low, mut high) = self.bounding_box(id)?;
+        for point in self.nets[id]
+            .routes
+            .iter()
+            .flat_map(|&Route(source, target)| [source, target])
+        {
+            let Pair(row, col) = point.flatten();
+            low = Pair(low.x().min(row), low.y().min(col));
+            high = Pair(high.x().max(row), high.y().max(col));
+        }
+
+        let Pair(rows, cols) = self.dim;
+        Some((
+            Pair(
+                low.x().saturating_sub(BOUNDING_BOX_MARGIN).max(1),
+                low.y().saturating_sub(BOUNDING_BOX_MARGIN).max(1),
+            ),
+            Pair(
+                (high.x() + BOUNDING_BOX_MARGIN).min(rows),
+                (high.y() + BOUNDING_BOX_MARGIN).min(cols),
+            ),
+        ))
+    }
+
+    /// The tile size to start with: the median side of the nets' regions,
+    /// so short and long nets alike lock only a few tiles.
+    fn initial_tile_size(&self, ids: &VecDeque<usize>) -> usize {
+        use crate::consts::PARALLEL_MAX_TILE;
+
+        let mut sides: Vec<_> = ids
+            .iter()
+            .filter_map(|&id| self.routing_region(id))
+            .map(|(low, high)| (high.x() - low.x() + 1).max(high.y() - low.y() + 1))
+            .collect();
+        sides.sort_unstable();
+        sides
+            .get(sides.len() / 2)
+            .map_or(1, |&side| side.clamp(1, PARALLEL_MAX_TILE))
+    }
+
+    /// Takes up to `workers` of the next nets of `pending` that lock pairwise disjoint tiles,
+    /// looking at most `PARALLEL_WINDOW` nets ahead so the order is roughly kept.
+    /// A net locks every tile its region touches, and nets finding one locked wait.
+    /// Returns the wave with the tiles of every net, and the number of nets looked at.
+    fn disjoint_wave(
+        &self,
+        pending: &mut VecDeque<usize>,
+        stats: &mut TileStats,
+        workers: usize,
+    ) -> (Vec<Member>, usize) {
+        use crate::consts::PARALLEL_WINDOW;
+
+        let mut wave = Vec::new();
+        let mut locked = HashSet::new();
+        let mut skipped = Vec::new();
+        let mut looked = 0;
+        while let Some(id) = pending.pop_front() {
+            looked += 1;
+            match self.routing_region(id) {
+                Some(region) => {
+                    let tiles = region_tiles(region, stats.size);
+                    if tiles.iter().any(|tile| locked.contains(tile)) {
+                        stats.wait(&tiles);
+                        skipped.push(id);
+                    } else {
+                        locked.extend(tiles.iter().copied());
+                        wave.push((id, region, tiles));
+                    }
+                }
+                None => skipped.push(id),
+            }
+            if looked == PARALLEL_WINDOW || wave.len() == workers {
+                break;
+            }
+        }
+
+        // A net without pins reserves nothing, it is routed alone.
+        if wave.is_empty() {
+            if let Some(id) = skipped.pop() {
+                let region = (Pair(1, 1), self.dim);
+                wave.push((id, region, region_tiles(region, stats.size)));
+            }
+        }
+        for id in skipped.into_iter().rev() {
+            pending.push_front(id);
+        }
+        (wave, looked)
+    }
+
+    /// Reroutes every net like `reroute_nets`, but routes nets whose regions are disjoint
+    /// concurrently with rayon, see `routing_region`.
+    /// Every net of a wave is ripped up, then searched inside its region,
+    /// which no other net of the wave touches, so each search sees the same demand
+    /// as if the nets were routed one by one. The results are committed in order.
+    /// A net that can't be routed inside its region is rerouted alone afterwards,
+    /// with the configured router.
+    /// Nets lock the tiles their regions touch to join a wave, and the tile size adapts
+    /// to the contention after every wave, see `TileStats` in the chip's metrics.
+    /// With a throttle, the nets of a wave take turns reporting their gains as the workers,
+    /// and a wave holds only as many nets as the throttle keeps workers active.
+    /// Bus following and tree growing are not done.
+    /// Returns the number of nets rerouted.
+    pub fn reroute_nets_parallel(&mut self, start: Instant, duration: Duration) -> Result<usize> {
+        let pin_points = self.pin_points();
+        let mut pending: VecDeque<_> = self
+            .routing_order()
+            .into_iter()
+            .filter(|&id| {
+                self.pin_access_exempt
+                    || self
+                        .unreachable_pins(&self.nets[id], &pin_points)
+                        .is_empty()
+            })
+            .collect();
+
+        let mut stats = TileStats {
+            size: self.initial_tile_size(&pending),
+            ..TileStats::default()
+        };
+        if let Some(throttle) = self.throttle.as_mut() {
+            throttle.reset();
+        }
+
+        let mut rerouted = 0;
+        let mut deferred = Vec::new();
+        let mut turn = 0;
+        while !pending.is_empty() && Self::check_time(start, duration).is_ok() {
+            stats.sizes.push(stats.size);
+            let waits = stats.total_waits;
+            let conflicts = stats.total_conflicts;
+            let workers = self
+                .throttle
+                .as_ref()
+                .map_or(usize::MAX, |throttle| throttle.active_workers());
+            let (wave, looked) = self.disjoint_wave(&mut pending, &mut stats, workers);
+
+            let mut olds = Vec::with_capacity(wave.len());
+            for &(id, _, _) in wave.iter() {
+                let connected = self.is_connected(&self.nets[id], &pin_points)
+                    && Self::below_min_layer(&self.nets[id]).is_empty()
+                    && self.wrong_direction(&self.nets[id]).is_empty();
+                let length = self.net_length(&self.nets[id]);
+                olds.push((connected, length, self.rip_up(id)?));
+            }
+
+            let chip = &*self;
+            let routed = chip.threads.install(|| {
+                utilities::par_map(&wave, |&(id, (low, high), _)| {
+                    chip.route_net(id, Some(&Corridor::rectangle(chip.dim, low, high)))
+                })
+            });
+
+            for ((&(id, _, ref tiles), (connected, length, old)), routes) in
+                wave.iter().zip(olds).zip(routed)
+            {
+                let routes = match routes {
+                    Some(routes) => routes,
+                    None => {
+                        stats.conflict(tiles);
+                        self.commit_routes(id, old)?;
+                        deferred.push((id, connected, length));
+                        continue;
+                    }
+                };
+
+                self.commit_routes(id, routes)?;
+                self.dirty_nets.remove(&id);
+                let new_length = self.net_length(&self.nets[id]);
+                if connected && new_length > length {
+                    self.rip_up(id)?;
+                    self.commit_routes(id, old)?;
+                } else {
+                    rerouted += 1;
+                    Counters::bump(&self.counters.nets_rerouted);
+                }
+
+                // Connecting a net is worth at least as much as its whole length.
+                let gain = if connected {
+                    length.saturating_sub(new_length)
+                } else {
+                    new_length.max(1)
+                } as isize;
+                if let Some(throttle) = self.throttle.as_mut() {
+                    throttle.record(turn % throttle.gains.len(), gain);
+                    turn += 1;
+                }
+            }
+
+            stats.adapt(
+                looked,
+                stats.total_waits - waits,
+                stats.total_conflicts - conflicts,
+            );
+        }
+        self.counters.record_tiles(stats);
+
+        for (id, connected, length) in deferred {
+            if Self::check_time(start, duration).is_err() {
+                break;
+            }
+
+            let old = self.rip_up(id)?;
+            let routes = match self.route_net_configured(id) {
+                Some(routes) => routes,
+                None => {
+                    Counters::bump(&self.counters.failed_routes);
+                    self.commit_routes(id, old)?;
+                    continue;
+                }
+            };
+
+            self.commit_routes(id, routes)?;
+            self.dirty_nets.remove(&id);
+            if connected && self.net_length(&self.nets[id]) > length {
+                self.rip_up(id)?;
+                self.commit_routes(id, old)?;
+            } else {
+                rerouted += 1;
+                Counters::bump(&self.counters.nets_rerouted);
+            }
+        }
+        self.age_congestion();
+
+        Ok(rerouted)
+    }
+}
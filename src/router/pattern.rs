@@ -0,0 +1,103 @@
+use crate::{
+    chip::Chip,
+    components::{Direction, Point},
+};
+
+/// Appends the straight line from the last point of `path` to `to`, excluding the start.
+/// Only one coordinate may differ.
+pub(crate) fn walk(path: &mut Vec<Point<usize>>, to: Point<usize>) {
+    let step = |from: usize, to: usize| {
+        if from < to {
+            from + 1
+        } else {
+            from - 1
+        }
+    };
+
+    while let Some(&last) = path.last() {
+        let Point(row, col, lay) = last;
+        let next = match to {
+            Point(trow, _, _) if trow != row => Point(step(row, trow), col, lay),
+            Point(_, tcol, _) if tcol != col => Point(row, step(col, tcol), lay),
+            Point(_, _, tlay) if tlay != lay => Point(row, col, step(lay, tlay)),
+            _ => break,
+        };
+        path.push(next);
+    }
+}
+
+impl Chip {
+    /// Tries L- and Z-shaped connections from `source` to `target`.
+    /// Planar runs go on a layer of the matching direction at or above `min_layer`,
+    /// joined by vias. Only paths whose GGrids (except both ends) pass `allowed` are considered.
+    /// Returns the shortest one found, `None` if every pattern is blocked.
+    pub fn pattern_path<F>(
+        &self,
+        source: Point<usize>,
+        target: Point<usize>,
+        min_layer: usize,
+        allowed: F,
+    ) -> Option<Vec<Point<usize>>>
+    where
+        F: Fn(Point<usize>) -> bool,
+    {
+        if source == target {
+            return Some(vec![source]);
+        }
+
+        let layers = |direction: Direction| -> Vec<usize> {
+            self.layers
+                .iter()
+                .filter(|layer| layer.direction == direction && layer.id >= min_layer)
+                .map(|layer| layer.id + 1)
+                .collect()
+        };
+        let horizontal = layers(Direction::Horizontal);
+        let vertical = layers(Direction::Vertical);
+
+        let Point(srow, scol, _) = source;
+        let Point(trow, tcol, _) = target;
+
+        // Corners (row, col) every pattern turns at, and whether it starts horizontally.
+        let mut shapes = vec![(vec![(srow, tcol)], true), (vec![(trow, scol)], false)];
+        for mid in scol.min(tcol) + 1..scol.max(tcol) {
+            shapes.push((vec![(srow, mid), (trow, mid)], true));
+        }
+        for mid in srow.min(trow) + 1..srow.max(trow) {
+            shapes.push((vec![(mid, scol), (mid, tcol)], false));
+        }
+
+        let mut best: Option<Vec<Point<usize>>> = None;
+        for &h in horizontal.iter() {
+            for &v in vertical.iter() {
+                for (corners, horizontal_first) in shapes.iter() {
+                    let mut path = vec![source];
+                    let mut on_horizontal = *horizontal_first;
+                    for &(row, col) in corners.iter().chain(Some(&(trow, tcol))) {
+                        let lay = if on_horizontal { h } else { v };
+                        on_horizontal = !on_horizontal;
+
+                        // Don't change layers for a run of length zero.
+                        let Point(r, c, _) = *path.last()?;
+                        if (r, c) == (row, col) {
+                            continue;
+                        }
+                        walk(&mut path, Point(r, c, lay));
+                        walk(&mut path, Point(row, col, lay));
+                    }
+                    walk(&mut path, target);
+
+                    let inner = &path[1..path.len() - 1];
+                    if best.as_ref().is_some_and(|best| best.len() <= path.len())
+                        || !inner.iter().all(|&point| allowed(point))
+                    {
+                        continue;
+                    }
+                    best = Some(path);
+                }
+            }
+        }
+
+        best
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{
+    chip::Chip,
+    components::{Net, Pair, Point, Route},
+};
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// A branch point of a routed net and the branches leaving it.
+struct Branching {
+    /// the GGrid where three or more branches meet
+    point: Point<usize>,
+    /// GGrids of the branches between the branch point and their anchors
+    interior: HashSet<Point<usize>>,
+    /// the pin or branch point at the far end of every branch
+    anchors: Vec<Point<usize>>,
+}
+
+impl Chip {
+    /// Neighbors of `point` inside `points`, see `neighbors`.
+    fn neighbors_within(
+        &self,
+        point: Point<usize>,
+        points: &HashSet<Point<usize>>,
+    ) -> Vec<Point<usize>> {
+        self.neighbors(point)
+            .into_iter()
+            .filter(|next| points.contains(next))
+            .collect()
+    }
+
+    /// Branch points of a net: GGrids other than pins where three or more branches meet,
+    /// with every branch followed to the next pin or branch point.
+    fn branchings(
+        &self,
+        points: &HashSet<Point<usize>>,
+        pins: &HashSet<Point<usize>>,
+    ) -> Vec<Branching> {
+        let mut branchings = Vec::new();
+        for &point in points.iter().filter(|point| !pins.contains(point)) {
+            let nexts = self.neighbors_within(point, points);
+            if nexts.len() < 3 {
+                continue;
+            }
+
+            let mut interior = HashSet::new();
+            let mut anchors = Vec::with_capacity(nexts.len());
+            for next in nexts {
+                let (mut prev, mut current) = (point, next);
+                loop {
+                    let onwards: Vec<_> = self
+                        .neighbors_within(current, points)
+                        .into_iter()
+                        .filter(|&after| after != prev)
+                        .collect();
+                    if current == point || pins.contains(&current) || onwards.len() != 1 {
+                        break;
+                    }
+                    interior.insert(current);
+                    prev = current;
+                    current = onwards[0];
+                }
+                anchors.push(current);
+            }
+            // A loop back to the branch point is left for the cleanup.
+            if anchors.contains(&point) {
+                continue;
+            }
+
+            branchings.push(Branching {
+                point,
+                interior,
+                anchors,
+            });
+        }
+        branchings.sort_unstable_by_key(|branching| {
+            let Point(row, col, lay) = branching.point;
+            (row, col, lay)
+        });
+        branchings
+    }
+
+    /// Positions worth trying for a branch point, on its layer within the bounding box
+    /// of its anchors, the feasible rectangle where it can only shorten the tree.
+    /// The ones closest to the anchors in total come first.
+    fn slide_candidates(&self, branching: &Branching) -> Vec<Point<usize>> {
+        use crate::consts::STEINER_SLIDE_CANDIDATES;
+
+        let flat: Vec<_> = branching.anchors.iter().map(Point::flatten).collect();
+        let (rlo, rhi) = (
+            flat.iter().map(Pair::x).min().unwrap_or(0),
+            flat.iter().map(Pair::x).max().unwrap_or(0),
+        );
+        let (clo, chi) = (
+            flat.iter().map(Pair::y).min().unwrap_or(0),
+            flat.iter().map(Pair::y).max().unwrap_or(0),
+        );
+        let total = |position: Pair<usize>| {
+            flat.iter()
+                .map(|anchor| anchor.distance(&position))
+                .sum::<usize>()
+        };
+
+        let current = branching.point.flatten();
+        let mut candidates: Vec<_> = (rlo..=rhi)
+            .flat_map(|row| (clo..=chi).map(move |col| Pair(row, col)))
+            .filter(|&position| position != current && total(position) <= total(current))
+            .collect();
+        candidates.sort_unstable_by_key(|&position| (total(position), position.x(), position.y()));
+        candidates.truncate(STEINER_SLIDE_CANDIDATES);
+        candidates
+            .into_iter()
+            .map(|position| position.with(branching.point.lay()))
+            .filter(|&point| self.in_bounds(point))
+            .collect()
+    }
+
+    /// Slides the branch points of a net within their feasible rectangles:
+    /// a branch point and its branches are replaced by a new point connected to the same anchors
+    /// whenever the net then passes fewer full GGrids, or as many but fewer GGrids in total.
+    /// New GGrids must have room, and the net stays connected.
+    /// The net's demand is updated once, when it is given its new routes.
+    /// Returns the number of GGrids saved.
+    pub fn relocate_steiner_points(&mut self, id: usize) -> Result<usize> {
+        let pin_points = self.pin_points();
+        let (pins, min_layer) = match self.nets.get(id) {
+            Some(net) => (
+                net.pins
+                    .iter()
+                    .map(|&pin| pin_points[pin])
+                    .collect::<HashSet<_>>(),
+                net.min_layer,
+            ),
+            None => return Ok(0),
+        };
+
+        let old = self.rip_up(id)?;
+        let mut points: HashSet<_> = old.iter().flat_map(Route::points).collect();
+        points.extend(pins.iter().copied());
+        let before = points.len();
+
+        let to_net = |chip: &Self, points: &HashSet<Point<usize>>| Net {
+            id,
+            min_layer,
+            pins: chip.nets[id].pins.clone(),
+            routes: chip.points_to_routes(points),
+        };
+        // GGrids that would overflow, then GGrids in total: the smaller, the better.
+        let key = |chip: &Self, points: &HashSet<Point<usize>>| {
+            let full = points
+                .iter()
+                .filter(|&&point| !chip.has_room(point))
+                .count();
+            (full, points.len())
+        };
+
+        let mut changed = false;
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for branching in self.branchings(&points, &pins) {
+                let mut rest = points.clone();
+                rest.remove(&branching.point);
+                for point in branching.interior.iter() {
+                    rest.remove(point);
+                }
+
+                for candidate in self.slide_candidates(&branching) {
+                    if !rest.contains(&candidate) && !self.has_room(candidate) {
+                        continue;
+                    }
+
+                    let mut moved = rest.clone();
+                    moved.insert(candidate);
+                    let connected = branching.anchors.iter().all(|&anchor| {
+                        let allowed =
+                            |point: Point<usize>| moved.contains(&point) || self.has_room(point);
+                        let path = self
+                            .pattern_path(candidate, anchor, min_layer, allowed)
+                            .or_else(|| self.search(candidate, anchor, min_layer, allowed));
+                        match path {
+                            Some(path) => {
+                                moved.extend(path);
+                                true
+                            }
+                            None => false,
+                        }
+                    });
+                    if !connected {
+                        continue;
+                    }
+                    self.prune(&mut moved, &pins);
+
+                    if key(self, &moved) < key(self, &points)
+                        && self.is_connected(&to_net(self, &moved), &pin_points)
+                    {
+                        points = moved;
+                        improved = true;
+                        changed = true;
+                        break;
+                    }
+                }
+
+                if improved {
+                    break;
+                }
+            }
+        }
+
+        if changed {
+            let routes = self.points_to_routes(&points);
+            self.commit_routes(id, routes)?;
+            Ok(before.saturating_sub(points.len()))
+        } else {
+            self.commit_routes(id, old)?;
+            Ok(0)
+        }
+    }
+
+    /// Runs `relocate_steiner_points` on every net.
+    /// Returns the number of GGrids saved.
+    pub fn relocate_all_steiner_points(&mut self) -> Result<usize> {
+        let mut saved = 0;
+        for id in 0..self.nets.len() {
+            saved += self.relocate_steiner_points(id)?;
+        }
+        Ok(saved)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    chip::Chip,
+    components::{Point, Route},
+    metrics::Counters,
+    router::Congestion,
+};
+use anyhow::Result;
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+impl Chip {
+    /// Whether a GGrid can take one more net while overflowing by at most `bound`.
+    pub fn has_soft_room(&self, point: Point<usize>, bound: usize) -> bool {
+        if !self.in_bounds(point) {
+            return false;
+        }
+        let Point(row, col, lay) = point;
+        self.get_layer(lay - 1).is_some_and(|layer| {
+            match (
+                layer.get_capacity(row - 1, col - 1),
+                layer.get_demand(row - 1, col - 1),
+            ) {
+                (Some(&capacity), Some(&demand)) => demand < capacity.saturating_add(bound),
+                _ => false,
+            }
+        })
+    }
+
+    /// Routes a net through GGrids overflowing by at most `bound`,
+    /// paying for the overflow it causes at the `present` weight, see `step_cost`.
+    /// The net's own demand should be ripped up first.
+    pub fn route_net_soft(
+        &self,
+        id: usize,
+        bound: usize,
+        present: usize,
+    ) -> Option<HashSet<Route<usize>>> {
+        let pin_grids = self.pin_access_grids(id);
+        let min_layer = self.nets.get(id)?.min_layer;
+        self.route_topology(id, |source, target, used| {
+            // Steps along the net itself are free, so no heuristic is admissible.
+            self.cheapest_path(source, target, 0, min_layer, |parent, from, to| {
+                if used.contains(&to) {
+                    return Some(0);
+                }
+                if !pin_grids.contains(&to.flatten()) && !self.has_soft_room(to, bound) {
+                    return None;
+                }
+                self.step_cost(parent, from, to, present)
+            })
+        })
+    }
+
+    /// Routes every net in the configured order letting GGrids overflow by at most `bound`,
+    /// so nets are rarely left unconnected for lack of room.
+    /// The price of overflow grows as the pass goes on, by `SOFT_PRESENT_RAMP` in total,
+    /// so later nets avoid the GGrids earlier ones filled.
+    /// A new route is only kept if the old one is disconnected or longer.
+    /// The overflow left is recorded in the congestion history for `negotiate` to repair.
+    /// Returns the number of nets rerouted.
+    pub fn soft_route_nets(
+        &mut self,
+        bound: usize,
+        start: Instant,
+        duration: Duration,
+    ) -> Result<usize> {
+        use crate::consts::SOFT_PRESENT_RAMP;
+
+        let order = self.routing_order();
+        let total = order.len().max(1);
+
+        let mut rerouted = 0;
+        for (done, id) in order.into_iter().enumerate() {
+            if Self::check_time(start, duration).is_err() {
+                break;
+            }
+
+            let pin_points = self.pin_points();
+            if !self.pin_access_exempt
+                && !self
+                    .unreachable_pins(&self.nets[id], &pin_points)
+                    .is_empty()
+            {
+                continue;
+            }
+
+            let connected = self.is_connected(&self.nets[id], &pin_points)
+                && Self::below_min_layer(&self.nets[id]).is_empty()
+                && self.wrong_direction(&self.nets[id]).is_empty();
+            let length = self.net_length(&self.nets[id]);
+
+            let present = Congestion::UNIT + SOFT_PRESENT_RAMP * done / total;
+            let old = self.rip_up(id)?;
+            let routes = match self.route_net_soft(id, bound, present) {
+                Some(routes) => routes,
+                None => {
+                    Counters::bump(&self.counters.failed_routes);
+                    self.commit_routes(id, old)?;
+                    continue;
+                }
+            };
+
+            self.commit_routes(id, routes)?;
+            self.dirty_nets.remove(&id);
+            if connected && self.net_length(&self.nets[id]) > length {
+                self.rip_up(id)?;
+                self.commit_routes(id, old)?;
+            } else {
+                rerouted += 1;
+                Counters::bump(&self.counters.nets_rerouted);
+            }
+        }
+        self.age_congestion();
+
+        Ok(rerouted)
+    }
+}
@@ -0,0 +1,20 @@
+use crate::{chip::Chip, components::Point};
+
+impl Chip {
+    /// The vias a pin below a net's min layer needs to reach the lowest layer
+    /// the net may run planar on, from the pin's GGrid up to that layer.
+    /// Every GGrid of the stack takes demand once the net is committed.
+    /// Returns `None` if the pin is already on a routable layer.
+    pub fn via_stack(&self, pin: Point<usize>, min_layer: usize) -> Option<Vec<Point<usize>>> {
+        let top = (min_layer + 1).min(self.layers.len());
+        if pin.lay() >= top {
+            return None;
+        }
+
+        Some(
+            (pin.lay()..=top)
+                .map(|lay| pin.flatten().with(lay))
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,239 @@
+use crate::{chip::Chip, components::Pair};
+use std::collections::{HashMap, HashSet};
+
+/// A rectilinear Steiner tree of a net.
+/// The first points are the net's pins in order, the rest are Steiner points.
+/// Every edge is a 2-pin connection for the router,
+/// and edges are ordered so that one end is always connected before.
+#[derive(Clone, Debug, Default)]
+pub struct Topology {
+    /// pins followed by Steiner points
+    pub points: Vec<Pair<usize>>,
+    /// number of pins at the front of `points`
+    pub num_pins: usize,
+    /// indices into `points`, the first one of every edge is already connected
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl Topology {
+    /// Builds a tree over `pins` with the iterated 1-Steiner heuristic.
+    /// Hanan grid points are added one at a time while they shorten the spanning tree,
+    /// then Steiner points that became leaves are dropped.
+    /// Large nets only get a spanning tree, very large ones a clustered one, see `clustered`.
+    pub fn new(pins: &[Pair<usize>]) -> Self {
+        use crate::consts::{CLUSTER_MIN_PINS, STEINER_MAX_PINS};
+
+        if pins.len() >= CLUSTER_MIN_PINS {
+            return Self::clustered(pins);
+        }
+
+        let mut points = pins.to_vec();
+
+        if pins.len() > 2 && pins.len() <= STEINER_MAX_PINS {
+            let rows: HashSet<_> = pins.iter().map(Pair::x).collect();
+            let cols: HashSet<_> = pins.iter().map(Pair::y).collect();
+            let taken: HashSet<_> = pins.iter().copied().collect();
+            let mut hanan: Vec<_> = rows
+                .iter()
+                .flat_map(|&row| cols.iter().map(move |&col| Pair(row, col)))
+                .filter(|point| !taken.contains(point))
+                .collect();
+
+            let mut cost = tree_cost(&points, &spanning_tree(&points));
+            loop {
+                let best = hanan
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, &candidate)| {
+                        points.push(candidate);
+                        let cost = tree_cost(&points, &spanning_tree(&points));
+                        points.pop();
+                        (cost, idx)
+                    })
+                    .min();
+
+                match best {
+                    Some((new_cost, idx)) if new_cost < cost => {
+                        cost = new_cost;
+                        points.push(hanan.swap_remove(idx));
+                    }
+                    _ => break,
+                }
+            }
+
+            // Steiner points with at most two neighbors don't shorten the tree.
+            loop {
+                let edges = spanning_tree(&points);
+                let mut degree = vec![0; points.len()];
+                for &(a, b) in edges.iter() {
+                    degree[a] += 1;
+                    degree[b] += 1;
+                }
+                match (pins.len()..points.len()).find(|&idx| degree[idx] <= 2) {
+                    Some(idx) => {
+                        points.remove(idx);
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        let edges = spanning_tree(&points);
+        Self {
+            points,
+            num_pins: pins.len(),
+            edges,
+        }
+    }
+
+    /// Builds a tree over a net with many pins in two levels, see `clusters`:
+    /// a trunk spanning the centers of the clusters, then a spanning tree inside every cluster
+    /// grown from its center. The center of a cluster is its pin nearest to its median.
+    /// Spanning only small sets of pins keeps the time far below one tree over all of them.
+    pub fn clustered(pins: &[Pair<usize>]) -> Self {
+        let clusters = clusters(pins);
+        let centers: Vec<_> = clusters
+            .iter()
+            .map(|members| {
+                let median = |key: fn(&Pair<usize>) -> usize| {
+                    let mut values: Vec<_> = members.iter().map(|&idx| key(&pins[idx])).collect();
+                    values.sort_unstable();
+                    values[values.len() / 2]
+                };
+                let median = Pair(median(Pair::x), median(Pair::y));
+                members
+                    .iter()
+                    .copied()
+                    .min_by_key(|&idx| (pins[idx].distance(&median), idx))
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        let trunk: Vec<_> = centers.iter().map(|&idx| pins[idx]).collect();
+        let mut edges: Vec<_> = spanning_tree(&trunk)
+            .into_iter()
+            .map(|(a, b)| (centers[a], centers[b]))
+            .collect();
+        for (members, &center) in clusters.iter().zip(centers.iter()) {
+            let mut order = vec![center];
+            order.extend(members.iter().copied().filter(|&idx| idx != center));
+            let points: Vec<_> = order.iter().map(|&idx| pins[idx]).collect();
+            edges.extend(
+                spanning_tree(&points)
+                    .into_iter()
+                    .map(|(a, b)| (order[a], order[b])),
+            );
+        }
+
+        Self {
+            points: pins.to_vec(),
+            num_pins: pins.len(),
+            edges,
+        }
+    }
+
+    /// Total Manhattan length of the edges.
+    pub fn length(&self) -> usize {
+        tree_cost(&self.points, &self.edges)
+    }
+}
+
+/// Edges of a minimum spanning tree (Manhattan distance) over `points`, using Prim's algorithm.
+/// The tree grows from the first point, so the first end of every edge is already in the tree.
+pub(crate) fn spanning_tree(points: &[Pair<usize>]) -> Vec<(usize, usize)> {
+    let mut edges = Vec::new();
+    if points.is_empty() {
+        return edges;
+    }
+
+    let mut in_tree = vec![false; points.len()];
+    let mut best: Vec<_> = points
+        .iter()
+        .map(|point| (point.distance(&points[0]), 0))
+        .collect();
+    in_tree[0] = true;
+
+    for _ in 1..points.len() {
+        let next = match (0..points.len())
+            .filter(|&idx| !in_tree[idx])
+            .min_by_key(|&idx| best[idx].0)
+        {
+            Some(next) => next,
+            None => break,
+        };
+        in_tree[next] = true;
+        edges.push((best[next].1, next));
+
+        for idx in 0..points.len() {
+            let dist = points[idx].distance(&points[next]);
+            if !in_tree[idx] && dist < best[idx].0 {
+                best[idx] = (dist, next);
+            }
+        }
+    }
+
+    edges
+}
+
+/// Groups nearby pins: the bounding box is cut into a grid of about `sqrt(n)` cells
+/// holding about `sqrt(n)` pins each, and the pins of every non-empty cell form a cluster.
+/// Clusters are ordered so the one of the first pin comes first.
+fn clusters(pins: &[Pair<usize>]) -> Vec<Vec<usize>> {
+    let side = |n: usize| (1..).find(|&side| side * side >= n).unwrap_or(1);
+    let cells = side(side(pins.len()));
+
+    let (rlo, rhi) = (
+        pins.iter().map(Pair::x).min().unwrap_or(0),
+        pins.iter().map(Pair::x).max().unwrap_or(0),
+    );
+    let (clo, chi) = (
+        pins.iter().map(Pair::y).min().unwrap_or(0),
+        pins.iter().map(Pair::y).max().unwrap_or(0),
+    );
+    let (height, width) = ((rhi - rlo) / cells + 1, (chi - clo) / cells + 1);
+
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+    let mut index = HashMap::new();
+    for (idx, &Pair(row, col)) in pins.iter().enumerate() {
+        let cell = ((row - rlo) / height, (col - clo) / width);
+        let slot = *index.entry(cell).or_insert_with(|| {
+            clusters.push(Vec::new());
+            clusters.len() - 1
+        });
+        clusters[slot].push(idx);
+    }
+    clusters
+}
+
+fn tree_cost(points: &[Pair<usize>], edges: &[(usize, usize)]) -> usize {
+    edges
+        .iter()
+        .map(|&(a, b)| points[a].distance(&points[b]))
+        .sum()
+}
+
+impl Chip {
+    /// The Steiner topology of a net over the GGrids of its pins.
+    /// Small nets are looked up in the chip's `SteinerTable`,
+    /// larger ones use the iterated 1-Steiner heuristic.
+    pub fn steiner_topology(&self, id: usize) -> Topology {
+        use crate::consts::FLUTE_MAX_PINS;
+
+        let net = match self.nets.get(id) {
+            Some(net) => net,
+            None => return Topology::default(),
+        };
+        let pins: Vec<_> = net
+            .pins
+            .iter()
+            .filter_map(|&pin| self.pin_point(pin))
+            .map(|point| point.flatten())
+            .collect();
+
+        if pins.len() <= FLUTE_MAX_PINS {
+            self.steiner_table.topology(&pins)
+        } else {
+            Topology::new(&pins)
+        }
+    }
+}
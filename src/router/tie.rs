@@ -0,0 +1,119 @@
+use crate::components::{Pair, Point};
+use anyhow::{anyhow, Error, Result};
+use std::str::FromStr;
+
+/// One way to choose between options that are otherwise equally good.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TieRule {
+    /// Searches prefer GGrids on lower layers.
+    LowerLayer,
+    /// Searches prefer paths with fewer changes of direction.
+    FewerTurns,
+    /// Searches prefer steps that keep the direction of the previous step.
+    Straighter,
+    /// Cells prefer positions closer to their origin.
+    Nearest,
+}
+
+/// Number of rules a tie key has room for, one per `TieRule`.
+pub const TIE_RULES: usize = 4;
+
+/// Key comparing equally good options, lower is preferred.
+pub type TieKey = [usize; TIE_RULES];
+
+/// Tie-breaking rules applied in order, each only deciding ties left by the ones before.
+/// Ties left by all of them fall back to the fixed order of the search or the candidates,
+/// so every choice is deterministic. No rules keep that order alone.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct TieBreak {
+    pub rules: Vec<TieRule>,
+}
+
+/// Axis a step between adjacent GGrids runs along: 0 for rows, 1 for columns, 2 for layers.
+pub(crate) fn axis(a: Point<usize>, b: Point<usize>) -> usize {
+    if a.row() != b.row() {
+        0
+    } else if a.col() != b.col() {
+        1
+    } else {
+        2
+    }
+}
+
+/// Whether the step from `from` to `to` changes the direction of the one from `parent`.
+/// The first step of a path, where `parent == from`, never turns.
+pub(crate) fn turns(parent: Point<usize>, from: Point<usize>, to: Point<usize>) -> usize {
+    (parent != from && axis(parent, from) != axis(from, to)) as usize
+}
+
+impl TieBreak {
+    /// Checks if no rules are set.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Tie key of a search step from `from`, entered from `parent`, to `to`,
+    /// where the path up to `from` turned `turned` times.
+    pub fn step_key(
+        &self,
+        parent: Point<usize>,
+        from: Point<usize>,
+        to: Point<usize>,
+        turned: usize,
+    ) -> TieKey {
+        let mut key = TieKey::default();
+        let values = self.rules.iter().filter_map(|rule| match rule {
+            TieRule::LowerLayer => Some(to.lay()),
+            TieRule::FewerTurns => Some(turned + turns(parent, from, to)),
+            TieRule::Straighter => Some(turns(parent, from, to)),
+            TieRule::Nearest => None,
+        });
+        for (slot, value) in key.iter_mut().zip(values) {
+            *slot = value;
+        }
+        key
+    }
+
+    /// Tie key of moving a cell from `origin` to `target`.
+    pub fn candidate_key(&self, origin: Pair<usize>, target: Pair<usize>) -> TieKey {
+        let mut key = TieKey::default();
+        let values = self.rules.iter().filter_map(|rule| match rule {
+            TieRule::Nearest => Some(origin.distance(&target)),
+            _ => None,
+        });
+        for (slot, value) in key.iter_mut().zip(values) {
+            *slot = value;
+        }
+        key
+    }
+}
+
+impl FromStr for TieRule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "lower-layer" => Ok(TieRule::LowerLayer),
+            "fewer-turns" => Ok(TieRule::FewerTurns),
+            "straight" => Ok(TieRule::Straighter),
+            "nearest" => Ok(TieRule::Nearest),
+            _ => Err(anyhow!("Unknown tie-breaking rule: {}", s)),
+        }
+    }
+}
+
+impl FromStr for TieBreak {
+    type Err = Error;
+
+    /// Parses rules separated by commas, e.g. `fewer-turns,lower-layer`.
+    fn from_str(s: &str) -> Result<Self> {
+        let rules = s
+            .split(',')
+            .map(str::trim)
+            .filter(|rule| !rule.is_empty())
+            .map(TieRule::from_str)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { rules })
+    }
+}
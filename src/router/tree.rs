@@ -0,0 +1,123 @@
+use super::path_to_routes;
+use crate::{
+    chip::Chip,
+    components::{Point, Route},
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+impl Chip {
+    /// Breadth-first search from every GGrid of `sources` at once
+    /// until any GGrid of `sinks` is reached,
+    /// only visiting GGrids for which `allowed` returns true
+    /// and never running planar below `min_layer`. Sinks can always be entered.
+    /// Returns the path from a source to the sink reached, including both ends.
+    pub fn grow_tree<F>(
+        &self,
+        sources: &HashSet<Point<usize>>,
+        sinks: &HashSet<Point<usize>>,
+        min_layer: usize,
+        allowed: F,
+    ) -> Option<Vec<Point<usize>>>
+    where
+        F: Fn(Point<usize>) -> bool,
+    {
+        let mut parents = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        for &source in sources.iter() {
+            parents.insert(source, source);
+            queue.push_back(source);
+        }
+
+        while let Some(point) = queue.pop_front() {
+            if sinks.contains(&point) {
+                let mut path = vec![point];
+                let mut current = point;
+                while parents[&current] != current {
+                    current = parents[&current];
+                    path.push(current);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for next in self.neighbors_above(point, min_layer) {
+                if parents.contains_key(&next) || !(sinks.contains(&next) || allowed(next)) {
+                    continue;
+                }
+                parents.insert(next, point);
+                queue.push_back(next);
+            }
+        }
+
+        None
+    }
+
+    /// Routes a net by growing the tree of `routes` connected to its first pin
+    /// towards the remaining pins, one wavefront from the whole tree at a time.
+    /// Routes not connected to the first pin, running below the min layer
+    /// or against their layer's direction are dropped.
+    /// GGrids without remaining capacity are avoided,
+    /// so the net's own demand should be ripped up first.
+    /// Returns `None` if some pins cannot be connected.
+    pub fn route_net_tree(
+        &self,
+        id: usize,
+        routes: &HashSet<Route<usize>>,
+    ) -> Option<HashSet<Route<usize>>> {
+        let net = self.nets.get(id)?;
+        let pin_points = self.pin_points();
+        let pin_grids = self.pin_access_grids(id);
+        let pins: HashSet<_> = net.pins.iter().map(|&pin| pin_points[pin]).collect();
+        let root = pin_points[*net.pins.first()?];
+
+        // GGrids adjacent along the existing routes.
+        let mut adjacent: HashMap<Point<usize>, Vec<Point<usize>>> = HashMap::new();
+        for route in routes
+            .iter()
+            .filter(|route| !net.is_below_min_layer(route) && !self.is_wrong_direction(route))
+        {
+            for pair in route.points().windows(2) {
+                adjacent.entry(pair[0]).or_default().push(pair[1]);
+                adjacent.entry(pair[1]).or_default().push(pair[0]);
+            }
+        }
+
+        let mut tree = HashSet::new();
+        let mut queue = VecDeque::new();
+        tree.insert(root);
+        queue.push_back(root);
+        while let Some(point) = queue.pop_front() {
+            for &next in adjacent.get(&point).into_iter().flatten() {
+                if tree.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let mut grown: HashSet<_> = routes
+            .iter()
+            .filter(|route| {
+                !net.is_below_min_layer(route)
+                    && !self.is_wrong_direction(route)
+                    && tree.contains(&route.source())
+            })
+            .copied()
+            .collect();
+
+        let mut sinks: HashSet<_> = pins.difference(&tree).copied().collect();
+        while !sinks.is_empty() {
+            let allowed =
+                |point: Point<usize>| pin_grids.contains(&point.flatten()) || self.has_room(point);
+            let path = self.grow_tree(&tree, &sinks, net.min_layer, allowed)?;
+
+            for point in path.iter() {
+                sinks.remove(point);
+            }
+            tree.extend(path.iter().copied());
+            grown.extend(path_to_routes(&path));
+        }
+
+        Some(grown)
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{
+    chip::Chip,
+    components::{Direction, Pair, Point, Route},
+    router::{path_to_routes, walk},
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+impl Chip {
+    /// Remaining supply of a GGrid summed over all layers of a direction,
+    /// negative if overflowed.
+    pub fn slack_2d(&self, position: Pair<usize>, direction: Direction) -> isize {
+        let Pair(row, col) = position;
+        self.layers
+            .iter()
+            .filter(|layer| layer.direction == direction)
+            .map(|layer| {
+                let capacity = layer.get_capacity(row - 1, col - 1).copied().unwrap_or(0);
+                let demand = layer.get_demand(row - 1, col - 1).copied().unwrap_or(0);
+                capacity as isize - demand as isize
+            })
+            .sum()
+    }
+
+    /// Breadth-first search on the 2D grid, where layers are compressed by direction.
+    /// A horizontal step needs horizontal slack on the GGrid it enters,
+    /// a vertical step needs vertical slack. GGrids in `free` can always be entered.
+    fn bfs_2d(
+        &self,
+        source: Pair<usize>,
+        target: Pair<usize>,
+        free: &HashSet<Pair<usize>>,
+    ) -> Option<Vec<Pair<usize>>> {
+        let mut parents = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        parents.insert(source, source);
+        queue.push_back(source);
+
+        while let Some(position) = queue.pop_front() {
+            if position == target {
+                let mut path = vec![position];
+                let mut current = position;
+                while current != source {
+                    current = parents[&current];
+                    path.push(current);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let Pair(row, col) = position;
+            let steps = [
+                (Pair(row, col + 1), Direction::Horizontal),
+                (Pair(row, col.wrapping_sub(1)), Direction::Horizontal),
+                (Pair(row + 1, col), Direction::Vertical),
+                (Pair(row.wrapping_sub(1), col), Direction::Vertical),
+            ];
+            for (next, direction) in steps {
+                if !Chip::in_grid(self.dim, next) {
+                    continue;
+                }
+                if parents.contains_key(&next) {
+                    continue;
+                }
+                if next != target && !free.contains(&next) && self.slack_2d(next, direction) <= 0 {
+                    continue;
+                }
+                parents.insert(next, position);
+                queue.push_back(next);
+            }
+        }
+
+        None
+    }
+
+    /// Assigns a 2D path to layers, starting from `source` on its own layer.
+    /// Every straight run goes on the layer of its direction, at or above `min_layer`,
+    /// that overflows the fewest GGrids, preferring layers closer to the previous run.
+    /// Turns and both ends are connected with vias.
+    /// Returns the 3D path, which ends at `target_layer` if given.
+    fn assign_layers(
+        &self,
+        source: Point<usize>,
+        path: &[Pair<usize>],
+        min_layer: usize,
+        target_layer: Option<usize>,
+    ) -> Option<Vec<Point<usize>>> {
+        let mut points = vec![source];
+
+        let mut start = 0;
+        while start + 1 < path.len() {
+            let direction = if path[start].x() == path[start + 1].x() {
+                Direction::Horizontal
+            } else {
+                Direction::Vertical
+            };
+            let mut end = start + 1;
+            while end + 1 < path.len()
+                && (path[end].x() == path[end + 1].x()) == (direction == Direction::Horizontal)
+            {
+                end += 1;
+            }
+
+            let current = points.last()?.lay();
+            let run = &path[start..=end];
+            let lay = self
+                .layers
+                .iter()
+                .filter(|layer| layer.direction == direction && layer.id >= min_layer)
+                .map(|layer| {
+                    let overflows = run
+                        .iter()
+                        .filter(|&&Pair(row, col)| {
+                            let capacity = layer.get_capacity(row - 1, col - 1).copied();
+                            let demand = layer.get_demand(row - 1, col - 1).copied();
+                            capacity <= demand
+                        })
+                        .count();
+                    (overflows, (layer.id + 1).abs_diff(current), layer.id + 1)
+                })
+                .min()
+                .map(|(_, _, lay)| lay)?;
+
+            let Pair(row, col) = path[start];
+            walk(&mut points, Point(row, col, lay));
+            let Pair(row, col) = path[end];
+            walk(&mut points, Point(row, col, lay));
+            start = end;
+        }
+
+        if let Some(lay) = target_layer {
+            let Point(row, col, _) = *points.last()?;
+            walk(&mut points, Point(row, col, lay));
+        }
+
+        Some(points)
+    }
+
+    /// Routes a net in two phases: every edge of its Steiner topology is routed
+    /// on the 2D grid first, then the path is assigned to layers.
+    /// The net's own demand should be ripped up first.
+    /// Returns `None` if some edge cannot be connected.
+    pub fn route_net_two_phase(&self, id: usize) -> Option<HashSet<Route<usize>>> {
+        let net = self.nets.get(id)?;
+        let pin_points = self.pin_points();
+        let topology = self.steiner_topology(id);
+
+        let mut points: Vec<Option<Point<usize>>> = vec![None; topology.points.len()];
+        let mut target_layers = vec![None; topology.points.len()];
+        for ((point, layer), &pin) in points
+            .iter_mut()
+            .zip(target_layers.iter_mut())
+            .zip(net.pins.iter())
+        {
+            *point = Some(pin_points[pin]);
+            *layer = Some(pin_points[pin].lay());
+        }
+
+        // GGrids the net already occupies cost nothing more.
+        let mut free: HashSet<_> = points.iter().flatten().map(Point::flatten).collect();
+
+        let mut routes = HashSet::new();
+        for &(from, to) in topology.edges.iter() {
+            let source = points[from]?;
+            let path = self.bfs_2d(source.flatten(), topology.points[to], &free)?;
+            let path = self.assign_layers(source, &path, net.min_layer, target_layers[to])?;
+
+            points[to] = path.last().copied();
+            free.extend(path.iter().map(Point::flatten));
+            routes.extend(path_to_routes(&path));
+        }
+
+        Some(routes)
+    }
+}
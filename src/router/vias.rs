@@ -0,0 +1,182 @@
+use crate::{
+    chip::Chip,
+    components::{Direction, Net, Point, Route},
+    router::path_to_routes,
+};
+use anyhow::Result;
+use std::collections::HashSet;
+
+impl Chip {
+    /// Removes GGrids of a net that lead nowhere:
+    /// points other than pins with at most one neighbor in the net are dropped until none is left.
+    pub(crate) fn prune(&self, points: &mut HashSet<Point<usize>>, pins: &HashSet<Point<usize>>) {
+        loop {
+            let leaves: Vec<_> = points
+                .iter()
+                .copied()
+                .filter(|point| !pins.contains(point))
+                .filter(|&point| {
+                    self.neighbors(point)
+                        .into_iter()
+                        .filter(|next| points.contains(next))
+                        .count()
+                        <= 1
+                })
+                .collect();
+
+            if leaves.is_empty() {
+                return;
+            }
+            for leaf in leaves {
+                points.remove(&leaf);
+            }
+        }
+    }
+
+    /// Segments connecting every pair of neighboring GGrids in `points`.
+    pub(crate) fn points_to_routes(&self, points: &HashSet<Point<usize>>) -> HashSet<Route<usize>> {
+        points
+            .iter()
+            .flat_map(|&point| {
+                self.neighbors(point)
+                    .into_iter()
+                    .filter(|next| points.contains(next))
+                    .flat_map(move |next| path_to_routes(&[point, next]))
+            })
+            .collect()
+    }
+
+    /// Straight planar runs of a net, as lists of at least two GGrids.
+    fn planar_runs(&self, points: &HashSet<Point<usize>>) -> Vec<Vec<Point<usize>>> {
+        let mut runs = Vec::new();
+        for &point in points.iter() {
+            let Point(row, col, lay) = point;
+            let step = match self.get_layer(lay - 1).map(|layer| layer.direction) {
+                Some(Direction::Horizontal) => |Point(r, c, l): Point<usize>| Point(r, c + 1, l),
+                Some(Direction::Vertical) => |Point(r, c, l): Point<usize>| Point(r + 1, c, l),
+                None => continue,
+            };
+
+            // Only start from the lowest end of a run.
+            let before = match self.get_layer(lay - 1).map(|layer| layer.direction) {
+                Some(Direction::Horizontal) => col.checked_sub(1).map(|c| Point(row, c, lay)),
+                _ => row.checked_sub(1).map(|r| Point(r, col, lay)),
+            };
+            if before.is_some_and(|before| points.contains(&before)) {
+                continue;
+            }
+
+            let mut run = vec![point];
+            while let Some(&last) = run.last() {
+                let next = step(last);
+                if !points.contains(&next) {
+                    break;
+                }
+                run.push(next);
+            }
+            if run.len() > 1 {
+                runs.push(run);
+            }
+        }
+        runs
+    }
+
+    /// Reduces the GGrids a net occupies by dropping dead ends
+    /// and moving straight runs to other layers of the same direction
+    /// when that saves vias. Moved runs stay at or above the net's min layer,
+    /// only enter GGrids with room, and keep the net connected.
+    /// Returns the number of GGrids saved.
+    pub fn minimize_vias(&mut self, id: usize) -> Result<usize> {
+        let pin_points = self.pin_points();
+        let (pins, min_layer) = match self.nets.get(id) {
+            Some(net) => (
+                net.pins
+                    .iter()
+                    .map(|&pin| pin_points[pin])
+                    .collect::<HashSet<_>>(),
+                net.min_layer,
+            ),
+            None => return Ok(0),
+        };
+
+        let old = self.rip_up(id)?;
+        let mut points: HashSet<_> = old.iter().flat_map(Route::points).collect();
+        points.extend(pins.iter().copied());
+        let before = points.len();
+        self.prune(&mut points, &pins);
+
+        let connected = |chip: &Self, points: &HashSet<Point<usize>>| {
+            let net = Net {
+                id,
+                min_layer,
+                pins: chip.nets[id].pins.clone(),
+                routes: chip.points_to_routes(points),
+            };
+            chip.is_connected(&net, &pin_points)
+        };
+
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for run in self.planar_runs(&points) {
+                let lay = run[0].lay();
+                let direction = match self.get_layer(lay - 1) {
+                    Some(layer) => layer.direction,
+                    None => continue,
+                };
+
+                for layer in self.layers.iter() {
+                    let target = layer.id + 1;
+                    if layer.direction != direction || layer.id < min_layer || target == lay {
+                        continue;
+                    }
+
+                    let mut moved = points.clone();
+                    for point in run.iter() {
+                        moved.remove(point);
+                    }
+                    moved.extend(run.iter().map(|point| point.flatten().with(target)));
+                    for end in [run[0], run[run.len() - 1]] {
+                        let (lo, hi) = (lay.min(target), lay.max(target));
+                        moved.extend((lo..=hi).map(|l| end.flatten().with(l)));
+                    }
+                    self.prune(&mut moved, &pins);
+
+                    if moved.len() < points.len()
+                        && moved
+                            .iter()
+                            .all(|point| points.contains(point) || self.has_room(*point))
+                        && connected(self, &moved)
+                    {
+                        points = moved;
+                        improved = true;
+                        break;
+                    }
+                }
+
+                if improved {
+                    break;
+                }
+            }
+        }
+
+        if points.len() < before && connected(self, &points) {
+            let routes = self.points_to_routes(&points);
+            self.commit_routes(id, routes)?;
+            Ok(before - points.len())
+        } else {
+            self.commit_routes(id, old)?;
+            Ok(0)
+        }
+    }
+
+    /// Runs `minimize_vias` on every net.
+    /// Returns the number of GGrids saved.
+    pub fn minimize_all_vias(&mut self) -> Result<usize> {
+        let mut saved = 0;
+        for id in 0..self.nets.len() {
+            saved += self.minimize_vias(id)?;
+        }
+        Ok(saved)
+    }
+}
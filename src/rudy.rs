@@ -0,0 +1,103 @@
+use crate::{chip::Chip, components::Pair, router::Congestion};
+
+/// Routing demand predicted for every GGrid before the nets are routed,
+/// the Rectangular Uniform wire DensitY of their bounding boxes:
+/// the wire a net needs to span its box is spread evenly over the box.
+/// Estimates are in thousandths of a track, summed over the nets added.
+#[derive(Clone, Debug, Default)]
+pub struct RudyMap {
+    /// dimensions
+    pub dim: Pair<usize>,
+    /// predicted demand of every GGrid
+    pub estimates: Vec<usize>,
+}
+
+/// Supply held back for the nets not routed yet in a routing pass.
+/// Of the supply left on a GGrid, a net may only take what is not reserved:
+/// `fraction` of it, scaled by the predicted demand of the GGrid up to one track.
+#[derive(Clone, Debug, Default)]
+pub struct Reservation {
+    /// thousandths of the remaining supply reserved where one full track is predicted
+    pub fraction: usize,
+    /// predicted demand of the nets not routed yet
+    pub rudy: RudyMap,
+}
+
+impl Chip {
+    /// Predicts the demand of the nets `ids`.
+    pub fn rudy_map(&self, ids: impl IntoIterator<Item = usize>) -> RudyMap {
+        let mut rudy = RudyMap {
+            dim: self.dim,
+            estimates: vec![0; self.dim.size()],
+        };
+        for id in ids {
+            if let Some(bbox) = self.bounding_box(id) {
+                rudy.add(bbox);
+            }
+        }
+        rudy
+    }
+
+    /// Starts reserving `fraction` thousandths of the supply for the nets `ids`,
+    /// see `Reservation`.
+    pub fn reserve_for(&mut self, fraction: usize, ids: impl IntoIterator<Item = usize>) {
+        self.reservation = Some(Reservation {
+            fraction: fraction.min(Congestion::UNIT),
+            rudy: self.rudy_map(ids),
+        });
+    }
+
+    /// Stops reserving supply for a net, before it is routed.
+    pub fn release_reservation(&mut self, id: usize) {
+        let bbox = self.bounding_box(id);
+        if let (Some(reservation), Some(bbox)) = (self.reservation.as_mut(), bbox) {
+            reservation.rudy.remove(bbox);
+        }
+    }
+
+    /// Units of supply reserved on a GGrid with `remaining` supply left.
+    pub fn reserved(&self, position: Pair<usize>, remaining: usize) -> usize {
+        self.reservation.as_ref().map_or(0, |reservation| {
+            let predicted = reservation.rudy.estimate(position).min(Congestion::UNIT);
+            remaining * predicted * reservation.fraction / (Congestion::UNIT * Congestion::UNIT)
+        })
+    }
+}
+
+impl RudyMap {
+    /// Predicted demand of a GGrid, in thousandths of a track.
+    pub fn estimate(&self, position: Pair<usize>) -> usize {
+        Chip::grid_index(self.dim, position).map_or(0, |idx| self.estimates[idx])
+    }
+
+    /// Adds the demand of a net spanning the bounding box from `low` to `high`.
+    pub fn add(&mut self, (low, high): (Pair<usize>, Pair<usize>)) {
+        let density = Self::density(low, high);
+        for idx in self.indices(low, high) {
+            self.estimates[idx] += density;
+        }
+    }
+
+    /// Removes the demand of a net added with `add`.
+    pub fn remove(&mut self, (low, high): (Pair<usize>, Pair<usize>)) {
+        let density = Self::density(low, high);
+        for idx in self.indices(low, high) {
+            self.estimates[idx] = self.estimates[idx].saturating_sub(density);
+        }
+    }
+
+    /// Demand of every GGrid of a box, in thousandths of a track:
+    /// a net crossing a box of `h` by `w` GGrids needs about `h + w - 1` of them.
+    fn density(Pair(r0, c0): Pair<usize>, Pair(r1, c1): Pair<usize>) -> usize {
+        let (height, width) = (r1 - r0 + 1, c1 - c0 + 1);
+        (height + width - 1) * Congestion::UNIT / (height * width)
+    }
+
+    /// Indices of the GGrids of a box inside the map.
+    fn indices(&self, low: Pair<usize>, high: Pair<usize>) -> Vec<usize> {
+        (low.x()..=high.x())
+            .flat_map(|row| (low.y()..=high.y()).map(move |col| Pair(row, col)))
+            .filter_map(|position| Chip::grid_index(self.dim, position))
+            .collect()
+    }
+}
@@ -0,0 +1,76 @@
+use crate::components::ConflictType;
+use anyhow::{anyhow, Error, Result};
+use std::{fmt::Debug, str::FromStr};
+
+/// Whether the pins of a net consume routing demand on their own layer.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum PinDemand {
+    /// Pins only consume demand when a route segment passes through them.
+    Ignore,
+    /// Every pin of a net consumes one unit of demand on its GGrid and layer,
+    /// shared with the net's segments passing through the same GGrid.
+    #[default]
+    Consume,
+}
+
+/// RuleSet collects every interpretation detail of scoring and legality.
+/// Demand calculation, verification, scoring and cell movement
+/// all ask the chip's `RuleSet` instead of hard-coding a contest's rules.
+pub trait RuleSet: Debug + Send + Sync {
+    /// Whether pins consume routing demand.
+    fn pin_demand(&self) -> PinDemand;
+
+    /// Whether GGrids only reached by vias count towards wirelength.
+    fn count_vias(&self) -> bool;
+
+    /// Whether a cell moved back to its original position stops counting as moved.
+    fn return_to_origin(&self) -> bool;
+
+    /// Number of conflicting pairs formed by `num` cells of one mastercell
+    /// and `other` cells of the conflicting mastercell.
+    /// `same` is true if both mastercells are of the same type.
+    fn conflict_pairs(&self, kind: ConflictType, same: bool, num: usize, other: usize) -> usize;
+}
+
+/// The rules of the ICCAD 2020 CAD contest problem B.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Iccad2020 {
+    /// pin demand interpretation
+    pub pin_demand: PinDemand,
+}
+
+impl RuleSet for Iccad2020 {
+    fn pin_demand(&self) -> PinDemand {
+        self.pin_demand
+    }
+
+    fn count_vias(&self) -> bool {
+        true
+    }
+
+    fn return_to_origin(&self) -> bool {
+        true
+    }
+
+    fn conflict_pairs(&self, _kind: ConflictType, _same: bool, num: usize, other: usize) -> usize {
+        num.min(other)
+    }
+}
+
+impl Default for Box<dyn RuleSet> {
+    fn default() -> Self {
+        Box::new(Iccad2020::default())
+    }
+}
+
+impl FromStr for PinDemand {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ignore" => Ok(PinDemand::Ignore),
+            "consume" => Ok(PinDemand::Consume),
+            _ => Err(anyhow!("Unknown pin demand rule: {}", s)),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+/// What runs out first if cells keep moving at the observed pace.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Exhaustion {
+    /// the move budget is used up before the deadline
+    Budget,
+    /// the deadline comes before the move budget is used up
+    Clock,
+}
+
+/// What the scheduler should do next.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Strategy {
+    /// keep moving cells, accepting any improving move
+    Move,
+    /// keep moving cells, but only accept moves gaining at least the average
+    Selective,
+    /// stop moving cells and spend the remaining time rerouting
+    Reroute,
+}
+
+/// Statistics of the cell moves tried so far,
+/// used to project whether the move budget or the clock runs out first.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Schedule {
+    /// moves tried
+    pub attempts: usize,
+    /// moves kept
+    pub accepted: usize,
+    /// sum of the gains of the moves kept, in thousandths of a GGrid
+    pub total_gain: isize,
+    /// time spent trying moves
+    pub elapsed: Duration,
+}
+
+impl Schedule {
+    /// Records one tried move and the time it took.
+    pub fn record(&mut self, accepted: bool, gain: isize, elapsed: Duration) {
+        self.attempts += 1;
+        self.elapsed += elapsed;
+        if accepted {
+            self.accepted += 1;
+            self.total_gain += gain;
+        }
+    }
+
+    /// Fraction of the tried moves that were kept.
+    pub fn acceptance_rate(&self) -> f64 {
+        if self.attempts == 0 {
+            return 0.0;
+        }
+        self.accepted as f64 / self.attempts as f64
+    }
+
+    /// Average gain of a kept move.
+    pub fn average_gain(&self) -> f64 {
+        if self.accepted == 0 {
+            return 0.0;
+        }
+        self.total_gain as f64 / self.accepted as f64
+    }
+
+    /// Moves expected to be kept in `remaining` time at the observed pace.
+    pub fn projected_moves(&self, remaining: Duration) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+        let attempts = remaining.as_secs_f64() / self.elapsed.as_secs_f64() * self.attempts as f64;
+        attempts * self.acceptance_rate()
+    }
+
+    /// Projects whether `moves_remaining` or `remaining` time runs out first.
+    /// Nothing is known before the first move is kept, so the clock is assumed to.
+    pub fn project(&self, moves_remaining: usize, remaining: Duration) -> Exhaustion {
+        if self.accepted > 0 && self.projected_moves(remaining) >= moves_remaining as f64 {
+            Exhaustion::Budget
+        } else {
+            Exhaustion::Clock
+        }
+    }
+
+    /// Projected total gain of the remaining moves.
+    pub fn projected_gain(&self, moves_remaining: usize, remaining: Duration) -> f64 {
+        self.projected_moves(remaining).min(moves_remaining as f64) * self.average_gain()
+    }
+
+    /// Picks what to do next.
+    /// Once the budget is spent only rerouting helps,
+    /// and a budget that would run out early is saved for the better moves.
+    pub fn strategy(&self, moves_remaining: usize, remaining: Duration) -> Strategy {
+        if moves_remaining == 0 {
+            return Strategy::Reroute;
+        }
+        match self.project(moves_remaining, remaining) {
+            Exhaustion::Budget => Strategy::Selective,
+            Exhaustion::Clock => Strategy::Move,
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{
+    chip::Chip,
+    components::{Net, Point, Route},
+    report::{Report, Severity},
+    router::axis,
+};
+use anyhow::Result;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// Wirelength of the planar segments on one layer, split by the axis they run along.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LayerWirelength {
+    /// GGrid steps along a row
+    pub horizontal: usize,
+    /// GGrid steps along a column
+    pub vertical: usize,
+}
+
+/// The quality of a single net's routes.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct NetScore {
+    /// wirelength as the rules count it
+    pub wirelength: usize,
+    /// vias, one per pair of adjacent layers crossed
+    pub vias: usize,
+    /// changes of direction, see `Chip::net_bends`
+    pub bends: usize,
+}
+
+/// A breakdown of the quality of a solution,
+/// so the effect of layer assignment can be told apart from the rest.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Score {
+    /// whether the solution has no violations
+    pub legal: bool,
+    /// sum of demand exceeding capacity over all GGrids
+    pub overflow: usize,
+    /// total wirelength as the rules count it
+    pub wirelength: usize,
+    /// planar wirelength of every layer, by layer id
+    pub layers: Vec<LayerWirelength>,
+    /// vias between every pair of adjacent layers, `vias[i]` between layer ids `i` and `i + 1`
+    pub vias: Vec<usize>,
+    /// total changes of direction
+    pub bends: usize,
+    /// score of every net, by net id
+    pub nets: Vec<NetScore>,
+}
+
+impl Chip {
+    /// Scores the current state, see `Score`.
+    /// Segments are counted in their output form, so overlaps are counted once.
+    pub fn score(&self) -> Score {
+        let mut layers = vec![LayerWirelength::default(); self.layers.len()];
+        let mut vias = vec![0; self.layers.len().saturating_sub(1)];
+
+        for Route(source, target) in self.nets.iter().flat_map(|net| net.normalized_routes()) {
+            let (lay, high) = (source.lay(), target.lay());
+            if lay != high {
+                for pair in vias.iter_mut().take(high - 1).skip(lay - 1) {
+                    *pair += 1;
+                }
+            } else if let Some(layer) = layers.get_mut(lay - 1) {
+                layer.horizontal += source.col().abs_diff(target.col());
+                layer.vertical += source.row().abs_diff(target.row());
+            }
+        }
+
+        let nets: Vec<_> = self.nets.iter().map(|net| self.net_score(net)).collect();
+        Score {
+            legal: self.is_legal(),
+            overflow: self.metrics().overflow,
+            wirelength: nets.iter().map(|net| net.wirelength).sum(),
+            layers,
+            vias,
+            bends: nets.iter().map(|net| net.bends).sum(),
+            nets,
+        }
+    }
+
+    /// Loads a solution of the loaded input, see `read_solution`, and scores it as is.
+    /// Moves and segments off the chip are errors that make the score illegal.
+    /// Returns the score with every problem of the solution: those of loading it,
+    /// then the violations, see `diagnose`.
+    pub fn evaluate(&mut self, solution: &str) -> Result<(Score, Report)> {
+        let mut report = self.read_solution(solution, Severity::Error)?;
+        report.append(self.diagnose());
+
+        let mut score = self.score();
+        score.legal &= report.errors == 0;
+        Ok((score, report))
+    }
+
+    /// Scores the routes of a single net.
+    pub fn net_score(&self, net: &Net) -> NetScore {
+        let vias = net
+            .normalized_routes()
+            .iter()
+            .map(|route| route.source().lay().abs_diff(route.target().lay()))
+            .sum();
+
+        NetScore {
+            wirelength: self.net_length(net),
+            vias,
+            bends: self.net_bends(net),
+        }
+    }
+
+    /// Changes of direction in a net's routes: every GGrid where segments along
+    /// `n` different axes meet counts `n - 1`, so a path counts each of its turns once
+    /// and going from planar to a via counts too.
+    pub fn net_bends(&self, net: &Net) -> usize {
+        let mut axes: HashMap<Point<usize>, HashSet<usize>> = HashMap::new();
+        for route in net.normalized_routes() {
+            for pair in route.points().windows(2) {
+                let step = axis(pair[0], pair[1]);
+                axes.entry(pair[0]).or_default().insert(step);
+                axes.entry(pair[1]).or_default().insert(step);
+            }
+        }
+
+        axes.values().map(|axes| axes.len().saturating_sub(1)).sum()
+    }
+}
+
+impl Score {
+    /// The score as a JSON object, for tools comparing runs.
+    pub fn to_json(&self) -> String {
+        self.to_json_with(&[])
+    }
+
+    /// Like `to_json`, with `extra` fields of JSON values appended.
+    pub fn to_json_with(&self, extra: &[(&str, String)]) -> String {
+        let layers: Vec<_> = self
+            .layers
+            .iter()
+            .map(|layer| {
+                format!(
+                    "{{\"horizontal\": {}, \"vertical\": {}}}",
+                    layer.horizontal, layer.vertical
+                )
+            })
+            .collect();
+        let vias: Vec<_> = self.vias.iter().map(ToString::to_string).collect();
+        let nets: Vec<_> = self
+            .nets
+            .iter()
+            .map(|net| {
+                format!(
+                    "{{\"wirelength\": {}, \"vias\": {}, \"bends\": {}}}",
+                    net.wirelength, net.vias, net.bends
+                )
+            })
+            .collect();
+
+        let extra: String = extra
+            .iter()
+            .map(|(name, value)| format!(", \"{}\": {}", name, value))
+            .collect();
+
+        format!(
+            "{{\"legal\": {}, \"overflow\": {}, \"wirelength\": {}, \"layers\": [{}], \"vias\": [{}], \"bends\": {}, \"nets\": [{}]{}}}",
+            self.legal,
+            self.overflow,
+            self.wirelength,
+            layers.join(", "),
+            vias.join(", "),
+            self.bends,
+            nets.join(", "),
+            extra
+        )
+    }
+}
+
+impl Display for Score {
+    /// The contest wirelength, the overflow and legality, then the planar length and vias
+    /// of the segments, on one line. Segments count steps between GGrids,
+    /// so their parts don't add up to the wirelength, which counts the GGrids occupied.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let planar: usize = self
+            .layers
+            .iter()
+            .map(|layer| layer.horizontal + layer.vertical)
+            .sum();
+        let vias: usize = self.vias.iter().sum();
+        write!(
+            f,
+            "Score: wirelength {}, overflow {}, {} (segments: planar length {}, vias {})",
+            self.wirelength,
+            self.overflow,
+            if self.legal { "legal" } else { "illegal" },
+            planar,
+            vias
+        )
+    }
+}
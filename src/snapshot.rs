@@ -0,0 +1,157 @@
+use crate::{
+    chip::Chip,
+    components::{self, Cell, FactoryID, Net, Pair, Route},
+};
+use anyhow::{anyhow, Result};
+use std::{
+    collections::HashSet,
+    fmt::{Display, Error as FmtError, Formatter, Result as FmtResult},
+};
+
+/// The mutable state of a chip: where the cells are and how the nets are routed.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Snapshot {
+    /// positions of all cells
+    pub positions: Vec<Pair<usize>>,
+    /// whether every cell has moved
+    pub moved: Vec<bool>,
+    /// already moved cells
+    pub already_moved: usize,
+    /// route segments of all nets
+    pub routes: Vec<HashSet<Route<usize>>>,
+}
+
+impl Chip {
+    /// Takes a snapshot of the cell placement and net routing.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            positions: self.cells.iter().map(|cell| cell.position).collect(),
+            moved: self.cells.iter().map(|cell| cell.moved).collect(),
+            already_moved: self.already_moved,
+            routes: self.nets.iter().map(|net| net.routes.clone()).collect(),
+        }
+    }
+
+    /// Restores the cell placement and net routing from a snapshot.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<()> {
+        if snapshot.positions.len() != self.cells.len() || snapshot.routes.len() != self.nets.len()
+        {
+            return Err(anyhow!("Snapshot does not belong to this chip"));
+        }
+
+        for ((cell, &position), &moved) in self
+            .cells
+            .iter_mut()
+            .zip(snapshot.positions.iter())
+            .zip(snapshot.moved.iter())
+        {
+            cell.position = position;
+            cell.moved = moved;
+        }
+        self.already_moved = snapshot.already_moved;
+
+        for (net, routes) in self.nets.iter_mut().zip(snapshot.routes.iter()) {
+            net.routes = routes.clone();
+        }
+        self.dirty_nets.clear();
+
+        self.update_demand()
+    }
+
+    /// Checks if the current state has no violations.
+    pub fn is_legal(&self) -> bool {
+        self.verify().is_empty()
+    }
+
+    /// Remembers the current state as the fallback if it is legal.
+    /// Returns whether the state was remembered.
+    pub fn checkpoint(&mut self) -> bool {
+        if !self.is_legal() {
+            return false;
+        }
+
+        let snapshot = self.snapshot();
+        if let Some(autosave) = &self.autosave {
+            autosave.publish(snapshot.clone());
+        }
+        self.fallback = Some(snapshot);
+        true
+    }
+
+    /// Makes a checkpoint at the end of a phase if `checkpoints` is set.
+    /// Checkpoints need a full verification, so they are off unless something rolls back to them.
+    pub(crate) fn phase_checkpoint(&mut self) {
+        if self.checkpoints {
+            self.checkpoint();
+        }
+    }
+
+    /// Rolls back to the fallback snapshot.
+    /// The fallback is the last legal checkpoint, or the unmodified input if there is none.
+    pub fn rollback(&mut self) -> Result<()> {
+        let fallback = self
+            .fallback
+            .take()
+            .ok_or_else(|| anyhow!("No snapshot to roll back to"))?;
+        let result = self.restore(&fallback);
+        self.fallback = Some(fallback);
+
+        result
+    }
+
+    /// Restores the unmodified input, undoing every move and reroute.
+    pub fn restore_original(&mut self) -> Result<()> {
+        let original = self
+            .original
+            .take()
+            .ok_or_else(|| anyhow!("No input to restore"))?;
+        let result = self.restore(&original);
+        self.original = Some(original);
+
+        result
+    }
+
+    /// Rolls back to the fallback snapshot if the current state is not legal.
+    /// Fails if the fallback is not legal either, which happens when no checkpoint was legal
+    /// and the unmodified input already has violations.
+    pub fn ensure_legal(&mut self) -> Result<()> {
+        if self.is_legal() {
+            return Ok(());
+        }
+
+        self.rollback()?;
+        if !self.is_legal() {
+            return Err(anyhow!(
+                "No legal solution to write, the input itself is illegal"
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Display for Snapshot {
+    /// The snapshot in the output format, the same as the chip's output in that state.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        writeln!(f, "NumMovedCellInst {}", self.already_moved)?;
+        for (idx, position) in self.positions.iter().enumerate() {
+            if self.moved[idx] {
+                let name = Cell::from_num(idx).map_err(|_| FmtError)?;
+                writeln!(f, "CellInst {} {}", name, position)?;
+            }
+        }
+
+        let routes: Vec<_> = self.routes.iter().map(components::merge_routes).collect();
+        writeln!(
+            f,
+            "NumRoutes {}",
+            routes.iter().map(Vec::len).sum::<usize>()
+        )?;
+        for (id, routes) in routes.iter().enumerate() {
+            let name = Net::from_num(id).map_err(|_| FmtError)?;
+            for route in routes {
+                writeln!(f, "{} {}", route, name)?;
+            }
+        }
+        Ok(())
+    }
+}
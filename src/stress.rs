@@ -0,0 +1,49 @@
+use crate::raw::RawDesign;
+use clap::Clap;
+
+/// How much harder to make a stressed variant of an input, every option in percent.
+#[derive(Clap, Clone, Debug, Default)]
+pub struct Stress {
+    // percentage of every GGrid's supply to remove
+    #[clap(long, default_value = "0")]
+    pub supply: usize,
+
+    // percentage of MaxCellMove to remove
+    #[clap(long, default_value = "0")]
+    pub moves: usize,
+
+    // percentage to raise every conflict's extra demand by
+    #[clap(long, default_value = "0")]
+    pub conflict: usize,
+}
+
+impl RawDesign {
+    /// A harder variant of the design:
+    /// supplies and the move budget shrink, conflicts demand more.
+    /// Non-default supplies never take a GGrid below zero.
+    pub fn stressed(&self, stress: &Stress) -> RawDesign {
+        let shrink = |value: usize, percent: usize| value * 100usize.saturating_sub(percent) / 100;
+
+        let mut raw = self.clone();
+        raw.max_move = shrink(raw.max_move, stress.moves);
+        for layer in raw.layers.iter_mut() {
+            layer.supply = shrink(layer.supply, stress.supply);
+        }
+        for supply in raw.non_default_supply.iter_mut() {
+            // Layers are 1-based in supply points, those off the chip are left to `compile`.
+            let layer = match supply.point.lay().checked_sub(1) {
+                Some(idx) => (self.layers.get(idx), raw.layers.get(idx)),
+                None => (None, None),
+            };
+            if let (Some(original), Some(shrunk)) = layer {
+                let total = (original.supply as isize + supply.delta).max(0) as usize;
+                supply.delta = shrink(total, stress.supply) as isize - shrunk.supply as isize;
+            }
+        }
+        for conflict in raw.conflicts.iter_mut() {
+            conflict.demand += conflict.demand * stress.conflict / 100;
+        }
+
+        raw
+    }
+}
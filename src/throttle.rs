@@ -0,0 +1,65 @@
+use std::collections::VecDeque;
+
+/// Watches the recent score gains of every worker thread
+/// and tells workers to pause once their gains diminish,
+/// so long runs don't keep a shared server busy for nothing.
+#[derive(Clone, Debug, Default)]
+pub struct Throttle {
+    /// number of recent gains averaged
+    pub window: usize,
+    /// the lowest average gain worth the work
+    pub min_gain: f64,
+    /// recent gains of every worker
+    pub gains: Vec<VecDeque<isize>>,
+}
+
+impl Throttle {
+    pub fn new(workers: usize, window: usize, min_gain: f64) -> Self {
+        Self {
+            window: window.max(1),
+            min_gain,
+            gains: vec![VecDeque::new(); workers.max(1)],
+        }
+    }
+
+    /// Records the score gain of one iteration of a worker.
+    pub fn record(&mut self, worker: usize, gain: isize) {
+        let window = self.window;
+        if let Some(gains) = self.gains.get_mut(worker) {
+            if gains.len() == window {
+                gains.pop_front();
+            }
+            gains.push_back(gain);
+        }
+    }
+
+    /// Checks if the average gain of a worker over a full window is below `min_gain`.
+    pub fn is_stalled(&self, worker: usize) -> bool {
+        self.gains.get(worker).is_some_and(|gains| {
+            gains.len() == self.window
+                && (gains.iter().sum::<isize>() as f64 / self.window as f64) < self.min_gain
+        })
+    }
+
+    /// Forgets the gains of a worker, so it runs a full window before stalling again.
+    pub fn resume(&mut self, worker: usize) {
+        if let Some(gains) = self.gains.get_mut(worker) {
+            gains.clear();
+        }
+    }
+
+    /// Forgets the gains of every worker, so a new pass starts with all of them active.
+    pub fn reset(&mut self) {
+        for worker in 0..self.gains.len() {
+            self.resume(worker);
+        }
+    }
+
+    /// Number of threads worth keeping, at least one.
+    pub fn active_workers(&self) -> usize {
+        (0..self.gains.len())
+            .filter(|&worker| !self.is_stalled(worker))
+            .count()
+            .max(1)
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    chip::Chip,
+    components::{Pair, Route},
+};
+use anyhow::{anyhow, Result};
+use std::{
+    collections::HashSet,
+    ops::{Deref, DerefMut},
+};
+
+/// A cell move that is undone unless it is committed, see `Chip::try_move`.
+/// The chip stays usable through the transaction in the meantime, e.g. to reroute or score,
+/// and rolling back also restores the routes and dirty flags of the cell's nets
+/// and of the nets dirty before the move, the only ones `reroute_dirty_nets` touches.
+/// Dropping an uncommitted transaction rolls it back.
+#[derive(Debug)]
+pub struct MoveTxn<'a> {
+    chip: &'a mut Chip,
+    /// the moved cell
+    idx: usize,
+    /// position and moved flag of the cell before the move
+    from: (Pair<usize>, bool),
+    /// number of moved cells before the move
+    already_moved: usize,
+    /// the cell's nets and the dirty nets, with their routes and whether they were dirty
+    /// before the move
+    nets: Vec<(usize, HashSet<Route<usize>>, bool)>,
+    /// whether the transaction is committed or rolled back
+    done: bool,
+}
+
+impl Chip {
+    /// Moves a cell to `target` like `move_cell` does, updating its position, the demand and the budget,
+    /// and returns the move as a transaction to commit or roll back.
+    /// Returns an error if `can_move` rejects the move.
+    pub fn try_move(&mut self, idx: usize, target: Pair<usize>) -> Result<MoveTxn<'_>> {
+        let cell = self
+            .cells
+            .get(idx)
+            .ok_or_else(|| anyhow!("No cell {}", idx + 1))?;
+        let from = (cell.position, cell.moved);
+        let already_moved = self.already_moved;
+        let mut ids = self.cell_nets(idx);
+        ids.extend(self.dirty_nets.iter().copied());
+        ids.sort_unstable();
+        ids.dedup();
+        let nets = ids
+            .into_iter()
+            .map(|id| {
+                (
+                    id,
+                    self.nets[id].routes.clone(),
+                    self.dirty_nets.contains(&id),
+                )
+            })
+            .collect();
+
+        self.move_cell(idx, target)?;
+        Ok(MoveTxn {
+            chip: self,
+            idx,
+            from,
+            already_moved,
+            nets,
+            done: false,
+        })
+    }
+}
+
+impl MoveTxn<'_> {
+    /// The moved cell.
+    pub fn cell(&self) -> usize {
+        self.idx
+    }
+
+    /// Position of the cell before the move.
+    pub fn from(&self) -> Pair<usize> {
+        self.from.0
+    }
+
+    /// Keeps the move and whatever was changed through the transaction.
+    pub fn commit(mut self) {
+        self.done = true;
+    }
+
+    /// Puts the cell back and restores the routes of the nets, the demand and the budget.
+    pub fn rollback(mut self) -> Result<()> {
+        self.undo()
+    }
+
+    fn undo(&mut self) -> Result<()> {
+        if self.done {
+            return Ok(());
+        }
+        self.done = true;
+
+        let chip = &mut *self.chip;
+        for (id, routes, dirty) in self.nets.drain(..) {
+            for point in chip.own_net_grids(&chip.nets[id]) {
+                chip.remove_demand(point, 1)?;
+            }
+            chip.nets[id].routes = routes;
+            for point in chip.own_net_grids(&chip.nets[id]) {
+                chip.add_demand(point, 1)?;
+            }
+            if dirty {
+                chip.dirty_nets.insert(id);
+            } else {
+                chip.dirty_nets.remove(&id);
+            }
+        }
+
+        // The same demand `move_cell` changed, the other way around.
+        let (position, moved) = self.from;
+        let around = [chip.cells[self.idx].position, position];
+        let before = chip.cell_demand(self.idx, &around);
+        let cell = &mut chip.cells[self.idx];
+        cell.position = position;
+        cell.moved = moved;
+        chip.already_moved = self.already_moved;
+
+        for (point, amount) in before {
+            chip.remove_demand(point, amount)?;
+        }
+        for (point, amount) in chip.cell_demand(self.idx, &around) {
+            chip.add_demand(point, amount)?;
+        }
+        Ok(())
+    }
+}
+
+impl Deref for MoveTxn<'_> {
+    type Target = Chip;
+
+    fn deref(&self) -> &Chip {
+        self.chip
+    }
+}
+
+impl DerefMut for MoveTxn<'_> {
+    fn deref_mut(&mut self) -> &mut Chip {
+        self.chip
+    }
+}
+
+impl Drop for MoveTxn<'_> {
+    /// Rolls back a move that was neither committed nor rolled back.
+    fn drop(&mut self) {
+        if let Err(err) = self.undo() {
+            eprintln!(
+                "Rolling back the move of cell {} failed: {}",
+                self.idx + 1,
+                err
+            );
+        }
+    }
+}
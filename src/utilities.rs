@@ -1,6 +1,6 @@
+use crate::components::{Point, Route};
 use anyhow::{anyhow, Error, Result};
-use num::Num;
-use std::{cmp::PartialEq, fmt::Debug, str::FromStr};
+use std::{cmp::PartialEq, fmt::Debug, str};
 
 #[derive(Debug)]
 pub struct InputError;
@@ -8,6 +8,12 @@ pub struct InputError;
 #[derive(Debug)]
 pub struct NameError;
 
+#[derive(Debug)]
+pub struct BoundsError(pub Point<usize>);
+
+#[derive(Debug)]
+pub struct ShapeError(pub Route<usize>);
+
 impl From<InputError> for Error {
     fn from(err: InputError) -> Self {
         anyhow!(format!("Error: {:?}", err))
@@ -20,22 +26,323 @@ impl From<NameError> for Error {
     }
 }
 
+impl From<BoundsError> for Error {
+    fn from(err: BoundsError) -> Self {
+        anyhow!(format!("Error: {:?}", err))
+    }
+}
+
+impl From<ShapeError> for Error {
+    fn from(err: ShapeError) -> Self {
+        anyhow!(format!("Error: {:?}", err))
+    }
+}
+
+/// A token yielded by an iterator, either a `&str` or a `&[u8]`.
+pub trait Token<'a> {
+    /// The raw bytes of the token.
+    fn bytes(self) -> &'a [u8];
+}
+
+impl<'a> Token<'a> for &'a str {
+    fn bytes(self) -> &'a [u8] {
+        self.as_bytes()
+    }
+}
+
+impl<'a> Token<'a> for &'a [u8] {
+    fn bytes(self) -> &'a [u8] {
+        self
+    }
+}
+
+/// Splits the content into tokens separated by ASCII whitespace.
+/// Tokens are slices of the original content, nothing is copied.
+#[derive(Clone, Debug)]
+pub struct Tokenizer<'a> {
+    /// content not yet consumed
+    rest: &'a [u8],
+}
+
+impl<'a> Tokenizer<'a> {
+    /// Creates a new tokenizer over `content`.
+    pub fn new(content: &'a str) -> Self {
+        Self {
+            rest: content.as_bytes(),
+        }
+    }
+
+    /// Room to reserve for `count` entries still to be read,
+    /// never more than the content left could hold, whatever the count claims.
+    pub fn capacity(&self, count: usize) -> usize {
+        count.min(self.rest.len())
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.rest.iter().position(|b| !b.is_ascii_whitespace())?;
+        let rest = &self.rest[start..];
+        let end = rest
+            .iter()
+            .position(u8::is_ascii_whitespace)
+            .unwrap_or(rest.len());
+
+        let (token, rest) = rest.split_at(end);
+        self.rest = rest;
+        Some(token)
+    }
+}
+
+/// Numeric values that can be parsed from ASCII digits directly.
+pub trait FromAscii: Sized {
+    /// Returns `None` if `bytes` is not a number or the number overflows.
+    fn parse_ascii(bytes: &[u8]) -> Option<Self>;
+}
+
+impl FromAscii for usize {
+    fn parse_ascii(bytes: &[u8]) -> Option<Self> {
+        let digits = match bytes {
+            [b'+', digits @ ..] => digits,
+            digits => digits,
+        };
+
+        if digits.is_empty() {
+            return None;
+        }
+
+        digits.iter().try_fold(0usize, |acc, &b| {
+            if b.is_ascii_digit() {
+                acc.checked_mul(10)?.checked_add((b - b'0') as usize)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl FromAscii for isize {
+    fn parse_ascii(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [b'-', digits @ ..] => {
+                let magnitude = usize::parse_ascii(digits)?;
+                if magnitude > isize::MAX as usize + 1 {
+                    None
+                } else {
+                    Some((magnitude as isize).wrapping_neg())
+                }
+            }
+            digits => {
+                let magnitude = usize::parse_ascii(digits)?;
+                if magnitude > isize::MAX as usize {
+                    None
+                } else {
+                    Some(magnitude as isize)
+                }
+            }
+        }
+    }
+}
+
 /// Parses a `&str` from an iterator
-pub fn parse_string<'a, T>(iter: &mut T) -> Result<&'a str>
+pub fn parse_string<'a, T, I>(iter: &mut T) -> Result<&'a str>
 where
-    T: Iterator<Item = &'a str>,
+    T: Iterator<Item = I>,
+    I: Token<'a>,
 {
-    iter.next().ok_or(InputError).map_err(Error::from)
+    let token = iter.next().ok_or(InputError)?;
+    str::from_utf8(token.bytes()).map_err(Error::from)
 }
 
 /// Parses a numeric value (usize, isize...) from an iterator
-pub fn parse_numeric<'a, T, U>(iter: &mut T) -> Result<U>
+pub fn parse_numeric<'a, T, I, U>(iter: &mut T) -> Result<U>
+where
+    T: Iterator<Item = I>,
+    I: Token<'a>,
+    U: FromAscii,
+{
+    let token = iter.next().ok_or(InputError)?;
+    U::parse_ascii(token.bytes())
+        .ok_or(InputError)
+        .map_err(Error::from)
+}
+
+/// Number of decimal digits of `n`.
+pub fn num_digits(n: usize) -> usize {
+    let mut digits = 1;
+    let mut n = n / 10;
+    while n > 0 {
+        digits += 1;
+        n /= 10;
+    }
+    digits
+}
+
+/// Hashes bytes with 64-bit FNV-1a.
+/// Unlike `DefaultHasher`, the result is stable across builds and platforms.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    bytes
+        .iter()
+        .fold(OFFSET, |hash, &b| (hash ^ b as u64).wrapping_mul(PRIME))
+}
+
+/// Threads the parallel work of one chip runs on.
+/// Every chip has its own pool, so chips optimized side by side in one process
+/// neither share nor reconfigure a global one. The default runs on rayon's global pool.
+/// Without the `parallel` feature everything runs on the calling thread.
+#[derive(Clone, Debug, Default)]
+pub struct ThreadPool {
+    #[cfg(feature = "parallel")]
+    pool: Option<std::sync::Arc<rayon::ThreadPool>>,
+}
+
+#[cfg(feature = "parallel")]
+impl ThreadPool {
+    /// A pool of `threads` threads of its own.
+    pub fn new(threads: usize) -> Result<Self> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?;
+        Ok(Self {
+            pool: Some(std::sync::Arc::new(pool)),
+        })
+    }
+
+    /// Number of threads parallel work is split over.
+    pub fn current_num_threads(&self) -> usize {
+        match &self.pool {
+            Some(pool) => pool.current_num_threads(),
+            None => rayon::current_num_threads(),
+        }
+    }
+
+    /// Runs `f` with the `par_*` functions it calls split over the threads of this pool.
+    pub fn install<R, F>(&self, f: F) -> R
+    where
+        R: Send,
+        F: FnOnce() -> R + Send,
+    {
+        match &self.pool {
+            Some(pool) => pool.install(f),
+            None => f(),
+        }
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+impl ThreadPool {
+    pub fn new(_threads: usize) -> Result<Self> {
+        Ok(Self::default())
+    }
+
+    pub fn current_num_threads(&self) -> usize {
+        1
+    }
+
+    pub fn install<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        f()
+    }
+}
+
+/// Maps `items` with `f`, in parallel with the `parallel` feature, keeping their order.
+#[cfg(feature = "parallel")]
+pub fn par_map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync + Send,
+{
+    use rayon::prelude::*;
+    items.par_iter().map(f).collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+pub fn par_map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    F: Fn(&T) -> R,
+{
+    items.iter().map(f).collect()
+}
+
+/// Runs `f` on every item, in parallel with the `parallel` feature.
+#[cfg(feature = "parallel")]
+pub fn par_for_each_mut<T, F>(items: &mut [T], f: F)
+where
+    T: Send,
+    F: Fn(&mut T) + Sync + Send,
+{
+    use rayon::prelude::*;
+    items.par_iter_mut().for_each(f)
+}
+
+#[cfg(not(feature = "parallel"))]
+pub fn par_for_each_mut<T, F>(items: &mut [T], f: F)
+where
+    F: Fn(&mut T),
+{
+    items.iter_mut().for_each(f)
+}
+
+/// Concatenates the strings `f` makes of `items` in order,
+/// built in parallel with the `parallel` feature.
+#[cfg(feature = "parallel")]
+pub fn par_concat<T, F>(items: &[T], f: F) -> String
 where
-    T: Iterator<Item = &'a str>,
-    U: FromStr + Num,
-    Error: From<<U as FromStr>::Err>,
+    T: Sync,
+    F: Fn(&T) -> String + Sync + Send,
 {
-    parse_string(iter)?.parse().map_err(Error::from)
+    use rayon::prelude::*;
+
+    // `fold_with + reduce_with` is the parallel iterators' equivalent to `fold_with` of iterators
+    let accumulate = |mut acc: String, s: String| {
+        acc.push_str(&s);
+        acc
+    };
+    items
+        .par_iter()
+        .map(f)
+        .fold_with(String::new(), accumulate)
+        .reduce(String::new, accumulate)
+}
+
+#[cfg(not(feature = "parallel"))]
+pub fn par_concat<T, F>(items: &[T], f: F) -> String
+where
+    F: Fn(&T) -> String,
+{
+    items.iter().map(f).collect()
+}
+
+/// A seeded pseudo-random generator (SplitMix64).
+/// Like `fnv1a`, its sequence is stable across builds and platforms, so runs can be replayed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`, 0 if `n` is 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        self.next_u64().checked_rem(n as u64).unwrap_or(0) as usize
+    }
 }
 
 /// Returns `Ok(())` if `mine == input`.
@@ -47,7 +354,7 @@ where
     if mine == input {
         Ok(())
     } else {
-        Err(NameError).map_err(Error::from)
+        Err(Error::from(NameError))
     }
 }
 
@@ -100,16 +407,13 @@ impl UnionFind {
     /// The set id every node belongs.
     pub fn belongs(&self) -> Vec<usize> {
         (0..self.len())
-            .map(|idx| self.find(idx).expect("Index out of bounds"))
+            .map(|idx| self.find(idx).unwrap_or(idx))
             .collect()
     }
 
     /// Check if there is only one set left.
     pub fn done(&self) -> bool {
-        self.belongs().windows(2).all(|arr| match arr {
-            [a, b] => *a == *b,
-            _ => unreachable!(),
-        })
+        self.belongs().windows(2).all(|arr| arr[0] == arr[1])
     }
 
     /// Check if two values are in the same group.
@@ -175,9 +479,9 @@ impl UnionFind {
     /// Unions two different disjoint sets.
     /// Returns true if a, b were unioned in this function.
     /// Returns false if a, b are already joined before this method is called.
-    /// Panics if an Index out of bounds error is encountered
+    /// Returns false if a or b is out of bounds.
     pub fn union(&mut self, a: usize, b: usize) -> bool {
-        self.union_checked(a, b).expect("Index out of bounds")
+        self.union_checked(a, b).unwrap_or(false)
     }
 
     /// Unions two different disjoint sets.
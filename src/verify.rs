@@ -0,0 +1,304 @@
+use crate::{
+    chip::Chip,
+    components::{CellType, Direction, Net, Pair, Point, Route},
+    utilities::UnionFind,
+};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// A problem found by the verifier.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Violation {
+    /// demand exceeds capacity on a GGrid
+    Overflow {
+        /// where the overflow happens
+        point: Point<usize>,
+        /// total demand
+        demand: usize,
+        /// total capacity
+        capacity: usize,
+    },
+    /// a route segment leaves the chip
+    OutOfBounds {
+        /// id of the net
+        net: usize,
+        /// the offending segment
+        route: Route<usize>,
+    },
+    /// a planar route segment runs below the net's min layer
+    BelowMinLayer {
+        /// id of the net
+        net: usize,
+        /// the offending segment
+        route: Route<usize>,
+    },
+    /// a planar route segment runs against its layer's routing direction
+    WrongDirection {
+        /// id of the net
+        net: usize,
+        /// the offending segment
+        route: Route<usize>,
+    },
+    /// more cells moved than the budget allows
+    TooManyMoves {
+        /// number of cells moved
+        moved: usize,
+        /// the budget
+        limit: usize,
+    },
+    /// a fixed cell moved, or a cell moved farther than allowed
+    IllegalMove {
+        /// id of the cell
+        cell: usize,
+    },
+    /// the pins of a net are not all connected by its routes
+    Disconnected {
+        /// id of the net
+        net: usize,
+    },
+    /// a pin's GGrid has no supply on any layer the net may route on
+    UnreachablePin {
+        /// id of the net
+        net: usize,
+        /// global id of the pin
+        pin: usize,
+        /// where the pin is
+        point: Point<usize>,
+    },
+    /// the input asks for less than no supply on a GGrid, which was clamped to 0
+    NegativeSupply {
+        /// where the supply is
+        point: Point<usize>,
+    },
+}
+
+impl Chip {
+    /// Checks the current state of the chip against the selected rules.
+    /// Returns every violation found.
+    pub fn verify(&self) -> Vec<Violation> {
+        let Pair(_, cols) = self.dim;
+
+        let out_of_bounds = self.nets.iter().flat_map(|net| {
+            net.routes
+                .iter()
+                .filter(move |route| {
+                    !self.in_bounds(route.source()) || !self.in_bounds(route.target())
+                })
+                .map(move |&route| Violation::OutOfBounds { net: net.id, route })
+        });
+
+        let below_min_layer = self.nets.iter().flat_map(|net| {
+            Self::below_min_layer(net)
+                .into_iter()
+                .map(move |route| Violation::BelowMinLayer { net: net.id, route })
+        });
+
+        let wrong_direction = self.nets.iter().flat_map(|net| {
+            self.wrong_direction(net)
+                .into_iter()
+                .map(move |route| Violation::WrongDirection { net: net.id, route })
+        });
+
+        let too_many_moves =
+            (self.already_moved > self.max_move).then_some(Violation::TooManyMoves {
+                moved: self.already_moved,
+                limit: self.max_move,
+            });
+
+        let illegal_moves = (0..self.cells.len())
+            .filter(|&idx| self.is_illegal_move(idx))
+            .map(|cell| Violation::IllegalMove { cell });
+
+        let overflow = self.layers.iter().flat_map(|layer| {
+            layer
+                .demand
+                .iter()
+                .zip(layer.capacity.iter())
+                .enumerate()
+                .filter(|(_, (demand, capacity))| demand > capacity)
+                .map(move |(idx, (&demand, &capacity))| Violation::Overflow {
+                    point: Point(idx / cols + 1, idx % cols + 1, layer.id + 1),
+                    demand,
+                    capacity,
+                })
+        });
+
+        let pin_points = self.pin_points();
+        let disconnected = self
+            .nets
+            .iter()
+            .filter(|net| !self.is_connected(net, &pin_points))
+            .map(|net| Violation::Disconnected { net: net.id });
+
+        out_of_bounds
+            .chain(below_min_layer)
+            .chain(wrong_direction)
+            .chain(too_many_moves)
+            .chain(illegal_moves)
+            .chain(overflow)
+            .chain(disconnected)
+            .collect()
+    }
+
+    /// Checks the input for supply clamped to 0 and nets that cannot be routed legally.
+    /// A pin is unreachable if its GGrid has no supply on the net's min layer and above,
+    /// unless pin access is exempt from demand.
+    pub fn sanity_check(&self) -> Vec<Violation> {
+        let negative_supply = self
+            .clamped_supply
+            .iter()
+            .map(|&point| Violation::NegativeSupply { point });
+        if self.pin_access_exempt {
+            return negative_supply.collect();
+        }
+
+        let pin_points = &self.pin_points();
+        let unreachable_pins = self.nets.iter().flat_map(|net| {
+            self.unreachable_pins(net, pin_points)
+                .into_iter()
+                .map(move |pin| Violation::UnreachablePin {
+                    net: net.id,
+                    pin,
+                    point: pin_points[pin],
+                })
+        });
+        negative_supply.chain(unreachable_pins).collect()
+    }
+
+    /// Pins of a net whose GGrids have no supply on the net's min layer and above.
+    pub fn unreachable_pins(&self, net: &Net, pin_points: &[Point<usize>]) -> Vec<usize> {
+        net.pins
+            .iter()
+            .copied()
+            .filter(|&pin| {
+                let Point(row, col, _) = pin_points[pin];
+                self.layers[net.min_layer.min(self.layers.len())..]
+                    .iter()
+                    .all(|layer| layer.get_capacity(row - 1, col - 1).is_none_or(|&c| c == 0))
+            })
+            .collect()
+    }
+
+    /// Planar route segments of a net that run below its min layer.
+    pub fn below_min_layer(net: &Net) -> Vec<Route<usize>> {
+        net.routes
+            .iter()
+            .copied()
+            .filter(|route| net.is_below_min_layer(route))
+            .collect()
+    }
+
+    /// Checks if a planar route segment runs against its layer's routing direction:
+    /// horizontal layers only take segments along a row, vertical layers along a column.
+    /// Vias and segments on unknown layers are never wrong.
+    pub fn is_wrong_direction(&self, route: &Route<usize>) -> bool {
+        let (Point(row0, col0, lay0), Point(row1, col1, lay1)) = (route.source(), route.target());
+        if lay0 != lay1 {
+            return false;
+        }
+        match self.get_layer(lay0 - 1).map(|layer| layer.direction) {
+            Some(Direction::Horizontal) => row0 != row1,
+            Some(Direction::Vertical) => col0 != col1,
+            None => false,
+        }
+    }
+
+    /// Planar route segments of a net that run against their layer's routing direction.
+    pub fn wrong_direction(&self, net: &Net) -> Vec<Route<usize>> {
+        net.routes
+            .iter()
+            .copied()
+            .filter(|route| self.is_wrong_direction(route))
+            .collect()
+    }
+
+    /// Checks if a cell is away from its origin although it may not be:
+    /// it is fixed, or farther than `max_displacement`.
+    pub fn is_illegal_move(&self, idx: usize) -> bool {
+        self.cells.get(idx).is_some_and(|cell| {
+            cell.position != cell.origin
+                && (!matches!(cell.movable, CellType::Movable)
+                    || self
+                        .max_displacement
+                        .is_some_and(|limit| cell.origin.distance(&cell.position) > limit))
+        })
+    }
+
+    /// Checks if all pins of a net are connected by its routes.
+    pub fn is_connected(&self, net: &Net, pin_points: &[Point<usize>]) -> bool {
+        let mut ids: HashMap<Point<usize>, usize> = HashMap::new();
+        let mut id_of = |point: Point<usize>| {
+            let next = ids.len();
+            *ids.entry(point).or_insert(next)
+        };
+
+        let pins: Vec<_> = net.pins.iter().map(|&pin| id_of(pin_points[pin])).collect();
+        let edges: Vec<_> = net
+            .routes
+            .iter()
+            .flat_map(|route| {
+                let points = route.points();
+                let ids: Vec<_> = points.iter().map(|&point| id_of(point)).collect();
+                ids.windows(2)
+                    .map(|pair| (pair[0], pair[1]))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        let mut union_find = UnionFind::new(ids.len());
+        for (a, b) in edges {
+            union_find.union(a, b);
+        }
+
+        pins.windows(2)
+            .all(|pair| union_find.grouped(pair[0], pair[1]) == Some(true))
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Violation::Overflow {
+                point,
+                demand,
+                capacity,
+            } => write!(
+                f,
+                "Overflow at {}: demand {} > capacity {}",
+                point, demand, capacity
+            ),
+            Violation::OutOfBounds { net, route } => {
+                write!(f, "Route {} of net {} is out of bounds", route, net + 1)
+            }
+            Violation::BelowMinLayer { net, route } => write!(
+                f,
+                "Route {} of net {} runs below its min layer",
+                route,
+                net + 1
+            ),
+            Violation::WrongDirection { net, route } => write!(
+                f,
+                "Route {} of net {} runs against its layer's direction",
+                route,
+                net + 1
+            ),
+            Violation::TooManyMoves { moved, limit } => {
+                write!(f, "{} cells moved, more than the limit {}", moved, limit)
+            }
+            Violation::IllegalMove { cell } => write!(f, "Cell {} may not move there", cell + 1),
+            Violation::Disconnected { net } => write!(f, "Net {} is disconnected", net + 1),
+            Violation::UnreachablePin { net, pin, point } => write!(
+                f,
+                "Pin {} of net {} at {} has no supply at or above the min layer",
+                pin + 1,
+                net + 1,
+                point
+            ),
+            Violation::NegativeSupply { point } => {
+                write!(f, "Supply at {} is negative, clamped to 0", point)
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{
+    chip::Chip,
+    components::{Direction, Layer, Pair, Point},
+};
+use std::sync::Arc;
+
+/// An immutable snapshot of a `Chip`.
+/// Cloning a view is cheap as all arrays are shared,
+/// so a view can be handed to other threads while the chip keeps changing.
+/// Capacity is shared with the chip too, which copies a layer's only if it changes.
+#[derive(Clone, Debug, Default)]
+pub struct ChipView {
+    /// maximum movement count
+    pub max_move: usize,
+    /// already moved cells
+    pub already_moved: usize,
+    /// dimensions
+    pub dim: Pair<usize>,
+    /// positions of all cells
+    pub positions: Arc<[Pair<usize>]>,
+    /// capacity of every layer
+    pub capacity: Arc<[Arc<Vec<usize>>]>,
+    /// demand of every layer
+    pub demand: Arc<[Vec<usize>]>,
+    /// wirelength of every net
+    pub lengths: Arc<[usize]>,
+}
+
+/// A read-only view of the supply of every GGrid, borrowed from a `Chip`.
+/// Unlike `ChipView` nothing is copied, and unlike the chip it is `Send + Sync`
+/// without giving access to demand, so estimators can share it across threads.
+/// The chip can't change while a view is alive.
+#[derive(Clone, Copy, Debug)]
+pub struct CapacityView<'a> {
+    /// dimensions
+    pub dim: Pair<usize>,
+    /// the chip's layers, reached only through the view's methods
+    layers: &'a [Layer],
+}
+
+impl Chip {
+    /// Borrows a view of the supply grids, see `CapacityView`.
+    pub fn capacity_view(&self) -> CapacityView<'_> {
+        CapacityView {
+            dim: self.dim,
+            layers: &self.layers,
+        }
+    }
+
+    /// Takes a snapshot of the current state.
+    pub fn view(&self) -> ChipView {
+        ChipView {
+            max_move: self.max_move,
+            already_moved: self.already_moved,
+            dim: self.dim,
+            positions: self.cells.iter().map(|cell| cell.position).collect(),
+            capacity: self
+                .layers
+                .iter()
+                .map(|layer| Arc::clone(&layer.capacity))
+                .collect(),
+            demand: self
+                .layers
+                .iter()
+                .map(|layer| layer.demand.clone())
+                .collect(),
+            lengths: self.nets.iter().map(|net| self.net_length(net)).collect(),
+        }
+    }
+}
+
+impl ChipView {
+    /// Number of layers.
+    pub fn num_layers(&self) -> usize {
+        self.capacity.len()
+    }
+
+    /// Capacity of a GGrid. `point` is indexed the same way as in the input file.
+    pub fn capacity_at(&self, point: Point<usize>) -> Option<usize> {
+        let idx = Chip::grid_index(self.dim, point.flatten())?;
+        self.capacity
+            .get(point.lay().checked_sub(1)?)?
+            .get(idx)
+            .copied()
+    }
+
+    /// Demand of a GGrid. `point` is indexed the same way as in the input file.
+    pub fn demand_at(&self, point: Point<usize>) -> Option<usize> {
+        let idx = Chip::grid_index(self.dim, point.flatten())?;
+        self.demand
+            .get(point.lay().checked_sub(1)?)?
+            .get(idx)
+            .copied()
+    }
+
+    /// Sum of demand exceeding capacity over all GGrids.
+    pub fn overflow(&self) -> usize {
+        self.capacity
+            .iter()
+            .zip(self.demand.iter())
+            .flat_map(|(capacity, demand)| capacity.iter().zip(demand.iter()))
+            .map(|(&capacity, &demand)| demand.saturating_sub(capacity))
+            .fold(0, usize::saturating_add)
+    }
+
+    /// Total wirelength of all nets.
+    pub fn wirelength(&self) -> usize {
+        self.lengths.iter().sum()
+    }
+}
+
+// A view must stay shareable across threads.
+const _: fn() = || {
+    fn shareable<T: Send + Sync>() {}
+    shareable::<CapacityView<'static>>();
+};
+
+impl<'a> CapacityView<'a> {
+    /// Number of layers.
+    pub fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Routing direction of the layer with id `layer`.
+    pub fn direction(&self, layer: usize) -> Option<Direction> {
+        self.layers.get(layer).map(|layer| layer.direction)
+    }
+
+    /// Capacity of every GGrid of the layer with id `layer`, row by row.
+    pub fn layer(&self, layer: usize) -> Option<&'a [usize]> {
+        self.layers
+            .get(layer)
+            .map(|layer| layer.capacity.as_slice())
+    }
+
+    /// Capacity of a GGrid. `point` is indexed the same way as in the input file.
+    pub fn capacity_at(&self, point: Point<usize>) -> Option<usize> {
+        let Point(row, col, lay) = point;
+        let Pair(rows, cols) = self.dim;
+        if row == 0 || col == 0 || lay == 0 || row > rows || col > cols {
+            return None;
+        }
+        self.layer(lay - 1)?
+            .get((row - 1) * cols + col - 1)
+            .copied()
+    }
+
+    /// Capacity of a position summed over all layers of a direction.
+    pub fn supply_2d(&self, position: Pair<usize>, direction: Direction) -> usize {
+        (1..=self.layers.len())
+            .filter(|&lay| self.direction(lay - 1) == Some(direction))
+            .filter_map(|lay| self.capacity_at(position.with(lay)))
+            .sum()
+    }
+}
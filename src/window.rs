@@ -0,0 +1,228 @@
+use crate::{
+    chip::Chip,
+    components::{CellType, Pair},
+};
+use anyhow::Result;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Bounding box of pins by lowest and highest row and column.
+type Bounds = Option<(usize, usize, usize, usize)>;
+
+/// Grows a bounding box to cover a position.
+fn cover(bounds: Bounds, Pair(row, col): Pair<usize>) -> Bounds {
+    Some(match bounds {
+        Some((r0, r1, c0, c1)) => (r0.min(row), r1.max(row), c0.min(col), c1.max(col)),
+        None => (row, row, col, col),
+    })
+}
+
+/// Half-perimeter of a bounding box.
+fn half_perimeter(bounds: Bounds) -> usize {
+    bounds.map_or(0, |(r0, r1, c0, c1)| (r1 - r0) + (c1 - c0))
+}
+
+/// A window's placement problem: where its cells may go
+/// and the nets connecting them, with the pins outside the window as fixed bounding boxes.
+struct Window {
+    /// the movable cells in the window
+    cells: Vec<usize>,
+    /// positions every cell may take, indexed like `cells`
+    positions: Vec<Vec<Pair<usize>>>,
+    /// every net's bounding box of the pins of other cells, and its cells in the window
+    nets: Vec<(Bounds, Vec<usize>)>,
+}
+
+impl Window {
+    /// Half-perimeter wirelength of the nets with the first `assigned.len()` cells placed,
+    /// a lower bound for any placement of the rest.
+    fn cost(&self, assigned: &[Pair<usize>]) -> usize {
+        self.nets
+            .iter()
+            .map(|(bounds, cells)| {
+                let bounds = cells
+                    .iter()
+                    .filter_map(|&local| assigned.get(local))
+                    .fold(*bounds, |bounds, &position| cover(bounds, position));
+                half_perimeter(bounds)
+            })
+            .sum()
+    }
+
+    /// Extends `assigned` cell by cell, pruning partial placements
+    /// that can't beat the best so far, and keeps the best full placement.
+    fn search<F>(
+        &self,
+        assigned: &mut Vec<Pair<usize>>,
+        fits: &mut F,
+        best: &mut (usize, Option<Vec<Pair<usize>>>),
+    ) where
+        F: FnMut(&[Pair<usize>], usize, Pair<usize>) -> bool,
+    {
+        let cost = self.cost(assigned);
+        if cost >= best.0 {
+            return;
+        }
+        let next = assigned.len();
+        if next == self.cells.len() {
+            *best = (cost, Some(assigned.clone()));
+            return;
+        }
+
+        for &position in self.positions[next].iter() {
+            if !fits(assigned, next, position) {
+                continue;
+            }
+            assigned.push(position);
+            self.search(assigned, fits, best);
+            assigned.pop();
+        }
+    }
+}
+
+impl Chip {
+    /// The placement problem of the GGrids from `low` to `high`, `None` without 2 movable cells.
+    /// At most `WINDOW_MAX_CELLS` cells, the first ones, are placed, the rest stay where they are.
+    fn window(&self, low: Pair<usize>, high: Pair<usize>) -> Option<Window> {
+        use crate::consts::WINDOW_MAX_CELLS;
+
+        let (Pair(r0, c0), Pair(r1, c1)) = (low, high);
+        let inside =
+            |Pair(row, col): Pair<usize>| (r0..=r1).contains(&row) && (c0..=c1).contains(&col);
+        let cells: Vec<_> = (0..self.cells.len())
+            .filter(|&idx| {
+                let cell = &self.cells[idx];
+                matches!(cell.movable, CellType::Movable) && inside(cell.position)
+            })
+            .take(WINDOW_MAX_CELLS)
+            .collect();
+        if cells.len() < 2 {
+            return None;
+        }
+
+        let positions = cells
+            .iter()
+            .map(|&idx| {
+                (r0..=r1)
+                    .flat_map(|row| (c0..=c1).map(move |col| Pair(row, col)))
+                    .filter(|&target| {
+                        target == self.cells[idx].position || self.can_move(idx, target)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let locals: HashMap<_, _> = cells.iter().enumerate().map(|(i, &idx)| (idx, i)).collect();
+        let mut ids: Vec<_> = cells.iter().flat_map(|&idx| self.cell_nets(idx)).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        let nets = ids
+            .into_iter()
+            .map(|id| {
+                let (mut bounds, mut inside) = (None, Vec::new());
+                for &pin in self.nets[id].pins.iter() {
+                    let owner = match self.pin_owners.get(pin) {
+                        Some(&(owner, _)) => owner,
+                        None => continue,
+                    };
+                    match locals.get(&owner) {
+                        Some(&local) => inside.push(local),
+                        None => bounds = cover(bounds, self.cells[owner].position),
+                    }
+                }
+                inside.sort_unstable();
+                inside.dedup();
+                (bounds, inside)
+            })
+            .collect();
+
+        Some(Window {
+            cells,
+            positions,
+            nets,
+        })
+    }
+
+    /// Places the movable cells of the GGrids from `low` to `high` optimally
+    /// by branch and bound: every cell in turn tries every GGrid of the window it may move to,
+    /// and partial placements whose wirelength, see `Window::cost`, can't beat the best are cut.
+    /// A GGrid must have the supply for the cell's blockages and its conflicts
+    /// with the cells placed so far, see `placement_fits`.
+    /// The best placement is only made if it fits the budget and improves the `quality`,
+    /// see `speculate_moves`. Returns the number of cells moved.
+    pub fn place_window(&mut self, low: Pair<usize>, high: Pair<usize>) -> Result<usize> {
+        let window = match self.window(low, high) {
+            Some(window) => window,
+            None => return Ok(0),
+        };
+
+        let current: Vec<_> = window
+            .cells
+            .iter()
+            .map(|&idx| self.cells[idx].position)
+            .collect();
+        // Conflicts reach one GGrid left and right, so that's all of the occupants needed.
+        let (Pair(r0, c0), Pair(r1, c1)) = (low, high);
+        let mut base = self.cells_by_position();
+        base.retain(|&Pair(row, col), _| {
+            (r0..=r1).contains(&row) && (c0.saturating_sub(1)..=c1 + 1).contains(&col)
+        });
+        let mut fits = |assigned: &[Pair<usize>], local: usize, target: Pair<usize>| {
+            let mut occupants = base.clone();
+            for (&idx, (&from, &to)) in window.cells.iter().zip(current.iter().zip(assigned)) {
+                if let Some(cells) = occupants.get_mut(&from) {
+                    cells.retain(|&other| other != idx);
+                }
+                occupants.entry(to).or_default().push(idx);
+            }
+            let idx = window.cells[local];
+            target == current[local] || self.placement_fits(idx, target, &occupants)
+        };
+
+        // Only a placement strictly better than the current one is of interest.
+        let mut best = (window.cost(&current), None);
+        window.search(&mut Vec::new(), &mut fits, &mut best);
+        let placement = match best {
+            (_, Some(placement)) => placement,
+            _ => return Ok(0),
+        };
+
+        let moves: Vec<_> = window
+            .cells
+            .iter()
+            .zip(placement)
+            .filter(|&(&idx, target)| target != self.cells[idx].position)
+            .map(|(&idx, target)| (idx, target))
+            .collect();
+        if self.speculate_moves(&moves)? {
+            Ok(moves.len())
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Refines the placement window by window, see `place_window`,
+    /// sliding `WINDOW_SIZE` by `WINDOW_SIZE` GGrid windows over the chip row by row until time runs out.
+    /// Returns the number of cells moved.
+    pub fn place_windows(&mut self, start: Instant, duration: Duration) -> Result<usize> {
+        use crate::consts::WINDOW_SIZE;
+
+        let Pair(rows, cols) = self.dim;
+        let mut moved = 0;
+        for row in (1..=rows).step_by(WINDOW_SIZE) {
+            for col in (1..=cols).step_by(WINDOW_SIZE) {
+                if Self::check_time(start, duration).is_err() {
+                    return Ok(moved);
+                }
+                let high = Pair(
+                    (row + WINDOW_SIZE - 1).min(rows),
+                    (col + WINDOW_SIZE - 1).min(cols),
+                );
+                moved += self.place_window(Pair(row, col), high)?;
+            }
+        }
+        Ok(moved)
+    }
+}
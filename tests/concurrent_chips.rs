@@ -0,0 +1,93 @@
+//! Optimizes two inputs on two threads of one process, each chip with a thread pool of its own,
+//! and fails unless both end up exactly where they end up when optimized alone.
+
+use cell_move_router::{Chip, Schedule, ThreadPool};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Rounds of moving and rerouting, the same on every run.
+const ROUNDS: usize = 3;
+/// Most cells moved in a round.
+const BATCH: usize = 2;
+/// Only a safety net, the passes finish long before it on these inputs.
+const TIME_LIMIT: Duration = Duration::from_secs(60);
+
+/// A `size` by `size` chip with a cell on every GGrid of the diagonal,
+/// each cell's second pin sharing a net with the first pin of the next one, all unrouted.
+fn chain(size: usize) -> String {
+    let mut lines = vec![
+        format!("MaxCellMove {}", size / 2),
+        format!("GGridBoundaryIdx 1 1 {} {}", size, size),
+        "NumLayer 3".to_string(),
+        "Lay M1 1 H 6".to_string(),
+        "Lay M2 2 V 6".to_string(),
+        "Lay M3 3 H 6".to_string(),
+        "NumNonDefaultSupplyGGrid 0".to_string(),
+        "NumMasterCell 1".to_string(),
+        "MasterCell MC1 2 1".to_string(),
+        "Pin P1 M1".to_string(),
+        "Pin P2 M1".to_string(),
+        "Blkg B1 M2 1".to_string(),
+        "NumNeighborCellExtraDemand 1".to_string(),
+        "adjHGGrid MC1 MC1 M1 1".to_string(),
+        format!("NumCellInst {}", size),
+    ];
+    for k in 1..=size {
+        let movable = if k == 1 { "Fixed" } else { "Movable" };
+        lines.push(format!("CellInst C{} MC1 {} {} {}", k, k, k, movable));
+    }
+    lines.push(format!("NumNets {}", size - 1));
+    for k in 1..size {
+        lines.push(format!("Net N{} 2 NoCstr", k));
+        lines.push(format!("Pin C{}/P2", k));
+        lines.push(format!("Pin C{}/P1", k + 1));
+    }
+    lines.push("NumRoutes 0".to_string());
+    lines.join("\n")
+}
+
+/// Moves cells and routes nets of one input for a fixed number of rounds,
+/// returning the solution.
+fn optimize(input: &str, threads: usize) -> String {
+    let mut chip = Chip {
+        threads: ThreadPool::new(threads).unwrap(),
+        ..Chip::default()
+    };
+    chip.read_str(input).unwrap();
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        chip.move_cells_batch(start, TIME_LIMIT, &mut Schedule::default(), BATCH)
+            .unwrap();
+        chip.reroute_nets_parallel(start, TIME_LIMIT).unwrap();
+        chip.negotiate(start, TIME_LIMIT).unwrap();
+    }
+    chip.clean_up_all_routes().unwrap();
+    assert!(start.elapsed() < TIME_LIMIT, "Ran into the time limit");
+
+    chip.to_string()
+}
+
+#[test]
+fn chips_optimized_side_by_side_match_alone() {
+    let inputs = vec![(chain(6), 1), (chain(9), 2)];
+    let alone: Vec<_> = inputs
+        .iter()
+        .map(|(input, threads)| optimize(input, *threads))
+        .collect();
+
+    let handles: Vec<_> = inputs
+        .into_iter()
+        .map(|(input, threads)| thread::spawn(move || optimize(&input, threads)))
+        .collect();
+    for (k, (handle, expected)) in handles.into_iter().zip(alone).enumerate() {
+        let together = handle.join().unwrap();
+        assert_eq!(
+            together, expected,
+            "Chip {} differs when optimized alongside the other",
+            k
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Feeds mangled copies of a small input through the public entry points
+//! and fails if any of them panics instead of returning an error.
+
+use cell_move_router::{Chip, Pair, Rng, Schedule, Violation};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    time::{Duration, Instant},
+};
+
+const INPUT: &str = "MaxCellMove 1
+GGridBoundaryIdx 1 1 3 3
+NumLayer 3
+Lay M1 1 H 10
+Lay M2 2 V 8
+Lay M3 3 H 8
+NumNonDefaultSupplyGGrid 1
+2 2 2 -2
+NumMasterCell 2
+MasterCell MC1 2 1
+Pin P1 M1
+Pin P2 M1
+Blkg B1 M1 2
+MasterCell MC2 1 0
+Pin P1 M1
+NumNeighborCellExtraDemand 2
+sameGGrid MC1 MC2 M2 1
+adjHGGrid MC1 MC2 M1 2
+NumCellInst 3
+CellInst C1 MC1 1 1 Movable
+CellInst C2 MC2 3 3 Movable
+CellInst C3 MC2 1 2 Fixed
+NumNets 2
+Net N1 2 NoCstr
+Pin C1/P1
+Pin C2/P1
+Net N2 2 M2
+Pin C1/P2
+Pin C3/P1
+NumRoutes 7
+1 1 1 1 3 1 N1
+1 3 1 1 3 2 N1
+1 3 2 3 3 2 N1
+3 3 2 3 3 1 N1
+1 1 1 1 1 3 N2
+1 1 3 1 2 3 N2
+1 2 3 1 2 1 N2
+";
+
+const SEED: u64 = 1;
+const ROUNDS: usize = 150;
+const TIME_LIMIT: Duration = Duration::from_secs(5);
+
+/// Replaces, drops, duplicates or cuts off tokens of the input at random.
+fn mangle(content: &str, rng: &mut Rng) -> String {
+    let mut lines: Vec<Vec<String>> = content
+        .lines()
+        .map(|line| line.split_whitespace().map(String::from).collect())
+        .collect();
+
+    for _ in 0..=rng.below(4) {
+        let row = rng.below(lines.len());
+        let line = &mut lines[row];
+        if line.is_empty() {
+            continue;
+        }
+        let col = rng.below(line.len());
+        match rng.below(8) {
+            0 => line[col] = "0".to_string(),
+            1 => line[col] = usize::MAX.to_string(),
+            2 => line[col] = rng.below(100).to_string(),
+            3 => line[col] = format!("-{}", rng.below(100)),
+            4 => line[col] = line[col].chars().take(1).collect(),
+            5 => {
+                line.remove(col);
+            }
+            6 => {
+                let copy = line.clone();
+                lines.insert(row, copy);
+            }
+            _ => lines.truncate(row + 1),
+        }
+    }
+
+    lines
+        .iter()
+        .map(|line| line.join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Runs every entry point on one input, errors are fine.
+fn exercise(content: &str, rng: &mut Rng) {
+    let mut chip = Chip::default();
+    if chip.read_str(content).is_err() {
+        return;
+    }
+
+    let _ = chip.diagnose().to_string();
+    let _ = chip.verify();
+    let _ = chip.score().to_json();
+    let _ = chip.view().overflow();
+    let _ = chip.to_string();
+
+    // Targets on the chip, on its edges and off it.
+    let Pair(rows, cols) = chip.dim;
+    let mut targets = vec![Pair(0, 0), Pair(rows + 1, cols + 1), Pair(rows, cols)];
+    targets.extend((0..4).map(|_| Pair(rng.below(rows + 2), rng.below(cols + 2))));
+    for idx in 0..chip.cells.len() {
+        for &target in targets.iter() {
+            let _ = chip.move_rejection(idx, target);
+            if let Ok(mut txn) = chip.try_move(idx, target) {
+                let _ = txn.reroute_dirty_nets();
+                let _ = txn.rollback();
+            }
+            let _ = chip.speculate_moves(&[(idx, target)]);
+            let _ = chip.move_cell(idx, target);
+        }
+    }
+    let _ = chip.enforce_move_budget();
+
+    let start = Instant::now();
+    let _ = chip.move_cells(start, TIME_LIMIT, &mut Schedule::default());
+    let _ = chip.move_cells_force(start, TIME_LIMIT);
+    let _ = chip.move_cells_by_gain(start, TIME_LIMIT);
+    let _ = chip.move_clusters(start, TIME_LIMIT);
+    let _ = chip.move_cells_by_gravity(start, TIME_LIMIT);
+    let _ = chip.move_cells_to_medians(start, TIME_LIMIT);
+    let _ = chip.partition_cells(start, TIME_LIMIT);
+    let _ = chip.move_cells_to_tiles(2, start, TIME_LIMIT);
+    let _ = chip.relieve_hotspots(2, start, TIME_LIMIT);
+    let _ = chip.spread_conflicts();
+
+    chip.plan_coarse_routes(2);
+    let _ = chip.reroute_nets(start, TIME_LIMIT);
+    let _ = chip.negotiate(start, TIME_LIMIT);
+    let _ = chip.clean_up_all_routes();
+    let _ = chip.to_string();
+}
+
+/// Exercises every input, returning those that made an entry point panic.
+fn panicking(inputs: Vec<String>, rng: &mut Rng) -> Vec<String> {
+    inputs
+        .into_iter()
+        .filter(|input| panic::catch_unwind(AssertUnwindSafe(|| exercise(input, rng))).is_err())
+        .collect()
+}
+
+#[test]
+fn mangled_inputs_return_errors() {
+    let mut rng = Rng::new(SEED);
+    let inputs: Vec<_> = (0..ROUNDS)
+        .map(|round| match round {
+            0 => INPUT.to_string(),
+            _ => mangle(INPUT, &mut rng),
+        })
+        .collect();
+
+    let panicked = panicking(inputs, &mut rng);
+    assert!(
+        panicked.is_empty(),
+        "{} inputs made an entry point panic, the first:\n{}",
+        panicked.len(),
+        panicked[0]
+    );
+}
+
+#[test]
+fn negative_supply_is_clamped() {
+    let input = INPUT.replace("2 2 2 -2", "2 2 2 -20");
+
+    let mut chip = Chip::default();
+    chip.read_str(&input).unwrap();
+    assert_eq!(chip.layers[1].get_capacity(1, 1), Some(&0));
+    assert!(chip
+        .sanity_check()
+        .iter()
+        .any(|violation| matches!(violation, Violation::NegativeSupply { .. })));
+
+    let panicked = panicking(vec![input], &mut Rng::new(SEED));
+    assert!(
+        panicked.is_empty(),
+        "A negative supply made an entry point panic"
+    );
+}
@@ -0,0 +1,154 @@
+//! Moves cells at random and fails unless the demand `move_cell` keeps up to date incrementally
+//! matches the demand computed from scratch after every move,
+//! and unless rolling a move back restores the demand, the routes and the dirty nets.
+
+use cell_move_router::{Chip, Pair, Rng};
+
+const INPUT: &str = "MaxCellMove 12
+GGridBoundaryIdx 1 1 4 4
+NumLayer 3
+Lay M1 1 H 10
+Lay M2 2 V 8
+Lay M3 3 H 8
+NumNonDefaultSupplyGGrid 0
+NumMasterCell 3
+MasterCell MC1 2 1
+Pin P1 M1
+Pin P2 M1
+Blkg B1 M1 2
+MasterCell MC2 1 1
+Pin P1 M1
+Blkg B1 M2 1
+MasterCell MC3 1 0
+Pin P1 M3
+NumNeighborCellExtraDemand 4
+sameGGrid MC1 MC2 M2 1
+adjHGGrid MC1 MC2 M1 2
+sameGGrid MC3 MC3 M3 1
+adjHGGrid MC2 MC3 M2 1
+NumCellInst 12
+CellInst C1 MC1 1 1 Movable
+CellInst C2 MC2 1 2 Movable
+CellInst C3 MC2 2 2 Fixed
+CellInst C4 MC3 3 3 Movable
+CellInst C5 MC3 3 3 Movable
+CellInst C6 MC1 4 1 Movable
+CellInst C7 MC2 4 4 Movable
+CellInst C8 MC1 2 3 Movable
+CellInst C9 MC2 2 4 Movable
+CellInst C10 MC1 2 2 Fixed
+CellInst C11 MC2 3 2 Movable
+CellInst C12 MC1 3 4 Movable
+NumNets 3
+Net N1 2 NoCstr
+Pin C1/P1
+Pin C2/P1
+Net N2 3 NoCstr
+Pin C4/P1
+Pin C5/P1
+Pin C6/P2
+Net N3 2 NoCstr
+Pin C3/P1
+Pin C7/P1
+NumRoutes 9
+1 1 1 1 2 1 N1
+3 3 3 3 1 3 N2
+3 1 3 3 1 2 N2
+3 1 2 4 1 2 N2
+4 1 2 4 1 1 N2
+2 2 1 2 4 1 N3
+2 4 1 2 4 2 N3
+2 4 2 4 4 2 N3
+4 4 2 4 4 1 N3
+";
+
+const SEED: u64 = 1;
+const MOVES: usize = 300;
+
+/// Demand of every layer.
+fn demands(chip: &Chip) -> Vec<Vec<usize>> {
+    chip.layers
+        .iter()
+        .map(|layer| layer.demand.clone())
+        .collect()
+}
+
+#[test]
+fn moves_update_demand_like_a_full_update() {
+    let mut chip = Chip::default();
+    chip.read_str(INPUT).unwrap();
+    let Pair(rows, cols) = chip.dim;
+
+    let mut rng = Rng::new(SEED);
+    let mut moved = 0;
+    for _ in 0..MOVES {
+        let idx = rng.below(chip.cells.len());
+        let target = Pair(rng.below(rows) + 1, rng.below(cols) + 1);
+        if chip.move_cell(idx, target).is_err() {
+            continue;
+        }
+        moved += 1;
+
+        let incremental = demands(&chip);
+        chip.update_demand().unwrap();
+        assert_eq!(
+            incremental,
+            demands(&chip),
+            "Demand differs after moving cell {} to {}",
+            idx + 1,
+            target
+        );
+    }
+    assert!(moved > MOVES / 2, "Only {} moves were made", moved);
+}
+
+#[test]
+fn rolled_back_moves_restore_demand_and_routes() {
+    let mut chip = Chip::default();
+    chip.read_str(INPUT).unwrap();
+    let Pair(rows, cols) = chip.dim;
+
+    let mut rng = Rng::new(SEED);
+    let mut rolled_back = 0;
+    for _ in 0..MOVES {
+        let idx = rng.below(chip.cells.len());
+        let target = Pair(rng.below(rows) + 1, rng.below(cols) + 1);
+        let before = (demands(&chip), chip.snapshot(), chip.dirty_nets.clone());
+        let keep = rng.below(2) == 0;
+
+        let mut txn = match chip.try_move(idx, target) {
+            Ok(txn) => txn,
+            Err(_) => continue,
+        };
+        // Kept moves leave their nets dirty for the next rollbacks to restore.
+        if keep {
+            txn.commit();
+            continue;
+        }
+        txn.reroute_dirty_nets().unwrap();
+        txn.rollback().unwrap();
+        rolled_back += 1;
+
+        let after = (demands(&chip), chip.snapshot(), chip.dirty_nets.clone());
+        assert_eq!(
+            before,
+            after,
+            "Rolling back cell {} from {} left changes",
+            idx + 1,
+            target
+        );
+        chip.update_demand().unwrap();
+        assert_eq!(
+            before.0,
+            demands(&chip),
+            "Demand differs after rolling back cell {} from {}",
+            idx + 1,
+            target
+        );
+    }
+    assert!(
+        rolled_back > MOVES / 4,
+        "Only {} moves were rolled back",
+        rolled_back
+    );
+}